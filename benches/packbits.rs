@@ -0,0 +1,50 @@
+extern crate criterion;
+extern crate tiff;
+
+use criterion::{black_box, BenchmarkId, Criterion, Throughput};
+use tiff::decoder::Decoder;
+use tiff::encoder::{colortype, compression::Packbits, TiffEncoder};
+
+fn read_image(image: &[u8]) {
+    let image = std::io::Cursor::new(image);
+    let mut reader = Decoder::new(black_box(image)).unwrap();
+    reader.read_image().unwrap();
+}
+
+/// Encodes a page of mostly white with some black strokes, similar to a scanned document.
+fn document(width: u32, height: u32) -> Vec<u8> {
+    let page: Vec<u8> = (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            if (y / 8) % 4 == 0 && (x / 3) % 7 != 0 {
+                0
+            } else {
+                255
+            }
+        })
+        .collect();
+
+    let mut file = std::io::Cursor::new(Vec::new());
+    TiffEncoder::new(&mut file)
+        .unwrap()
+        .write_image_with_compression::<colortype::Gray8, _>(width, height, Packbits, &page)
+        .unwrap();
+    file.into_inner()
+}
+
+fn main() {
+    let mut c = Criterion::default().configure_from_args();
+    let mut group = c.benchmark_group("tiff-packbits");
+
+    for &(width, height) in [(256, 256), (2480, 3508)].iter() {
+        let data = document(width, height);
+        group
+            .sample_size(20)
+            .throughput(Throughput::Bytes(u64::from(width * height)))
+            .bench_with_input(
+                BenchmarkId::new("document", format!("{}x{}", width, height)),
+                &data[..],
+                |b, input| b.iter(|| read_image(input)),
+            );
+    }
+}
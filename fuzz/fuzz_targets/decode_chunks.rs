@@ -0,0 +1,48 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use tiff::decoder::{ChunkType, Decoder, DecodingMode, Limits};
+
+fn decode(data: &[u8], mode: DecodingMode, recover_chunks: bool) {
+    let mut limits = Limits::default();
+    limits.decoding_buffer_size = 1_000_000;
+    limits.ifd_value_size = 1_000_000;
+    limits.intermediate_buffer_size = 1_000_000;
+
+    let mut decoder = match Decoder::new(std::io::Cursor::new(data)) {
+        Ok(decoder) => decoder
+            .with_limits(limits)
+            .with_mode(mode)
+            .with_chunk_recovery(recover_chunks),
+        Err(_) => return,
+    };
+
+    loop {
+        let _ = decoder.dimensions();
+        let _ = decoder.colortype();
+        let _ = decoder.tags();
+        let _ = decoder.resolution();
+        let _ = decoder.read_image();
+
+        let chunks = match decoder.get_chunk_type() {
+            ChunkType::Strip => decoder.strip_count(),
+            ChunkType::Tile => decoder.tile_count(),
+        };
+        if let Ok(chunks) = chunks {
+            for chunk in 0..chunks.min(64) {
+                let _ = decoder.read_chunk(chunk);
+                let _ = decoder.read_chunk_bytes(chunk);
+            }
+        }
+
+        if !decoder.more_images() || decoder.next_image().is_err() {
+            break;
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    decode(data, DecodingMode::Lenient, false);
+    decode(data, DecodingMode::Strict, false);
+    decode(data, DecodingMode::Lenient, true);
+});
@@ -0,0 +1,81 @@
+//! Scratch buffers reused between chunks and images.
+
+/// Byte buffers that are reused for the data of every chunk instead of allocated anew.
+#[derive(Debug)]
+pub(crate) struct BufferPool {
+    buffers: Vec<Vec<u8>>,
+    /// The most bytes kept for reuse across all buffers.
+    max_bytes: usize,
+}
+
+impl BufferPool {
+    /// The most buffers kept for reuse, any more that are returned are dropped.
+    const MAX_BUFFERS: usize = 4;
+
+    /// The default of the most bytes kept for reuse, enough for a run of chunks and the input
+    /// buffer of a decompressor.
+    pub(crate) const MAX_BYTES: usize = 8 * 1024 * 1024;
+
+    /// A buffer of at least `length` bytes. Its contents are unspecified.
+    pub(crate) fn take(&mut self, length: usize) -> Vec<u8> {
+        let mut buffer = self.buffers.pop().unwrap_or_default();
+        if buffer.len() < length {
+            buffer.resize(length, 0);
+        }
+        buffer
+    }
+
+    /// Returns a buffer to be reused, unless that would keep more than the allowed bytes.
+    pub(crate) fn give(&mut self, buffer: Vec<u8>) {
+        if buffer.capacity() == 0 || self.buffers.len() >= Self::MAX_BUFFERS {
+            return;
+        }
+        let kept: usize = self.buffers.iter().map(Vec::capacity).sum();
+        if kept.saturating_add(buffer.capacity()) <= self.max_bytes {
+            self.buffers.push(buffer);
+        }
+    }
+
+    /// The most bytes kept for reuse.
+    pub(crate) fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Limits the bytes kept for reuse, dropping buffers kept beyond it.
+    pub(crate) fn set_max_bytes(&mut self, max_bytes: usize) {
+        self.max_bytes = max_bytes;
+        let buffers = std::mem::take(&mut self.buffers);
+        for buffer in buffers {
+            self.give(buffer);
+        }
+    }
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        BufferPool {
+            buffers: Vec::new(),
+            max_bytes: Self::MAX_BYTES,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_max_bytes() {
+        let mut pool = BufferPool::default();
+        pool.set_max_bytes(100);
+        pool.give(vec![0; 60]);
+        pool.give(vec![0; 60]);
+        assert_eq!(pool.buffers.len(), 1);
+
+        pool.give(vec![0; 40]);
+        assert_eq!(pool.buffers.len(), 2);
+        pool.set_max_bytes(50);
+        assert_eq!(pool.buffers.len(), 1);
+        assert_eq!(pool.take(0).capacity(), 40);
+    }
+}
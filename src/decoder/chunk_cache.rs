@@ -0,0 +1,101 @@
+//! A cache of decoded chunks for random access to the same strips or tiles.
+
+use std::collections::{BTreeMap, HashMap};
+
+/// Identifies a chunk by the offset of its image file directory and its index.
+pub(crate) type ChunkKey = (u64, u32);
+
+/// Decoded chunks, up to a total size in bytes, evicting the least recently used first.
+#[derive(Debug, Default)]
+pub(crate) struct ChunkCache {
+    capacity: usize,
+    size: usize,
+    /// Incremented on every access, to order the entries by their last use.
+    clock: u64,
+    entries: HashMap<ChunkKey, (u64, Vec<u8>)>,
+    /// The entries by their last use, the least recently used first.
+    recency: BTreeMap<u64, ChunkKey>,
+}
+
+impl ChunkCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        ChunkCache {
+            capacity,
+            ..Default::default()
+        }
+    }
+
+    /// The most bytes of decoded data kept.
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub(crate) fn get(&mut self, key: ChunkKey) -> Option<&[u8]> {
+        self.clock += 1;
+        let clock = self.clock;
+        let (last_use, data) = self.entries.get_mut(&key)?;
+        self.recency.remove(last_use);
+        self.recency.insert(clock, key);
+        *last_use = clock;
+        Some(data)
+    }
+
+    /// Stores a copy of `data`, unless it is larger than the whole cache.
+    pub(crate) fn insert(&mut self, key: ChunkKey, data: &[u8]) {
+        if data.len() > self.capacity {
+            return;
+        }
+        self.remove(key);
+        while self.size + data.len() > self.capacity {
+            let oldest = match self.recency.values().next() {
+                Some(&key) => key,
+                None => break,
+            };
+            self.remove(oldest);
+        }
+        self.clock += 1;
+        self.size += data.len();
+        self.entries.insert(key, (self.clock, data.to_vec()));
+        self.recency.insert(self.clock, key);
+    }
+
+    fn remove(&mut self, key: ChunkKey) {
+        if let Some((last_use, data)) = self.entries.remove(&key) {
+            self.recency.remove(&last_use);
+            self.size -= data.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_eviction_order() {
+        let mut cache = ChunkCache::new(30);
+        cache.insert((0, 0), &[0; 10]);
+        cache.insert((0, 1), &[1; 10]);
+        cache.insert((0, 2), &[2; 10]);
+        assert!(cache.get((0, 0)).is_some());
+
+        cache.insert((0, 3), &[3; 10]);
+        assert!(cache.get((0, 1)).is_none());
+        assert_eq!(cache.get((0, 0)), Some(&[0; 10][..]));
+        assert_eq!(cache.get((0, 2)), Some(&[2; 10][..]));
+        assert_eq!(cache.get((0, 3)), Some(&[3; 10][..]));
+
+        // Replacing an entry frees its old data first.
+        cache.insert((0, 2), &[4; 20]);
+        assert!(cache.get((0, 0)).is_none());
+        assert_eq!(cache.get((0, 3)), Some(&[3; 10][..]));
+        assert_eq!(cache.get((0, 2)), Some(&[4; 20][..]));
+        assert_eq!(cache.entries.len(), cache.recency.len());
+        assert_eq!(cache.size, 30);
+
+        // Data larger than the whole cache is not kept.
+        cache.insert((0, 5), &[5; 31]);
+        assert!(cache.get((0, 5)).is_none());
+        assert_eq!(cache.get((0, 2)), Some(&[4; 20][..]));
+    }
+}
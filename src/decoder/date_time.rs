@@ -0,0 +1,126 @@
+//! Timestamps of the `DateTime` tags.
+
+use std::fmt;
+
+use crate::tags::Tag;
+use crate::{TiffFormatError, TiffResult};
+
+/// A timestamp as stored in the `DateTime` and `DateTimeOriginal` tags.
+///
+/// The tags do not record a time zone, the timestamp is usually the local time of the device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DateTime {
+    pub year: u16,
+    /// Month of the year, from 1 to 12.
+    pub month: u8,
+    /// Day of the month, from 1 to 31.
+    pub day: u8,
+    /// Hour of the day, from 0 to 23.
+    pub hour: u8,
+    pub minute: u8,
+    /// Second of the minute, 60 being a leap second.
+    pub second: u8,
+}
+
+impl DateTime {
+    /// Parses a value of the form `YYYY:MM:DD HH:MM:SS`.
+    ///
+    /// Dashes as date separators and a `T` between date and time are accepted as well, since
+    /// some writers use them. Returns `None` if the value is malformed or out of range.
+    pub fn parse(value: &str) -> Option<DateTime> {
+        let value = value.trim_end_matches(|c: char| c == '\0' || c.is_whitespace());
+        let bytes = value.as_bytes();
+        if bytes.len() != 19 {
+            return None;
+        }
+        let separators_valid = bytes.iter().enumerate().all(|(i, &b)| match i {
+            4 | 7 => b == b':' || b == b'-',
+            10 => b == b' ' || b == b'T',
+            13 | 16 => b == b':',
+            _ => b.is_ascii_digit(),
+        });
+        if !separators_valid {
+            return None;
+        }
+
+        let number = |start: usize, end: usize| value[start..end].parse::<u16>().ok();
+        let date_time = DateTime {
+            year: number(0, 4)?,
+            month: number(5, 7)? as u8,
+            day: number(8, 10)? as u8,
+            hour: number(11, 13)? as u8,
+            minute: number(14, 16)? as u8,
+            second: number(17, 19)? as u8,
+        };
+        let in_range = (1..=12).contains(&date_time.month)
+            && (1..=31).contains(&date_time.day)
+            && date_time.hour < 24
+            && date_time.minute < 60
+            // Leave room for a leap second.
+            && date_time.second <= 60;
+        if in_range {
+            Some(date_time)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "{:04}:{:02}:{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// Parses the value of a timestamp tag.
+///
+/// Unknown timestamps, which are written as blanks or zeros, result in `Ok(None)`.
+pub(crate) fn read(value: &str, tag: Tag) -> TiffResult<Option<DateTime>> {
+    let unknown = value
+        .chars()
+        .all(|c| c == ' ' || c == ':' || c == '0' || c == '\0');
+    if unknown {
+        return Ok(None);
+    }
+    match DateTime::parse(value) {
+        Some(date_time) => Ok(Some(date_time)),
+        None => Err(TiffFormatError::InvalidTagValueType(tag).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_date_time() {
+        let expected = DateTime {
+            year: 2021,
+            month: 3,
+            day: 14,
+            hour: 15,
+            minute: 9,
+            second: 26,
+        };
+        assert_eq!(DateTime::parse("2021:03:14 15:09:26"), Some(expected));
+        assert_eq!(DateTime::parse("2021-03-14T15:09:26\0"), Some(expected));
+        assert_eq!(expected.to_string(), "2021:03:14 15:09:26");
+
+        assert_eq!(DateTime::parse("2021:03:14"), None);
+        assert_eq!(DateTime::parse("2021:13:14 15:09:26"), None);
+        assert_eq!(DateTime::parse("2021:03:14 24:09:26"), None);
+        assert_eq!(DateTime::parse("2021:03:+4 15:09:26"), None);
+    }
+
+    #[test]
+    fn read_unknown_date_time() {
+        assert_eq!(read("    :  :     :  :  ", Tag::DateTime).unwrap(), None);
+        assert_eq!(read("0000:00:00 00:00:00", Tag::DateTime).unwrap(), None);
+        assert!(read("yesterday", Tag::DateTime).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! GeoTIFF keys stored in the `GeoKeyDirectory`.
+
+use crate::tags::{GeoKey, ModelType, RasterType, Tag};
+use crate::{TiffError, TiffFormatError, TiffResult};
+
+/// The value of a GeoTIFF key.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GeoKeyValue {
+    /// Values stored in the key entry itself or in the `GeoKeyDirectory`.
+    Short(Vec<u16>),
+    /// Values stored in `GeoDoubleParams`.
+    Double(Vec<f64>),
+    /// A string stored in `GeoAsciiParams`, without its `|` terminator.
+    Ascii(String),
+}
+
+/// The decoded `GeoKeyDirectory` of an image.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GeoKeyDirectory {
+    /// Version of the key directory.
+    pub version: u16,
+    /// Revision of the key set as `(major, minor)`.
+    pub revision: (u16, u16),
+    /// The keys in the order of the directory.
+    pub keys: Vec<(GeoKey, GeoKeyValue)>,
+}
+
+impl GeoKeyDirectory {
+    /// Resolves the keys of `directory` against the values of the parameter tags.
+    pub(crate) fn parse(
+        directory: &[u16],
+        double_params: &[f64],
+        ascii_params: &str,
+    ) -> TiffResult<GeoKeyDirectory> {
+        let invalid = || {
+            TiffError::from(TiffFormatError::InvalidTagValueType(
+                Tag::GeoKeyDirectoryTag,
+            ))
+        };
+
+        let (version, revision, entries) = match directory {
+            [version, major, minor, count, rest @ ..] => {
+                let entries = rest.get(..usize::from(*count) * 4).ok_or_else(invalid)?;
+                (*version, (*major, *minor), entries)
+            }
+            _ => return Err(invalid()),
+        };
+
+        let mut keys = Vec::with_capacity(entries.len() / 4);
+        for entry in entries.chunks_exact(4) {
+            let key = GeoKey::from_u16_exhaustive(entry[0]);
+            let count = usize::from(entry[2]);
+            let offset = usize::from(entry[3]);
+            let range = offset..offset + count;
+            let value = match Tag::from_u16_exhaustive(entry[1]) {
+                Tag::Unknown(0) => GeoKeyValue::Short(vec![entry[3]]),
+                Tag::GeoKeyDirectoryTag => {
+                    GeoKeyValue::Short(directory.get(range).ok_or_else(invalid)?.to_vec())
+                }
+                Tag::GeoDoubleParamsTag => {
+                    GeoKeyValue::Double(double_params.get(range).ok_or_else(invalid)?.to_vec())
+                }
+                Tag::GeoAsciiParamsTag => {
+                    let value = ascii_params.get(range).ok_or_else(invalid)?;
+                    GeoKeyValue::Ascii(value.trim_end_matches('|').into())
+                }
+                _ => return Err(invalid()),
+            };
+            keys.push((key, value));
+        }
+
+        Ok(GeoKeyDirectory {
+            version,
+            revision,
+            keys,
+        })
+    }
+
+    /// The value of a key, if it is present.
+    pub fn get(&self, key: GeoKey) -> Option<&GeoKeyValue> {
+        self.keys
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// The model type of the coordinate system.
+    pub fn model_type(&self) -> Option<ModelType> {
+        self.get_short(GeoKey::GTModelType)
+            .map(ModelType::from_u16_exhaustive)
+    }
+
+    /// Whether raster points refer to the area or the center of a pixel.
+    pub fn raster_type(&self) -> Option<RasterType> {
+        self.get_short(GeoKey::GTRasterType)
+            .map(RasterType::from_u16_exhaustive)
+    }
+
+    /// The EPSG code of the coordinate reference system.
+    ///
+    /// This is the projected coordinate system if present, and the geographic one otherwise.
+    pub fn crs_code(&self) -> Option<u16> {
+        self.get_short(GeoKey::ProjectedCSType)
+            .or_else(|| self.get_short(GeoKey::GeographicType))
+    }
+
+    /// The general citation of the GeoTIFF, describing the georeferencing of the image.
+    pub fn citation(&self) -> Option<&str> {
+        match self.get(GeoKey::GTCitation) {
+            Some(GeoKeyValue::Ascii(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn get_short(&self, key: GeoKey) -> Option<u16> {
+        match self.get(key) {
+            Some(GeoKeyValue::Short(values)) => values.first().copied(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_parameters() {
+        let directory = [
+            1, 1, 0, 5, // header
+            1024, 0, 1, 1, // projected model
+            1026, 34737, 21, 0, // citation
+            2057, 34736, 1, 1, // semi-major axis
+            3072, 0, 1, 26711, // projected CRS
+            4096, 34735, 2, 24, // values following the keys
+            7, 8,
+        ];
+        let keys = GeoKeyDirectory::parse(
+            &directory,
+            &[298.257223563, 6378137.0],
+            "NAD27 / UTM zone 11N|WGS 84|",
+        )
+        .unwrap();
+
+        assert_eq!(keys.version, 1);
+        assert_eq!(keys.revision, (1, 0));
+        assert_eq!(keys.keys.len(), 5);
+        assert_eq!(keys.model_type(), Some(ModelType::Projected));
+        assert_eq!(keys.raster_type(), None);
+        assert_eq!(keys.crs_code(), Some(26711));
+        assert_eq!(keys.citation(), Some("NAD27 / UTM zone 11N"));
+        assert_eq!(
+            keys.get(GeoKey::GeogSemiMajorAxis),
+            Some(&GeoKeyValue::Double(vec![6378137.0]))
+        );
+        assert_eq!(
+            keys.get(GeoKey::VerticalCSType),
+            Some(&GeoKeyValue::Short(vec![7, 8]))
+        );
+    }
+
+    #[test]
+    fn reject_out_of_range_parameters() {
+        assert!(GeoKeyDirectory::parse(&[1, 1, 0], &[], "").is_err());
+        assert!(GeoKeyDirectory::parse(&[1, 1, 0, 1], &[], "").is_err());
+        assert!(GeoKeyDirectory::parse(&[1, 1, 0, 1, 2057, 34736, 1, 0], &[], "").is_err());
+        assert!(GeoKeyDirectory::parse(&[1, 1, 0, 1, 1026, 34737, 8, 0], &[], "abc|").is_err());
+        assert!(GeoKeyDirectory::parse(&[1, 1, 0, 1, 1026, 256, 1, 0], &[], "").is_err());
+    }
+}
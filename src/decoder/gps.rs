@@ -0,0 +1,85 @@
+//! Position information from the GPS directory of an image.
+
+use std::io::{Read, Seek};
+
+use crate::tags::Tag;
+use crate::{TiffFormatError, TiffResult};
+
+use super::tag_reader::TagReader;
+
+// Tags of the GPS directory, which has a numbering of its own.
+const LATITUDE_REF: u16 = 1;
+const LATITUDE: u16 = 2;
+const LONGITUDE_REF: u16 = 3;
+const LONGITUDE: u16 = 4;
+const ALTITUDE_REF: u16 = 5;
+const ALTITUDE: u16 = 6;
+
+/// The position recorded in the GPS directory, converted to decimal values.
+///
+/// Each value is `None` if the directory does not contain it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GpsInfo {
+    /// Latitude in degrees, negative south of the equator.
+    pub latitude: Option<f64>,
+    /// Longitude in degrees, negative west of the prime meridian.
+    pub longitude: Option<f64>,
+    /// Altitude in meters, negative below sea level.
+    pub altitude: Option<f64>,
+}
+
+impl GpsInfo {
+    pub(crate) fn read<R: Read + Seek>(tag_reader: &mut TagReader<R>) -> TiffResult<GpsInfo> {
+        Ok(GpsInfo {
+            latitude: read_coordinate(tag_reader, LATITUDE, LATITUDE_REF, "S")?,
+            longitude: read_coordinate(tag_reader, LONGITUDE, LONGITUDE_REF, "W")?,
+            altitude: read_altitude(tag_reader)?,
+        })
+    }
+}
+
+/// Reads degrees, minutes and seconds of a coordinate as decimal degrees.
+fn read_coordinate<R: Read + Seek>(
+    tag_reader: &mut TagReader<R>,
+    value: u16,
+    reference: u16,
+    negative: &str,
+) -> TiffResult<Option<f64>> {
+    let tag = Tag::from_u16_exhaustive(value);
+    let degrees = match tag_reader.find_tag(tag)? {
+        Some(val) => match val.into_f64_vec()?[..] {
+            [degrees, minutes, seconds] => degrees + minutes / 60.0 + seconds / 3600.0,
+            _ => return Err(TiffFormatError::InvalidTagValueType(tag).into()),
+        },
+        None => return Ok(None),
+    };
+
+    let reference = tag_reader
+        .find_tag(Tag::from_u16_exhaustive(reference))?
+        .map(|val| val.into_string())
+        .transpose()?;
+    let sign = match reference {
+        Some(ref val) if val.trim() == negative => -1.0,
+        _ => 1.0,
+    };
+    Ok(Some(sign * degrees))
+}
+
+/// Reads the altitude in meters, a reference of 1 denotes a value below sea level.
+fn read_altitude<R: Read + Seek>(tag_reader: &mut TagReader<R>) -> TiffResult<Option<f64>> {
+    let altitude = match tag_reader.find_tag(Tag::from_u16_exhaustive(ALTITUDE))? {
+        Some(val) => val.into_f64()?,
+        None => return Ok(None),
+    };
+
+    let reference = tag_reader
+        .find_tag(Tag::from_u16_exhaustive(ALTITUDE_REF))?
+        .map(|val| val.into_u8())
+        .transpose()?;
+    let sign = match reference {
+        Some(1) => -1.0,
+        _ => 1.0,
+    };
+    Ok(Some(sign * altitude))
+}
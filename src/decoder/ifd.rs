@@ -1,7 +1,7 @@
 //! Function for reading TIFF tags
 
 use std::collections::HashMap;
-use std::convert::{TryFrom, TryInto};
+use std::convert::TryFrom;
 use std::io::{self, Read, Seek};
 use std::mem;
 use std::str;
@@ -11,12 +11,13 @@ use crate::tags::{Tag, Type};
 use crate::{TiffError, TiffFormatError, TiffResult};
 
 use self::Value::{
-    Ascii, Byte, Double, Float, Ifd, IfdBig, List, Rational, RationalBig, SRational, SRationalBig,
-    Short, Signed, SignedBig, Unsigned, UnsignedBig,
+    Ascii, Byte, Bytes, Double, Float, Ifd, IfdBig, List, Rational, RationalBig, SRational,
+    SRationalBig, Short, Signed, SignedBig, Unsigned, UnsignedBig,
 };
 
 #[allow(unused_qualifications)]
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Value {
     Byte(u8),
     Short(u16),
@@ -34,6 +35,9 @@ pub enum Value {
     Ascii(String),
     Ifd(u32),
     IfdBig(u64),
+    /// The payload of a `BYTE` or `UNDEFINED` field with more than one value, such as an ICC
+    /// profile or XMP packet.
+    Bytes(Vec<u8>),
     #[doc(hidden)] // Do not match against this.
     __NonExhaustive,
 }
@@ -42,15 +46,23 @@ impl Value {
     pub fn into_u8(self) -> TiffResult<u8> {
         match self {
             Byte(val) => Ok(val),
+            Short(val) => Ok(u8::try_from(val)?),
+            Unsigned(val) => Ok(u8::try_from(val)?),
+            UnsignedBig(val) => Ok(u8::try_from(val)?),
+            Signed(val) if val >= 0 => Ok(u8::try_from(val)?),
+            SignedBig(val) if val >= 0 => Ok(u8::try_from(val)?),
             val => Err(TiffError::FormatError(TiffFormatError::ByteExpected(val))),
         }
     }
 
     pub fn into_u16(self) -> TiffResult<u16> {
         match self {
+            Byte(val) => Ok(val.into()),
             Short(val) => Ok(val),
             Unsigned(val) => Ok(u16::try_from(val)?),
             UnsignedBig(val) => Ok(u16::try_from(val)?),
+            Signed(val) if val >= 0 => Ok(u16::try_from(val)?),
+            SignedBig(val) if val >= 0 => Ok(u16::try_from(val)?),
             val => Err(TiffError::FormatError(
                 TiffFormatError::UnsignedIntegerExpected(val),
             )),
@@ -59,9 +71,12 @@ impl Value {
 
     pub fn into_u32(self) -> TiffResult<u32> {
         match self {
+            Byte(val) => Ok(val.into()),
             Short(val) => Ok(val.into()),
             Unsigned(val) => Ok(val),
             UnsignedBig(val) => Ok(u32::try_from(val)?),
+            Signed(val) if val >= 0 => Ok(u32::try_from(val)?),
+            SignedBig(val) if val >= 0 => Ok(u32::try_from(val)?),
             Ifd(val) => Ok(val),
             IfdBig(val) => Ok(u32::try_from(val)?),
             val => Err(TiffError::FormatError(
@@ -82,9 +97,12 @@ impl Value {
 
     pub fn into_u64(self) -> TiffResult<u64> {
         match self {
+            Byte(val) => Ok(val.into()),
             Short(val) => Ok(val.into()),
             Unsigned(val) => Ok(val.into()),
             UnsignedBig(val) => Ok(val),
+            Signed(val) if val >= 0 => Ok(u64::try_from(val)?),
+            SignedBig(val) if val >= 0 => Ok(u64::try_from(val)?),
             Ifd(val) => Ok(val.into()),
             IfdBig(val) => Ok(val),
             val => Err(TiffError::FormatError(
@@ -112,18 +130,56 @@ impl Value {
         }
     }
 
+    /// Converts floating point and rational values to `f64`.
+    ///
+    /// Rationals are divided out, a zero denominator results in an infinite or NaN value.
     pub fn into_f64(self) -> TiffResult<f64> {
         match self {
             Double(val) => Ok(val),
+            Float(val) => Ok(val.into()),
+            Rational(numerator, denominator) => Ok(f64::from(numerator) / f64::from(denominator)),
+            SRational(numerator, denominator) => Ok(f64::from(numerator) / f64::from(denominator)),
+            RationalBig(numerator, denominator) => Ok(numerator as f64 / denominator as f64),
+            SRationalBig(numerator, denominator) => Ok(numerator as f64 / denominator as f64),
             val => Err(TiffError::FormatError(
                 TiffFormatError::SignedIntegerExpected(val),
             )),
         }
     }
 
+    /// Numerator and denominator of a rational value.
+    pub fn into_rational(self) -> TiffResult<(u32, u32)> {
+        match self {
+            Rational(numerator, denominator) => Ok((numerator, denominator)),
+            RationalBig(numerator, denominator) => {
+                Ok((u32::try_from(numerator)?, u32::try_from(denominator)?))
+            }
+            val => Err(TiffError::FormatError(
+                TiffFormatError::UnsignedIntegerExpected(val),
+            )),
+        }
+    }
+
+    /// Numerator and denominator of a signed rational value.
+    pub fn into_srational(self) -> TiffResult<(i32, i32)> {
+        match self {
+            SRational(numerator, denominator) => Ok((numerator, denominator)),
+            SRationalBig(numerator, denominator) => {
+                Ok((i32::try_from(numerator)?, i32::try_from(denominator)?))
+            }
+            val => Err(TiffError::FormatError(
+                TiffFormatError::SignedIntegerExpected(val),
+            )),
+        }
+    }
+
+    /// The string of an ASCII value, or the first string if the field holds several.
     pub fn into_string(self) -> TiffResult<String> {
         match self {
             Ascii(val) => Ok(val),
+            List(vec) if matches!(vec.first(), Some(Ascii(_))) => {
+                vec.into_iter().next().unwrap().into_string()
+            }
             val => Err(TiffError::FormatError(
                 TiffFormatError::SignedIntegerExpected(val),
             )),
@@ -132,6 +188,10 @@ impl Value {
 
     pub fn into_u32_vec(self) -> TiffResult<Vec<u32>> {
         match self {
+            // Numeric fields stored as ASCII only ever used their first string.
+            List(vec) if matches!(vec.first(), Some(Ascii(_))) => {
+                Ok(List(vec).into_string()?.chars().map(u32::from).collect())
+            }
             List(vec) => {
                 let mut new_vec = Vec::with_capacity(vec.len());
                 for v in vec {
@@ -147,13 +207,41 @@ impl Value {
             }
             Ifd(val) => Ok(vec![val]),
             IfdBig(val) => Ok(vec![u32::try_from(val)?]),
+            Bytes(val) => Ok(val.into_iter().map(u32::from).collect()),
             Ascii(val) => Ok(val.chars().map(u32::from).collect()),
+            val @ Byte(_) | val @ Short(_) | val @ Signed(_) | val @ SignedBig(_) => {
+                Ok(vec![val.into_u32()?])
+            }
             val => Err(TiffError::FormatError(
                 TiffFormatError::UnsignedIntegerExpected(val),
             )),
         }
     }
 
+    /// Numerators and denominators of a list of rational values.
+    pub fn into_rational_vec(self) -> TiffResult<Vec<(u32, u32)>> {
+        match self {
+            List(vec) => vec.into_iter().map(Value::into_rational).collect(),
+            val => Ok(vec![val.into_rational()?]),
+        }
+    }
+
+    /// Numerators and denominators of a list of signed rational values.
+    pub fn into_srational_vec(self) -> TiffResult<Vec<(i32, i32)>> {
+        match self {
+            List(vec) => vec.into_iter().map(Value::into_srational).collect(),
+            val => Ok(vec![val.into_srational()?]),
+        }
+    }
+
+    /// All strings of an ASCII value.
+    pub fn into_string_vec(self) -> TiffResult<Vec<String>> {
+        match self {
+            List(vec) => vec.into_iter().map(Value::into_string).collect(),
+            val => Ok(vec![val.into_string()?]),
+        }
+    }
+
     pub fn into_u8_vec(self) -> TiffResult<Vec<u8>> {
         match self {
             List(vec) => {
@@ -164,7 +252,12 @@ impl Value {
                 Ok(new_vec)
             }
             Byte(val) => Ok(vec![val]),
-
+            Bytes(val) => Ok(val),
+            val @ Short(_)
+            | val @ Unsigned(_)
+            | val @ UnsignedBig(_)
+            | val @ Signed(_)
+            | val @ SignedBig(_) => Ok(vec![val.into_u8()?]),
             val => Err(TiffError::FormatError(
                 TiffFormatError::UnsignedIntegerExpected(val),
             )),
@@ -181,6 +274,12 @@ impl Value {
                 Ok(new_vec)
             }
             Short(val) => Ok(vec![val]),
+            Bytes(val) => Ok(val.into_iter().map(u16::from).collect()),
+            val @ Byte(_)
+            | val @ Unsigned(_)
+            | val @ UnsignedBig(_)
+            | val @ Signed(_)
+            | val @ SignedBig(_) => Ok(vec![val.into_u16()?]),
             val => Err(TiffError::FormatError(
                 TiffFormatError::UnsignedIntegerExpected(val),
             )),
@@ -244,6 +343,11 @@ impl Value {
                 Ok(new_vec)
             }
             Double(val) => Ok(vec![val]),
+            val @ Float(_)
+            | val @ Rational(..)
+            | val @ SRational(..)
+            | val @ RationalBig(..)
+            | val @ SRationalBig(..) => Ok(vec![val.into_f64()?]),
             val => Err(TiffError::FormatError(
                 TiffFormatError::UnsignedIntegerExpected(val),
             )),
@@ -252,6 +356,13 @@ impl Value {
 
     pub fn into_u64_vec(self) -> TiffResult<Vec<u64>> {
         match self {
+            // Numeric fields stored as ASCII only ever used their first string.
+            List(vec) if matches!(vec.first(), Some(Ascii(_))) => Ok(List(vec)
+                .into_string()?
+                .chars()
+                .map(u32::from)
+                .map(u64::from)
+                .collect()),
             List(vec) => {
                 let mut new_vec = Vec::with_capacity(vec.len());
                 for v in vec {
@@ -265,7 +376,11 @@ impl Value {
             RationalBig(numerator, denominator) => Ok(vec![numerator, denominator]),
             Ifd(val) => Ok(vec![val.into()]),
             IfdBig(val) => Ok(vec![val]),
+            Bytes(val) => Ok(val.into_iter().map(u64::from).collect()),
             Ascii(val) => Ok(val.chars().map(u32::from).map(u64::from).collect()),
+            val @ Byte(_) | val @ Short(_) | val @ Signed(_) | val @ SignedBig(_) => {
+                Ok(vec![val.into_u64()?])
+            }
             val => Err(TiffError::FormatError(
                 TiffFormatError::UnsignedIntegerExpected(val),
             )),
@@ -307,6 +422,8 @@ pub struct Entry {
     type_: Type,
     count: u64,
     offset: [u8; 8],
+    /// The value once it has been read through `cached_val`.
+    value: Option<Value>,
 }
 
 impl ::std::fmt::Debug for Entry {
@@ -320,9 +437,9 @@ impl ::std::fmt::Debug for Entry {
 
 impl Entry {
     pub fn new(type_: Type, count: u32, offset: [u8; 4]) -> Entry {
-        let mut offset = offset.to_vec();
-        offset.append(&mut vec![0; 4]);
-        Entry::new_u64(type_, count.into(), offset[..].try_into().unwrap())
+        let mut offset_u64 = [0; 8];
+        offset_u64[..4].copy_from_slice(&offset);
+        Entry::new_u64(type_, count.into(), offset_u64)
     }
 
     pub fn new_u64(type_: Type, count: u64, offset: [u8; 8]) -> Entry {
@@ -330,12 +447,106 @@ impl Entry {
             type_,
             count,
             offset,
+            value: None,
         }
     }
 
+    /// The field type of the entry.
+    pub(crate) fn field_type(&self) -> Type {
+        self.type_
+    }
+
+    /// The number of values of the entry.
+    pub(crate) fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Read the raw bytes of all values in native byte order.
+    ///
+    /// Unlike `val`, this preserves the field type exactly, e.g. to copy the entry into another
+    /// file.
+    pub(crate) fn raw_data<R: Read + Seek>(
+        &self,
+        limits: &super::Limits,
+        bigtiff: bool,
+        reader: &mut SmartReader<R>,
+    ) -> TiffResult<Vec<u8>> {
+        let mut data = self.payload(limits, bigtiff, reader)?;
+
+        // Rationals are pairs of 4 byte integers.
+        let element = match self.type_ {
+            Type::RATIONAL | Type::SRATIONAL => 4,
+            _ => type_size(self.type_) as usize,
+        };
+        let native = match reader.byte_order() {
+            ByteOrder::LittleEndian => cfg!(target_endian = "little"),
+            ByteOrder::BigEndian => cfg!(target_endian = "big"),
+        };
+        if !native && element > 1 {
+            data.chunks_exact_mut(element)
+                .for_each(|value| value.reverse());
+        }
+        Ok(data)
+    }
+
+    /// Read the bytes of all values as they are stored in the file.
+    ///
+    /// Some blobs, such as IPTC records, are commonly written with a field type that does not
+    /// match their content, this reads them regardless of the type.
+    pub(crate) fn payload<R: Read + Seek>(
+        &self,
+        limits: &super::Limits,
+        bigtiff: bool,
+        reader: &mut SmartReader<R>,
+    ) -> TiffResult<Vec<u8>> {
+        let len = match self.count.checked_mul(type_size(self.type_)) {
+            Some(len) if len <= limits.ifd_value_size as u64 => usize::try_from(len)?,
+            _ => return Err(TiffError::LimitsExceeded),
+        };
+
+        if len <= 4 || bigtiff && len <= 8 {
+            return Ok(self.offset[..len].to_vec());
+        }
+
+        let bo = reader.byte_order();
+        let offset = if bigtiff {
+            self.r(bo).read_u64()?
+        } else {
+            self.r(bo).read_u32()?.into()
+        };
+        reader.goto_offset(offset)?;
+        read_exact_bounded(reader, len)
+    }
+
     /// Returns a mem_reader for the offset/value field
-    fn r(&self, byte_order: ByteOrder) -> SmartReader<io::Cursor<Vec<u8>>> {
-        SmartReader::wrap(io::Cursor::new(self.offset.to_vec()), byte_order)
+    fn r(&self, byte_order: ByteOrder) -> SmartReader<io::Cursor<&[u8]>> {
+        SmartReader::wrap(io::Cursor::new(&self.offset[..]), byte_order)
+    }
+
+    /// Like `val`, but keeps the value so that later calls don't read the file again.
+    pub(crate) fn cached_val<R: Read + Seek>(
+        &mut self,
+        limits: &super::Limits,
+        bigtiff: bool,
+        reader: &mut SmartReader<R>,
+    ) -> TiffResult<Value> {
+        let value = self.val(limits, bigtiff, reader)?;
+        self.value = Some(value.clone());
+        Ok(value)
+    }
+
+    /// The file range of the values if they are stored outside of the entry.
+    fn value_range(&self, bigtiff: bool, bo: ByteOrder) -> Option<(u64, u64)> {
+        let length = self.count.checked_mul(type_size(self.type_))?;
+        if length <= 4 || bigtiff && length <= 8 {
+            return None;
+        }
+        let offset = if bigtiff {
+            self.r(bo).read_u64().ok()?
+        } else {
+            self.r(bo).read_u32().ok()?.into()
+        };
+        Some((offset, length))
     }
 
     pub fn val<R: Read + Seek>(
@@ -344,6 +555,10 @@ impl Entry {
         bigtiff: bool,
         reader: &mut SmartReader<R>,
     ) -> TiffResult<Value> {
+        if let Some(value) = &self.value {
+            return Ok(value.clone());
+        }
+
         // Case 1: there are no values so we can return immediately.
         if self.count == 0 {
             return Ok(List(Vec::new()));
@@ -351,22 +566,12 @@ impl Entry {
 
         let bo = reader.byte_order();
 
-        let tag_size = match self.type_ {
-            Type::BYTE | Type::SBYTE | Type::ASCII | Type::UNDEFINED => 1,
-            Type::SHORT | Type::SSHORT => 2,
-            Type::LONG | Type::SLONG | Type::FLOAT | Type::IFD => 4,
-            Type::LONG8
-            | Type::SLONG8
-            | Type::DOUBLE
-            | Type::RATIONAL
-            | Type::SRATIONAL
-            | Type::IFD8 => 8,
-            Type::__NonExhaustive => unreachable!(),
-        };
+        let tag_size = type_size(self.type_);
 
+        // The count comes from the file, so bound the size of the values before allocating them.
         let value_bytes = match self.count.checked_mul(tag_size) {
-            Some(n) => n,
-            None => {
+            Some(n) if n <= limits.ifd_value_size as u64 => n,
+            _ => {
                 return Err(TiffError::LimitsExceeded);
             }
         };
@@ -412,13 +617,7 @@ impl Entry {
                 Type::LONG => Unsigned(self.r(bo).read_u32()?),
                 Type::SLONG => Signed(self.r(bo).read_i32()?),
                 Type::FLOAT => Float(self.r(bo).read_f32()?),
-                Type::ASCII => {
-                    if self.offset[0] == 0 {
-                        Ascii("".to_string())
-                    } else {
-                        return Err(TiffError::FormatError(TiffFormatError::InvalidTag));
-                    }
-                }
+                Type::ASCII => ascii_value(&self.offset[..1])?,
                 Type::LONG8 => {
                     reader.goto_offset(self.r(bo).read_u32()?.into())?;
                     UnsignedBig(reader.read_u64()?)
@@ -451,30 +650,14 @@ impl Entry {
         // Case 3: There is more than one value, but it fits in the offset field.
         if value_bytes <= 4 || bigtiff && value_bytes <= 8 {
             match self.type_ {
-                Type::BYTE => return offset_to_bytes(self.count as usize, self),
-                Type::SBYTE => return offset_to_sbytes(self.count as usize, self),
-                Type::ASCII => {
-                    let mut buf = vec![0; self.count as usize];
-                    self.r(bo).read_exact(&mut buf)?;
-                    if buf.is_ascii() && buf.ends_with(&[0]) {
-                        let v = str::from_utf8(&buf)?;
-                        let v = v.trim_matches(char::from(0));
-                        return Ok(Ascii(v.into()));
-                    } else {
-                        return Err(TiffError::FormatError(TiffFormatError::InvalidTag));
-                    }
-                }
-                Type::UNDEFINED => {
-                    return Ok(List(
-                        self.offset[0..self.count as usize]
-                            .iter()
-                            .map(|&b| Byte(b))
-                            .collect(),
-                    ));
+                Type::BYTE | Type::UNDEFINED => {
+                    return Ok(Bytes(self.offset[..self.count as usize].to_vec()))
                 }
+                Type::SBYTE => return offset_to_sbytes(self.count as usize, self),
+                Type::ASCII => return ascii_value(&self.offset[..self.count as usize]),
                 Type::SHORT => {
                     let mut r = self.r(bo);
-                    let mut v = Vec::new();
+                    let mut v = Vec::with_capacity(self.count as usize);
                     for _ in 0..self.count {
                         v.push(Short(r.read_u16()?));
                     }
@@ -482,7 +665,7 @@ impl Entry {
                 }
                 Type::SSHORT => {
                     let mut r = self.r(bo);
-                    let mut v = Vec::new();
+                    let mut v = Vec::with_capacity(self.count as usize);
                     for _ in 0..self.count {
                         v.push(Signed(i32::from(r.read_i16()?)));
                     }
@@ -490,7 +673,7 @@ impl Entry {
                 }
                 Type::LONG => {
                     let mut r = self.r(bo);
-                    let mut v = Vec::new();
+                    let mut v = Vec::with_capacity(self.count as usize);
                     for _ in 0..self.count {
                         v.push(Unsigned(r.read_u32()?));
                     }
@@ -498,7 +681,7 @@ impl Entry {
                 }
                 Type::SLONG => {
                     let mut r = self.r(bo);
-                    let mut v = Vec::new();
+                    let mut v = Vec::with_capacity(self.count as usize);
                     for _ in 0..self.count {
                         v.push(Signed(r.read_i32()?));
                     }
@@ -506,7 +689,7 @@ impl Entry {
                 }
                 Type::FLOAT => {
                     let mut r = self.r(bo);
-                    let mut v = Vec::new();
+                    let mut v = Vec::with_capacity(self.count as usize);
                     for _ in 0..self.count {
                         v.push(Float(r.read_f32()?));
                     }
@@ -514,7 +697,7 @@ impl Entry {
                 }
                 Type::IFD => {
                     let mut r = self.r(bo);
-                    let mut v = Vec::new();
+                    let mut v = Vec::with_capacity(self.count as usize);
                     for _ in 0..self.count {
                         v.push(Ifd(r.read_u32()?));
                     }
@@ -534,13 +717,9 @@ impl Entry {
 
         // Case 4: there is more than one value, and it doesn't fit in the offset field.
         match self.type_ {
-            // TODO check if this could give wrong results
-            // at a different endianess of file/computer.
-            Type::BYTE => self.decode_offset(self.count, bo, bigtiff, limits, reader, |reader| {
-                let mut buf = [0; 1];
-                reader.read_exact(&mut buf)?;
-                Ok(UnsignedBig(u64::from(buf[0])))
-            }),
+            Type::BYTE | Type::UNDEFINED => {
+                Ok(Bytes(self.read_bytes(bo, bigtiff, limits, reader)?))
+            }
             Type::SBYTE => self.decode_offset(self.count, bo, bigtiff, limits, reader, |reader| {
                 Ok(SignedBig(i64::from(reader.read_i8()? as i8)))
             }),
@@ -584,35 +763,31 @@ impl Entry {
             Type::IFD8 => self.decode_offset(self.count, bo, bigtiff, limits, reader, |reader| {
                 Ok(IfdBig(reader.read_u64()?))
             }),
-            Type::UNDEFINED => {
-                self.decode_offset(self.count, bo, bigtiff, limits, reader, |reader| {
-                    let mut buf = [0; 1];
-                    reader.read_exact(&mut buf)?;
-                    Ok(Byte(buf[0]))
-                })
-            }
-            Type::ASCII => {
-                let n = usize::try_from(self.count)?;
-                if n > limits.decoding_buffer_size {
-                    return Err(TiffError::LimitsExceeded);
-                }
+            Type::ASCII => ascii_value(&self.read_bytes(bo, bigtiff, limits, reader)?),
+            Type::__NonExhaustive => unreachable!(),
+        }
+    }
 
-                if bigtiff {
-                    reader.goto_offset(self.r(bo).read_u64()?)?
-                } else {
-                    reader.goto_offset(self.r(bo).read_u32()?.into())?
-                }
+    /// Reads the out-of-line values of a field with single byte values.
+    fn read_bytes<R: Read + Seek>(
+        &self,
+        bo: ByteOrder,
+        bigtiff: bool,
+        limits: &super::Limits,
+        reader: &mut SmartReader<R>,
+    ) -> TiffResult<Vec<u8>> {
+        let n = usize::try_from(self.count)?;
+        if n > limits.ifd_value_size {
+            return Err(TiffError::LimitsExceeded);
+        }
 
-                let mut out = vec![0; n];
-                reader.read_exact(&mut out)?;
-                // Strings may be null-terminated, so we trim anything downstream of the null byte
-                if let Some(first) = out.iter().position(|&b| b == 0) {
-                    out.truncate(first);
-                }
-                Ok(Ascii(String::from_utf8(out)?))
-            }
-            Type::__NonExhaustive => unreachable!(),
+        if bigtiff {
+            reader.goto_offset(self.r(bo).read_u64()?)?
+        } else {
+            reader.goto_offset(self.r(bo).read_u32()?.into())?
         }
+
+        read_exact_bounded(reader, n)
     }
 
     #[inline]
@@ -650,15 +825,146 @@ impl Entry {
     }
 }
 
-/// Extracts a list of BYTE tags stored in an offset
-#[inline]
-fn offset_to_bytes(n: usize, entry: &Entry) -> TiffResult<Value> {
-    Ok(List(
-        entry.offset[0..n]
+/// Reads the values that the entries of `directory` store outside of the IFD in advance.
+///
+/// Instead of seeking to each value on its own, values are sorted by offset and read with as few
+/// large reads as the value size limit allows. Values that can't be read this way are left to be
+/// read, and their errors reported, when they are accessed.
+pub(crate) fn prefetch_values<R: Read + Seek>(
+    directory: &mut Directory,
+    limits: &super::Limits,
+    bigtiff: bool,
+    reader: &mut SmartReader<R>,
+) {
+    /// Values that are at most this far apart are read together.
+    const MAX_GAP: u64 = 4096;
+
+    let bo = reader.byte_order();
+    let mut ranges: Vec<(u64, u64, Tag)> = directory
+        .iter()
+        .filter(|(_, entry)| entry.value.is_none())
+        .filter_map(|(&tag, entry)| {
+            let (offset, length) = entry.value_range(bigtiff, bo)?;
+            let end = offset.checked_add(length)?;
+            if length > limits.ifd_value_size as u64 {
+                return None;
+            }
+            Some((offset, end, tag))
+        })
+        .collect();
+    ranges.sort_by_key(|&(offset, end, _)| (offset, end));
+
+    let mut ranges = &ranges[..];
+    while let Some(&(start, first_end, _)) = ranges.first() {
+        let mut end = first_end;
+        let run_length = ranges
             .iter()
-            .map(|&e| Unsigned(u32::from(e)))
-            .collect(),
-    ))
+            .take_while(|&&(offset, value_end, _)| {
+                let fits = offset <= end.saturating_add(MAX_GAP)
+                    && value_end.max(end) - start <= limits.ifd_value_size as u64;
+                if fits {
+                    end = end.max(value_end);
+                }
+                fits
+            })
+            .count();
+        let (run, rest) = ranges.split_at(run_length);
+        ranges = rest;
+
+        let data = match reader
+            .goto_offset(start)
+            .map_err(TiffError::from)
+            .and_then(|_| read_exact_bounded(reader, (end - start) as usize))
+        {
+            Ok(data) => data,
+            Err(_) => continue,
+        };
+        let mut run_reader = SmartReader::wrap(
+            RangeReader {
+                data: io::Cursor::new(&data[..]),
+                start,
+            },
+            bo,
+        );
+        for &(_, _, tag) in run {
+            let entry = directory.get_mut(&tag).unwrap();
+            if let Ok(value) = entry.val(limits, bigtiff, &mut run_reader) {
+                entry.value = Some(value);
+            }
+        }
+    }
+}
+
+/// A reader over a range of the file that has already been read, addressed by file offsets.
+struct RangeReader<'a> {
+    data: io::Cursor<&'a [u8]>,
+    start: u64,
+}
+
+impl Read for RangeReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.data.read(buf)
+    }
+}
+
+impl Seek for RangeReader<'_> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            io::SeekFrom::Start(offset) => match offset.checked_sub(self.start) {
+                Some(offset) => io::SeekFrom::Start(offset),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "seek before prefetched range",
+                    ))
+                }
+            },
+            pos => pos,
+        };
+        Ok(self.data.seek(pos)? + self.start)
+    }
+}
+
+/// Reads `len` bytes, growing the buffer only as data arrives.
+///
+/// The length comes from the file, so a count beyond the end of the file fails before the buffer
+/// for it is allocated.
+fn read_exact_bounded(reader: &mut impl Read, len: usize) -> TiffResult<Vec<u8>> {
+    let mut data = Vec::new();
+    reader.take(len as u64).read_to_end(&mut data)?;
+    if data.len() != len {
+        return Err(
+            io::Error::new(io::ErrorKind::UnexpectedEof, "tag value beyond end of file").into(),
+        );
+    }
+    Ok(data)
+}
+
+/// Decodes the strings of an ASCII field.
+///
+/// A field may hold several NUL-terminated strings, which are returned as a list. Bytes after
+/// the last terminator, as well as trailing strings that are not valid UTF-8, are considered
+/// padding and dropped, while a field without any terminator is read as a single string.
+fn ascii_value(bytes: &[u8]) -> TiffResult<Value> {
+    let terminated = match bytes.iter().rposition(|&b| b == 0) {
+        Some(end) => &bytes[..end],
+        None => bytes,
+    };
+    let mut strings = Vec::new();
+    for (index, part) in terminated.split(|&b| b == 0).enumerate() {
+        match str::from_utf8(part) {
+            Ok(string) => strings.push(string),
+            Err(err) if index == 0 => return Err(err.into()),
+            Err(_) => break,
+        }
+    }
+    while strings.len() > 1 && strings.last() == Some(&"") {
+        strings.pop();
+    }
+    Ok(match strings.len() {
+        1 => Ascii(strings[0].into()),
+        _ => List(strings.into_iter().map(|s| Ascii(s.into())).collect()),
+    })
 }
 
 /// Extracts a list of SBYTE tags stored in an offset
@@ -673,4 +979,21 @@ fn offset_to_sbytes(n: usize, entry: &Entry) -> TiffResult<Value> {
 }
 
 /// Type representing an Image File Directory
+/// The size in bytes of a single value of the given type.
+fn type_size(type_: Type) -> u64 {
+    match type_ {
+        Type::BYTE | Type::SBYTE | Type::ASCII | Type::UNDEFINED => 1,
+        Type::SHORT | Type::SSHORT => 2,
+        Type::LONG | Type::SLONG | Type::FLOAT | Type::IFD => 4,
+        Type::LONG8
+        | Type::SLONG8
+        | Type::DOUBLE
+        | Type::RATIONAL
+        | Type::SRATIONAL
+        | Type::IFD8 => 8,
+        Type::__NonExhaustive => unreachable!(),
+    }
+}
+
+/// The entries of an IFD by tag. Of duplicate entries in the file, only the last is kept.
 pub type Directory = HashMap<Tag, Entry>;
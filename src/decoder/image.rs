@@ -1,7 +1,8 @@
+use super::buffer_pool::BufferPool;
 use super::ifd::{Directory, Value};
 use super::stream::{
-    add_app14segment, ByteOrder, DeflateReader, JpegReader, JpegTagApp14Transform, LZWReader,
-    PackBitsReader,
+    add_app14segment, ByteOrder, ChunkInput, ChunkReader, DeflateReader, JpegReader,
+    JpegTagApp14Transform, LZWReader, PackBitsReader,
 };
 use super::tag_reader::TagReader;
 use super::{fp_predict_f32, fp_predict_f64, DecodingBuffer, Limits};
@@ -10,8 +11,81 @@ use crate::tags::{CompressionMethod, PhotometricInterpretation, Predictor, Sampl
 use crate::{ColorType, TiffError, TiffFormatError, TiffResult, TiffUnsupportedError, UsageError};
 use std::convert::{TryFrom, TryInto};
 use std::io::{self, Cursor, Read, Seek};
+use std::ops::Deref;
 use std::sync::Arc;
 
+/// Decompressors report malformed input as `InvalidData`, other errors stem from the file.
+fn decompression_error(err: io::Error) -> TiffError {
+    match err.kind() {
+        io::ErrorKind::InvalidData => {
+            TiffFormatError::CompressedDataCorrupt(err.to_string()).into()
+        }
+        _ => err.into(),
+    }
+}
+
+/// The byte counts of `strips` uncompressed strips, the last one may hold fewer rows.
+fn uncompressed_strip_bytes(
+    width: u32,
+    height: u32,
+    rows_per_strip: u32,
+    bits_per_sample: &[u8],
+    strips: usize,
+) -> Vec<u64> {
+    let bits_per_pixel: u64 = bits_per_sample.iter().map(|&bits| u64::from(bits)).sum();
+    let row_bytes = (u64::from(width) * bits_per_pixel + 7) / 8;
+    let rows_per_strip = u64::from(rows_per_strip.max(1));
+    (0..strips as u64)
+        .map(|strip| {
+            let rows = u64::from(height).saturating_sub(strip.saturating_mul(rows_per_strip));
+            row_bytes.saturating_mul(rows.min(rows_per_strip))
+        })
+        .collect()
+}
+
+fn jpeg_error(err: jpeg::Error) -> TiffError {
+    match err {
+        jpeg::Error::Io(err) => err.into(),
+        err => TiffFormatError::CompressedDataCorrupt(err.to_string()).into(),
+    }
+}
+
+/// The most samples per pixel of the images that can be decoded.
+const MAX_SAMPLES: usize = 4;
+
+/// The size of the blocks in which compressed chunk data is read.
+pub(crate) const CHUNK_INPUT_BUFFER_SIZE: u64 = 32 * 1024;
+
+/// Values given for each sample of a pixel, stored inline instead of on the heap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct SampleValues<T> {
+    values: [T; MAX_SAMPLES],
+    len: usize,
+}
+
+impl<T: Copy> SampleValues<T> {
+    /// Returns `None` if there are more than `MAX_SAMPLES` values.
+    fn new(values: &[T], filler: T) -> Option<Self> {
+        if values.len() > MAX_SAMPLES {
+            return None;
+        }
+        let mut inline = [filler; MAX_SAMPLES];
+        inline[..values.len()].copy_from_slice(values);
+        Some(SampleValues {
+            values: inline,
+            len: values.len(),
+        })
+    }
+}
+
+impl<T> Deref for SampleValues<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.values[..self.len]
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct StripDecodeState {
     pub rows_per_strip: u32,
@@ -29,10 +103,11 @@ pub(crate) struct TileAttributes {
 
 impl TileAttributes {
     pub fn tiles_across(&self) -> usize {
-        (self.image_width + self.tile_width - 1) / self.tile_width
+        self.image_width / self.tile_width + usize::from(self.image_width % self.tile_width != 0)
     }
     pub fn tiles_down(&self) -> usize {
-        (self.image_height + self.tile_length - 1) / self.tile_length
+        self.image_height / self.tile_length
+            + usize::from(self.image_height % self.tile_length != 0)
     }
     fn padding_right(&self) -> usize {
         self.tile_width - self.image_width % self.tile_width
@@ -61,16 +136,54 @@ impl TileAttributes {
     }
 }
 
+/// Checks that the number of samples per pixel fits the photometric interpretation.
+///
+/// Interpretations with a fixed number of color samples need at least as many samples. Beyond
+/// those, only the samples declared in `ExtraSamples` and an undeclared alpha channel of gray and
+/// RGB images are accepted.
+fn check_samples_per_pixel(
+    interpretation: PhotometricInterpretation,
+    samples: u8,
+    extra_samples: usize,
+) -> TiffResult<()> {
+    let (color_samples, undeclared_alpha) = match interpretation {
+        PhotometricInterpretation::WhiteIsZero | PhotometricInterpretation::BlackIsZero => (1, 1),
+        PhotometricInterpretation::RGBPalette | PhotometricInterpretation::TransparencyMask => {
+            (1, 0)
+        }
+        PhotometricInterpretation::RGB => (3, 1),
+        PhotometricInterpretation::YCbCr | PhotometricInterpretation::CIELab => (3, 0),
+        // Separated images may use other inks than CMYK, so only their minimum is checked.
+        PhotometricInterpretation::CMYK => (4, usize::MAX),
+        _ => return Ok(()),
+    };
+
+    let samples_usize = usize::from(samples);
+    let too_many =
+        samples_usize.saturating_sub(color_samples) > extra_samples.max(undeclared_alpha);
+    if samples_usize < color_samples || too_many {
+        return Err(TiffFormatError::SamplesPerPixelMismatch {
+            interpretation,
+            samples,
+        }
+        .into());
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 pub(crate) struct Image {
     pub ifd: Option<Directory>,
+    /// The byte order of the file, which applies to the image data.
+    pub byte_order: ByteOrder,
     pub width: u32,
     pub height: u32,
-    pub bits_per_sample: Vec<u8>,
+    pub bits_per_sample: SampleValues<u8>,
     #[allow(unused)]
     pub samples: u8,
-    pub sample_format: Vec<SampleFormat>,
+    pub sample_format: SampleValues<SampleFormat>,
     pub photometric_interpretation: PhotometricInterpretation,
+    pub raw_samples: bool,
     pub compression_method: CompressionMethod,
     pub predictor: Predictor,
     pub jpeg_tables: Option<Arc<Vec<u8>>>,
@@ -87,6 +200,7 @@ impl Image {
         ifd: Directory,
         limits: &Limits,
         bigtiff: bool,
+        raw_samples: bool,
     ) -> TiffResult<Image> {
         let mut tag_reader = TagReader {
             reader,
@@ -102,7 +216,7 @@ impl Image {
             .find_tag(Tag::PhotometricInterpretation)?
             .map(Value::into_u16)
             .transpose()?
-            .and_then(PhotometricInterpretation::from_u16)
+            .map(PhotometricInterpretation::from_u16_exhaustive)
             .ok_or(TiffUnsupportedError::UnknownInterpretation)?;
 
         // Try to parse both the compression method and the number, format, and bits of the included samples.
@@ -142,6 +256,12 @@ impl Image {
             .transpose()?
             .unwrap_or(1)
             .try_into()?;
+        let extra_samples = tag_reader
+            .find_tag(Tag::ExtraSamples)?
+            .map(Value::into_u64_vec)
+            .transpose()?
+            .map_or(0, |extra| extra.len());
+        check_samples_per_pixel(photometric_interpretation, samples, extra_samples)?;
 
         let sample_format = match tag_reader.find_tag_uint_vec(Tag::SampleFormat)? {
             Some(vals) => {
@@ -155,15 +275,23 @@ impl Image {
                     return Err(TiffUnsupportedError::UnsupportedSampleFormat(sample_format).into());
                 }
 
-                sample_format
+                // All values are the same, so one for each sample is enough.
+                let len = sample_format.len().min(MAX_SAMPLES);
+                SampleValues::new(&sample_format[..len], SampleFormat::Uint).unwrap()
             }
-            None => vec![SampleFormat::Uint],
+            None => SampleValues::new(&[SampleFormat::Uint], SampleFormat::Uint).unwrap(),
         };
 
-        let bits_per_sample = match samples {
-            1 | 3 | 4 => tag_reader
-                .find_tag_uint_vec(Tag::BitsPerSample)?
-                .unwrap_or_else(|| vec![1]),
+        let bits_per_sample = match usize::from(samples) {
+            samples @ 1..=MAX_SAMPLES => match tag_reader.find_tag_uint_vec(Tag::BitsPerSample)? {
+                // Some writers store a single value for all samples.
+                Some(bits) if bits.len() == 1 => {
+                    SampleValues::new(&[bits[0]; MAX_SAMPLES][..samples], 0)
+                }
+                Some(bits) => SampleValues::new(&bits, 0),
+                None => SampleValues::new(&[1], 0),
+            }
+            .ok_or(TiffFormatError::InconsistentSizesEncountered)?,
             _ => return Err(TiffUnsupportedError::UnsupportedSampleDepth(samples).into()),
         };
 
@@ -189,22 +317,40 @@ impl Image {
             ifd.contains_key(&Tag::TileByteCounts),
             ifd.contains_key(&Tag::TileOffsets),
         ) {
-            (true, true, false, false) => {
+            (_, true, false, false) => {
                 chunk_type = ChunkType::Strip;
 
                 chunk_offsets = tag_reader
                     .find_tag(Tag::StripOffsets)?
                     .unwrap()
                     .into_u64_vec()?;
-                chunk_bytes = tag_reader
-                    .find_tag(Tag::StripByteCounts)?
-                    .unwrap()
-                    .into_u64_vec()?;
+                // The default of 2**32 - 1 and other values beyond the height of the image all
+                // denote a single strip.
                 let rows_per_strip = tag_reader
                     .find_tag(Tag::RowsPerStrip)?
                     .map(Value::into_u32)
                     .transpose()?
-                    .unwrap_or(height);
+                    .unwrap_or(u32::MAX)
+                    .min(height);
+                chunk_bytes = match tag_reader.find_tag(Tag::StripByteCounts)? {
+                    Some(counts) => counts.into_u64_vec()?,
+                    // Some writers omit the byte counts of uncompressed images, which can be
+                    // derived from the dimensions as libtiff does.
+                    None if compression_method == CompressionMethod::None => {
+                        uncompressed_strip_bytes(
+                            width,
+                            height,
+                            rows_per_strip,
+                            &bits_per_sample,
+                            chunk_offsets.len(),
+                        )
+                    }
+                    None => {
+                        return Err(
+                            TiffFormatError::RequiredTagNotFound(Tag::StripByteCounts).into()
+                        )
+                    }
+                };
                 strip_decoder = Some(StripDecodeState { rows_per_strip });
                 tile_attributes = None;
 
@@ -245,9 +391,11 @@ impl Image {
                     .into_u64_vec()?;
 
                 let tile = tile_attributes.as_ref().unwrap();
-                if chunk_offsets.len() != chunk_bytes.len()
-                    || chunk_offsets.len() != tile.tiles_down() * tile.tiles_across()
-                {
+                let tiles = tile
+                    .tiles_down()
+                    .checked_mul(tile.tiles_across())
+                    .ok_or(TiffError::IntSizeError)?;
+                if chunk_offsets.len() != chunk_bytes.len() || chunk_offsets.len() != tiles {
                     return Err(TiffError::FormatError(
                         TiffFormatError::InconsistentSizesEncountered,
                     ));
@@ -260,14 +408,17 @@ impl Image {
             }
         };
 
+        let byte_order = tag_reader.reader.byte_order;
         Ok(Image {
             ifd: Some(ifd),
+            byte_order,
             width,
             height,
             bits_per_sample,
             samples,
             sample_format,
             photometric_interpretation,
+            raw_samples,
             compression_method,
             jpeg_tables,
             predictor,
@@ -279,7 +430,91 @@ impl Image {
         })
     }
 
+    /// An image without any data, only giving access to the tags of its directory.
+    pub(crate) fn without_data(ifd: Option<Directory>) -> Image {
+        Image {
+            ifd,
+            byte_order: ByteOrder::LittleEndian,
+            width: 0,
+            height: 0,
+            bits_per_sample: SampleValues::new(&[1], 0).unwrap(),
+            samples: 1,
+            sample_format: SampleValues::new(&[SampleFormat::Uint], SampleFormat::Uint).unwrap(),
+            photometric_interpretation: PhotometricInterpretation::BlackIsZero,
+            raw_samples: false,
+            compression_method: CompressionMethod::None,
+            jpeg_tables: None,
+            predictor: Predictor::None,
+            chunk_type: ChunkType::Strip,
+            strip_decoder: None,
+            tile_attributes: None,
+            chunk_offsets: Vec::new(),
+            chunk_bytes: Vec::new(),
+        }
+    }
+
     pub(crate) fn colortype(&self) -> TiffResult<ColorType> {
+        match self.interpreted_colortype() {
+            Err(TiffError::UnsupportedError(TiffUnsupportedError::UnknownInterpretation))
+            | Err(TiffError::UnsupportedError(TiffUnsupportedError::InterpretationWithBits(..)))
+                if self.raw_samples =>
+            {
+                self.multiband_colortype()
+            }
+            result => result,
+        }
+    }
+
+    /// The samples of the image without any interpretation, if they all have the same size.
+    fn multiband_colortype(&self) -> TiffResult<ColorType> {
+        match self.bits_per_sample[..] {
+            [bit_depth, ref rest @ ..] if rest.iter().all(|&bits| bits == bit_depth) => {
+                Ok(ColorType::Multiband {
+                    bit_depth,
+                    num_samples: u16::try_from(self.bits_per_sample.len())?,
+                })
+            }
+            _ => Err(TiffError::UnsupportedError(
+                TiffUnsupportedError::InterpretationWithBits(
+                    self.photometric_interpretation,
+                    self.bits_per_sample.to_vec(),
+                ),
+            )),
+        }
+    }
+
+    /// Checks that the predictor can be reversed for the samples of the image.
+    pub(crate) fn check_predictor(&self) -> TiffResult<()> {
+        if self.predictor == Predictor::None {
+            return Ok(());
+        }
+        if self.photometric_interpretation == PhotometricInterpretation::RGBPalette {
+            return Err(TiffFormatError::PaletteWithPredictor(self.predictor).into());
+        }
+
+        let color_type = self.colortype()?;
+        let floating_point = self.sample_format.first() == Some(&SampleFormat::IEEEFP);
+        let bits = &self.bits_per_sample;
+        match self.predictor {
+            Predictor::Horizontal
+                if floating_point || !bits.iter().all(|b| matches!(b, 8 | 16 | 32 | 64)) =>
+            {
+                Err(TiffError::UnsupportedError(
+                    TiffUnsupportedError::HorizontalPredictor(color_type),
+                ))
+            }
+            Predictor::FloatingPoint
+                if !floating_point || !bits.iter().all(|b| matches!(b, 32 | 64)) =>
+            {
+                Err(TiffError::UnsupportedError(
+                    TiffUnsupportedError::FloatingPointPredictor(color_type),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn interpreted_colortype(&self) -> TiffResult<ColorType> {
         match self.photometric_interpretation {
             PhotometricInterpretation::RGB => match self.bits_per_sample[..] {
                 [r, g, b] if [r, r] == [g, b] => Ok(ColorType::RGB(r)),
@@ -292,7 +527,7 @@ impl Image {
                 _ => Err(TiffError::UnsupportedError(
                     TiffUnsupportedError::InterpretationWithBits(
                         self.photometric_interpretation,
-                        self.bits_per_sample.clone(),
+                        self.bits_per_sample.to_vec(),
                     ),
                 )),
             },
@@ -301,7 +536,7 @@ impl Image {
                 _ => Err(TiffError::UnsupportedError(
                     TiffUnsupportedError::InterpretationWithBits(
                         self.photometric_interpretation,
-                        self.bits_per_sample.clone(),
+                        self.bits_per_sample.to_vec(),
                     ),
                 )),
             },
@@ -310,31 +545,52 @@ impl Image {
             {
                 Ok(ColorType::Gray(self.bits_per_sample[0]))
             }
+            PhotometricInterpretation::BlackIsZero | PhotometricInterpretation::WhiteIsZero
+                if self.bits_per_sample.len() == 2
+                    && self.bits_per_sample[0] == self.bits_per_sample[1] =>
+            {
+                Ok(ColorType::GrayA(self.bits_per_sample[0]))
+            }
+            PhotometricInterpretation::Unknown(_) => Err(TiffError::UnsupportedError(
+                TiffUnsupportedError::UnknownInterpretation,
+            )),
 
             // TODO: this is bad we should not fail at this point
             _ => Err(TiffError::UnsupportedError(
                 TiffUnsupportedError::InterpretationWithBits(
                     self.photometric_interpretation,
-                    self.bits_per_sample.clone(),
+                    self.bits_per_sample.to_vec(),
                 ),
             )),
         }
     }
 
-    fn create_reader<'r, R: 'r + Read>(
+    fn create_reader<R: Read>(
         reader: R,
         compression_method: CompressionMethod,
         compressed_length: u64,
+        max_uncompressed_length: usize,
         jpeg_tables: Option<Arc<Vec<u8>>>,
-    ) -> TiffResult<Box<dyn Read + 'r>> {
+        limits: &Limits,
+        buffers: &mut BufferPool,
+    ) -> TiffResult<ChunkReader<R>> {
+        let input_buffer_size = CHUNK_INPUT_BUFFER_SIZE.min(compressed_length) as usize;
         Ok(match compression_method {
-            CompressionMethod::None => Box::new(reader),
+            CompressionMethod::None => ChunkReader::Uncompressed(reader),
             CompressionMethod::LZW => {
-                Box::new(LZWReader::new(reader, usize::try_from(compressed_length)?))
+                let buffer = buffers.take(input_buffer_size);
+                let input = ChunkInput::new(reader, compressed_length, buffer);
+                ChunkReader::Lzw(LZWReader::new(input, max_uncompressed_length))
+            }
+            CompressionMethod::PackBits => {
+                let buffer = buffers.take(input_buffer_size);
+                let input = ChunkInput::new(reader, compressed_length, buffer);
+                ChunkReader::PackBits(PackBitsReader::new(input))
             }
-            CompressionMethod::PackBits => Box::new(PackBitsReader::new(reader, compressed_length)),
             CompressionMethod::Deflate | CompressionMethod::OldDeflate => {
-                Box::new(DeflateReader::new(reader))
+                let buffer = buffers.take(input_buffer_size);
+                let input = ChunkInput::new(reader, compressed_length, buffer);
+                ChunkReader::Deflate(DeflateReader::new(input))
             }
             CompressionMethod::ModernJPEG => {
                 if jpeg_tables.is_some() && compressed_length < 2 {
@@ -343,11 +599,24 @@ impl Image {
                     ));
                 }
 
+                if compressed_length > limits.intermediate_buffer_size as u64 {
+                    return Err(TiffError::LimitsExceeded);
+                }
+
                 let jpeg_reader = JpegReader::new(reader, compressed_length, jpeg_tables)?;
                 let mut decoder = jpeg::Decoder::new(jpeg_reader);
-                let data = decoder.decode().unwrap();
+                decoder.read_info().map_err(jpeg_error)?;
+                let info = decoder.info().unwrap();
+                let decoded_length = usize::from(info.width)
+                    .checked_mul(usize::from(info.height))
+                    .and_then(|x| x.checked_mul(info.pixel_format.pixel_bytes()))
+                    .ok_or(TiffError::LimitsExceeded)?;
+                if decoded_length > limits.intermediate_buffer_size {
+                    return Err(TiffError::LimitsExceeded);
+                }
+                let data = decoder.decode().map_err(jpeg_error)?;
 
-                Box::new(Cursor::new(data))
+                ChunkReader::Decoded(Cursor::new(data))
             }
             method => {
                 return Err(TiffError::UnsupportedError(
@@ -420,14 +689,138 @@ impl Image {
         }
     }
 
+    /// The position of the top left pixel of a chunk in the image.
+    pub(crate) fn chunk_origin(&self, chunk_index: u32) -> TiffResult<(u32, u32)> {
+        let invalid = TiffError::UsageError(UsageError::InvalidChunkIndex(chunk_index));
+        if chunk_index as usize >= self.chunk_offsets.len() {
+            return Err(invalid);
+        }
+
+        let (chunk_width, chunk_height) = self.chunk_dimensions()?;
+        let (column, row) = match self.chunk_type {
+            ChunkType::Strip => (0, chunk_index),
+            ChunkType::Tile => {
+                let tiles_across =
+                    u32::try_from(self.tile_attributes.as_ref().unwrap().tiles_across())?;
+                (chunk_index % tiles_across, chunk_index / tiles_across)
+            }
+        };
+        match (
+            column.checked_mul(chunk_width),
+            row.checked_mul(chunk_height),
+        ) {
+            (Some(x), Some(y)) if x < self.width && y < self.height => Ok((x, y)),
+            _ => Err(invalid),
+        }
+    }
+
+    /// The index of the chunk holding the pixel at `x`, `y`, if it is within the image.
+    pub(crate) fn chunk_at(&self, x: u32, y: u32) -> Option<u32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let (chunk_width, chunk_height) = self.chunk_dimensions().ok()?;
+        let chunk = match self.chunk_type {
+            ChunkType::Strip => y.checked_div(chunk_height)?,
+            ChunkType::Tile => {
+                let tiles_across =
+                    u32::try_from(self.tile_attributes.as_ref()?.tiles_across()).ok()?;
+                (y / chunk_height)
+                    .checked_mul(tiles_across)?
+                    .checked_add(x / chunk_width)?
+            }
+        };
+        if (chunk as usize) < self.chunk_offsets.len() {
+            Some(chunk)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a chunk is left out of a sparse file, with both its offset and byte count zero.
+    pub(crate) fn is_sparse_chunk(&self, chunk_index: u32) -> bool {
+        let chunk = chunk_index as usize;
+        self.chunk_offsets.get(chunk) == Some(&0) && self.chunk_bytes.get(chunk) == Some(&0)
+    }
+
+    /// The number of buffer elements taken by `pixels` pixels of a row.
+    ///
+    /// Samples of less than a byte are packed, with each row starting at a byte boundary.
+    pub(crate) fn row_samples(&self, pixels: usize) -> TiffResult<usize> {
+        let row_samples = if self.bits_per_sample.iter().all(|&bits| bits < 8) {
+            let bits_per_pixel = self
+                .bits_per_sample
+                .iter()
+                .map(|&bits| usize::from(bits))
+                .sum();
+            pixels
+                .checked_mul(bits_per_pixel)
+                .map(|bits| (bits + 7) / 8)
+        } else {
+            pixels.checked_mul(self.bits_per_sample.len())
+        };
+        row_samples.ok_or(TiffError::IntSizeError)
+    }
+
+    /// The number of bytes of an uncompressed chunk that hold its rows.
+    ///
+    /// Fails if the byte count of the chunk is smaller than that.
+    pub(crate) fn uncompressed_chunk_bytes(&self, chunk_index: u32) -> TiffResult<u64> {
+        let (_, compressed_bytes) = self.chunk_file_range(chunk_index)?;
+        let chunk_dims = self.chunk_dimensions()?;
+        let data_dims = self.chunk_data_dimensions(chunk_index)?;
+
+        let bits_per_pixel = self
+            .bits_per_sample
+            .iter()
+            .map(|&bits| u64::from(bits))
+            .sum::<u64>();
+        let required_bytes = ((u64::from(chunk_dims.0) * bits_per_pixel + 7) / 8)
+            .checked_mul(u64::from(data_dims.1))
+            .ok_or(TiffError::IntSizeError)?;
+        if compressed_bytes < required_bytes {
+            return Err(TiffFormatError::UnexpectedCompressedData {
+                actual_bytes: usize::try_from(compressed_bytes)?,
+                required_bytes: usize::try_from(required_bytes)?,
+            }
+            .into());
+        }
+        Ok(required_bytes)
+    }
+
+    /// Fills the rows of a chunk in `buffer` with `value`, e.g. for a sparse chunk or after it
+    /// failed to decode.
+    pub(crate) fn fill_chunk(
+        &self,
+        mut buffer: DecodingBuffer,
+        output_width: usize,
+        chunk_index: u32,
+        value: f64,
+    ) -> TiffResult<()> {
+        let data_dims = self.chunk_data_dimensions(chunk_index)?;
+        let row_samples = self.row_samples(output_width)?;
+        let data_row_samples = self.row_samples(data_dims.0 as usize)?;
+
+        for row in 0..data_dims.1 as usize {
+            let row_start = row.saturating_mul(row_samples).min(buffer.len());
+            let row_end = row_start.saturating_add(data_row_samples).min(buffer.len());
+            buffer.subrange(row_start..row_end).fill(value);
+        }
+        Ok(())
+    }
+
     pub(crate) fn expand_chunk(
         &self,
         reader: impl Read,
         mut buffer: DecodingBuffer,
         output_width: usize,
-        byte_order: ByteOrder,
         chunk_index: u32,
+        limits: &Limits,
+        buffers: &mut BufferPool,
     ) -> TiffResult<()> {
+        self.check_predictor()?;
+
         // Validate that the provided buffer is of the expected type.
         let color_type = self.colortype()?;
         match (color_type, &buffer) {
@@ -435,21 +828,10 @@ impl Image {
             | (ColorType::RGBA(n), _)
             | (ColorType::CMYK(n), _)
             | (ColorType::Gray(n), _)
+            | (ColorType::GrayA(n), _)
+            | (ColorType::Multiband { bit_depth: n, .. }, _)
                 if usize::from(n) == buffer.byte_len() * 8 => {}
-            (ColorType::Gray(n), DecodingBuffer::U8(_)) if n < 8 => match self.predictor {
-                Predictor::None => {}
-                Predictor::Horizontal => {
-                    return Err(TiffError::UnsupportedError(
-                        TiffUnsupportedError::HorizontalPredictor(color_type),
-                    ))
-                }
-                Predictor::FloatingPoint => {
-                    return Err(TiffError::UnsupportedError(
-                        TiffUnsupportedError::FloatingPointPredictor(color_type),
-                    ));
-                }
-                Predictor::__NonExhaustive => unreachable!(),
-            },
+            (ColorType::Gray(n), DecodingBuffer::U8(_)) if n < 8 => {}
             (type_, _) => {
                 return Err(TiffError::UnsupportedError(
                     TiffUnsupportedError::UnsupportedColorType(type_),
@@ -485,6 +867,7 @@ impl Image {
         let byte_len = buffer.byte_len();
         let compression_method = self.compression_method;
         let photometric_interpretation = self.photometric_interpretation;
+        let byte_order = self.byte_order;
         let predictor = self.predictor;
         let samples = self.bits_per_sample.len();
 
@@ -493,34 +876,83 @@ impl Image {
 
         let padding_right = chunk_dims.0 - data_dims.0;
 
+        // The dimensions come from the file, so compute the extent of the chunk in the buffer
+        // with checked arithmetic. Once it fits the buffer, all offsets below are in bounds.
+        let data_width = usize::try_from(data_dims.0)?;
+        let data_rows = usize::try_from(data_dims.1)?;
+        let data_row_samples = self.row_samples(data_width)?;
+        let output_row_samples = self.row_samples(output_width)?;
+        let padding_row_samples =
+            self.row_samples(usize::try_from(chunk_dims.0)?)? - data_row_samples;
+        let required_samples = output_row_samples
+            .checked_mul(data_rows.saturating_sub(1))
+            .and_then(|x| x.checked_add(data_row_samples))
+            .ok_or(TiffError::IntSizeError)?;
+        if data_rows > 0 && required_samples > buffer.len() {
+            return Err(TiffError::FormatError(
+                TiffFormatError::InconsistentSizesEncountered,
+            ));
+        }
+
+        // Uncompressed data is read directly, so the chunk must hold all of its rows. Extra bytes,
+        // such as padding to an even length, are ignored.
+        if compression_method == CompressionMethod::None {
+            self.uncompressed_chunk_bytes(chunk_index)?;
+        }
+
+        // Decompression never needs to produce more than the chunk including its padding.
+        let max_uncompressed_length = usize::try_from(chunk_dims.0)?
+            .checked_mul(usize::try_from(chunk_dims.1)?)
+            .and_then(|x| x.checked_mul(samples))
+            .and_then(|x| x.checked_mul(byte_len))
+            .ok_or(TiffError::IntSizeError)?;
+
         let jpeg_tables = self.jpeg_tables.clone();
-        let mut reader =
-            Self::create_reader(reader, compression_method, *compressed_bytes, jpeg_tables)?;
+        let mut reader = Self::create_reader(
+            reader,
+            compression_method,
+            *compressed_bytes,
+            max_uncompressed_length,
+            jpeg_tables,
+            limits,
+            buffers,
+        )?;
 
-        if output_width == data_dims.0 as usize && padding_right == 0 {
-            let total_samples = data_dims.0 as usize * data_dims.1 as usize * samples;
+        if output_width == data_width && padding_right == 0 {
+            let total_samples = data_row_samples * data_rows;
             let tile = &mut buffer.as_bytes_mut()[..total_samples * byte_len];
-            reader.read_exact(tile)?;
-
-            for row in 0..data_dims.1 as usize {
-                let row_start = row as usize * output_width as usize * samples;
-                let row_end = (row + 1) * output_width as usize * samples;
-                let row = buffer.subrange(row_start..row_end);
-                super::fix_endianness_and_predict(row, samples, byte_order, predictor);
-            }
+            reader.read_exact(tile).map_err(decompression_error)?;
+
+            super::fix_endianness_and_predict_rows(
+                buffer.subrange(0..total_samples),
+                data_row_samples,
+                samples,
+                byte_order,
+                predictor,
+            );
             if photometric_interpretation == PhotometricInterpretation::WhiteIsZero {
                 super::invert_colors(&mut buffer.subrange(0..total_samples), color_type);
             }
         } else if padding_right > 0 && self.predictor == Predictor::FloatingPoint {
             // The floating point predictor shuffles the padding bytes into the encoded output, so
             // this case is handled specially when needed.
-            let mut encoded = vec![0u8; chunk_dims.0 as usize * samples * byte_len];
+            let encoded_len = usize::try_from(chunk_dims.0)?
+                .checked_mul(samples)
+                .and_then(|x| x.checked_mul(byte_len))
+                .ok_or(TiffError::IntSizeError)?;
+            if encoded_len > limits.intermediate_buffer_size {
+                return Err(TiffError::LimitsExceeded);
+            }
+            let mut encoded = buffers.take(encoded_len);
+            encoded.truncate(encoded_len);
 
-            for row in 0..data_dims.1 as usize {
-                let row_start = row * output_width as usize * samples;
-                let row_end = row_start + data_dims.0 as usize * samples;
+            for row in 0..data_rows {
+                let row_start = row * output_row_samples;
+                let row_end = row_start + data_row_samples;
 
-                reader.read_exact(&mut encoded)?;
+                reader
+                    .read_exact(&mut encoded)
+                    .map_err(decompression_error)?;
                 match buffer.subrange(row_start..row_end) {
                     DecodingBuffer::F32(buf) => fp_predict_f32(&mut encoded, buf, samples),
                     DecodingBuffer::F64(buf) => fp_predict_f64(&mut encoded, buf, samples),
@@ -530,18 +962,20 @@ impl Image {
                     super::invert_colors(&mut buffer.subrange(row_start..row_end), color_type);
                 }
             }
+            buffers.give(encoded);
         } else {
-            for row in 0..data_dims.1 as usize {
-                let row_start = row * output_width as usize * samples;
-                let row_end = row_start + data_dims.0 as usize * samples;
+            for row in 0..data_rows {
+                let row_start = row * output_row_samples;
+                let row_end = row_start + data_row_samples;
 
                 let row = &mut buffer.as_bytes_mut()[(row_start * byte_len)..(row_end * byte_len)];
-                reader.read_exact(row)?;
+                reader.read_exact(row).map_err(decompression_error)?;
 
                 // Skip horizontal padding
                 if padding_right > 0 {
-                    let len = u64::try_from(padding_right as usize * samples * byte_len)?;
-                    io::copy(&mut reader.by_ref().take(len), &mut io::sink())?;
+                    let len = u64::try_from(padding_row_samples * byte_len)?;
+                    io::copy(&mut reader.by_ref().take(len), &mut io::sink())
+                        .map_err(decompression_error)?;
                 }
 
                 let mut row = buffer.subrange(row_start..row_end);
@@ -552,6 +986,9 @@ impl Image {
             }
         }
 
+        if let Some(buffer) = reader.into_buffer() {
+            buffers.give(buffer);
+        }
         Ok(())
     }
 }
@@ -0,0 +1,217 @@
+//! Metadata of images written by ImageJ.
+
+use std::convert::TryInto;
+
+use crate::tags::Tag;
+use crate::{TiffError, TiffFormatError, TiffResult};
+
+/// Metadata ImageJ stores in the `ImageDescription` and `IJMetadata` tags.
+///
+/// Stacks are written as one directory per plane, `images` being the total number of planes
+/// and `channels`, `slices` and `frames` the dimensions of a hyperstack.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImageJMetadata {
+    /// The version of ImageJ that wrote the image.
+    pub version: String,
+    /// Number of planes in the file.
+    pub images: Option<u32>,
+    pub channels: Option<u32>,
+    /// Number of z-slices.
+    pub slices: Option<u32>,
+    /// Number of time points.
+    pub frames: Option<u32>,
+    /// Whether the planes form a hyperstack of channels, slices and frames.
+    pub hyperstack: bool,
+    /// The unit of the pixel size, e.g. `micron`.
+    pub unit: Option<String>,
+    /// The distance between slices.
+    pub spacing: Option<f64>,
+    /// The display range `(min, max)` of the image.
+    pub display_range: Option<(f64, f64)>,
+    /// The display ranges of each channel.
+    pub channel_ranges: Vec<(f64, f64)>,
+    /// The labels of the planes.
+    pub labels: Vec<String>,
+    /// The info property of the image.
+    pub info: Option<String>,
+}
+
+impl ImageJMetadata {
+    /// Parses the key-value lines of an ImageJ image description.
+    ///
+    /// Returns `None` if the description was not written by ImageJ. Malformed values are
+    /// ignored.
+    pub(crate) fn from_description(description: &str) -> Option<ImageJMetadata> {
+        let mut lines = description.lines();
+        let version = lines.next()?.strip_prefix("ImageJ=")?;
+        let mut metadata = ImageJMetadata {
+            version: version.trim().into(),
+            ..ImageJMetadata::default()
+        };
+
+        let (mut min, mut max) = (None, None);
+        for line in lines {
+            let (key, value) = match line.find('=') {
+                Some(index) => (&line[..index], line[index + 1..].trim()),
+                None => continue,
+            };
+            match key {
+                "images" => metadata.images = value.parse().ok(),
+                "channels" => metadata.channels = value.parse().ok(),
+                "slices" => metadata.slices = value.parse().ok(),
+                "frames" => metadata.frames = value.parse().ok(),
+                "hyperstack" => metadata.hyperstack = value == "true",
+                "unit" => metadata.unit = Some(value.into()),
+                "spacing" => metadata.spacing = value.parse().ok(),
+                "min" => min = value.parse().ok(),
+                "max" => max = value.parse().ok(),
+                _ => {}
+            }
+        }
+        if let (Some(min), Some(max)) = (min, max) {
+            metadata.display_range = Some((min, max));
+        }
+        Some(metadata)
+    }
+
+    /// Reads the ranges, labels and info from the `IJMetadata` tag, split into blocks as given
+    /// by `IJMetadataByteCounts`.
+    pub(crate) fn read_blocks(&mut self, data: &[u8], byte_counts: &[u32]) -> TiffResult<()> {
+        let invalid = || TiffError::from(TiffFormatError::InvalidTagValueType(Tag::IJMetadata));
+
+        let mut blocks = Vec::with_capacity(byte_counts.len());
+        let mut rest = data;
+        for &count in byte_counts {
+            let count = count as usize;
+            if rest.len() < count {
+                return Err(invalid());
+            }
+            let (block, tail) = rest.split_at(count);
+            blocks.push(block);
+            rest = tail;
+        }
+
+        // The header starts with a magic number in the byte order of the data, followed by
+        // pairs of block type and count.
+        let (header, mut blocks) = match blocks.split_first() {
+            Some((header, blocks)) if header.len() >= 4 && header.len() % 8 == 4 => {
+                (*header, blocks)
+            }
+            _ => return Err(invalid()),
+        };
+        let big_endian = match &header[..4] {
+            b"IJIJ" => true,
+            b"JIJI" => false,
+            _ => return Err(invalid()),
+        };
+        let read_u32 = |bytes: &[u8]| {
+            let bytes = bytes.try_into().unwrap();
+            if big_endian {
+                u32::from_be_bytes(bytes)
+            } else {
+                u32::from_le_bytes(bytes)
+            }
+        };
+
+        for entry in header[4..].chunks_exact(8) {
+            let kind = read_u32(&entry[..4]);
+            let count = read_u32(&entry[4..]) as usize;
+            if blocks.len() < count {
+                return Err(invalid());
+            }
+            let (current, remaining) = blocks.split_at(count);
+            blocks = remaining;
+
+            match &kind.to_be_bytes() {
+                b"info" => self.info = current.first().map(|block| utf16(block, big_endian)),
+                b"labl" => {
+                    self.labels = current
+                        .iter()
+                        .map(|block| utf16(block, big_endian))
+                        .collect()
+                }
+                b"rang" => {
+                    self.channel_ranges = current
+                        .iter()
+                        .flat_map(|block| block.chunks_exact(16))
+                        .map(|range| {
+                            let value = |bytes: &[u8]| {
+                                let bytes = bytes.try_into().unwrap();
+                                if big_endian {
+                                    f64::from_be_bytes(bytes)
+                                } else {
+                                    f64::from_le_bytes(bytes)
+                                }
+                            };
+                            (value(&range[..8]), value(&range[8..]))
+                        })
+                        .collect()
+                }
+                // Lookup tables, ROIs, overlays, plots and properties are not decoded.
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decodes a UTF-16 string, replacing invalid code units.
+fn utf16(data: &[u8], big_endian: bool) -> String {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|unit| {
+            if big_endian {
+                u16::from_be_bytes([unit[0], unit[1]])
+            } else {
+                u16::from_le_bytes([unit[0], unit[1]])
+            }
+        })
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_description() {
+        let description = "ImageJ=1.53t\nimages=60\nchannels=2\nslices=5\nframes=6\n\
+                           hyperstack=true\nmode=composite\nunit=micron\nspacing=0.5\n\
+                           min=0.0\nmax=255.0\nframes=x\n";
+        let metadata = ImageJMetadata::from_description(description).unwrap();
+        assert_eq!(metadata.version, "1.53t");
+        assert_eq!(metadata.images, Some(60));
+        assert_eq!(metadata.channels, Some(2));
+        assert_eq!(metadata.slices, Some(5));
+        assert_eq!(metadata.frames, None);
+        assert!(metadata.hyperstack);
+        assert_eq!(metadata.unit.as_deref(), Some("micron"));
+        assert_eq!(metadata.spacing, Some(0.5));
+        assert_eq!(metadata.display_range, Some((0.0, 255.0)));
+
+        assert_eq!(ImageJMetadata::from_description("A microscope image"), None);
+    }
+
+    #[test]
+    fn read_metadata_blocks() {
+        let mut data = b"IJIJinfo\0\0\0\x01labl\0\0\0\x02rang\0\0\0\x01".to_vec();
+        data.extend_from_slice(b"\0h\0i");
+        data.extend_from_slice(b"\0a");
+        data.extend_from_slice(b"\0b\0c");
+        data.extend_from_slice(&0.0f64.to_be_bytes());
+        data.extend_from_slice(&4095.0f64.to_be_bytes());
+
+        let mut metadata = ImageJMetadata::default();
+        metadata.read_blocks(&data, &[28, 4, 2, 4, 16]).unwrap();
+        assert_eq!(metadata.info.as_deref(), Some("hi"));
+        assert_eq!(metadata.labels, ["a", "bc"]);
+        assert_eq!(metadata.channel_ranges, [(0.0, 4095.0)]);
+
+        assert!(metadata.read_blocks(&data, &[28, 4, 2, 4, 32]).is_err());
+        assert!(metadata
+            .read_blocks(&data[4..], &[24, 4, 2, 4, 16])
+            .is_err());
+    }
+}
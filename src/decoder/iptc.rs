@@ -0,0 +1,115 @@
+//! Datasets of the IPTC-NAA record.
+
+use crate::tags::Tag;
+use crate::{TiffError, TiffFormatError, TiffResult};
+
+/// Marker starting each dataset of an IPTC record.
+const TAG_MARKER: u8 = 0x1c;
+
+/// A dataset of an IPTC-NAA record, such as a keyword or the caption of an image.
+///
+/// Datasets are identified by their record and dataset number, e.g. keywords are `2:25` and the
+/// caption is `2:120`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IptcDataset {
+    /// The record number.
+    pub record: u8,
+    /// The dataset number within the record.
+    pub dataset: u8,
+    /// The value of the dataset, its encoding depends on the dataset.
+    pub data: Vec<u8>,
+}
+
+/// Splits an IPTC-NAA record into its datasets.
+///
+/// The record is usually stored with a field type of `LONG`, so trailing zeros padding it to
+/// a multiple of four bytes are ignored.
+pub(crate) fn parse(mut data: &[u8]) -> TiffResult<Vec<IptcDataset>> {
+    let invalid = || TiffError::from(TiffFormatError::InvalidTagValueType(Tag::Iptc));
+
+    let mut datasets = Vec::new();
+    while let Some((&marker, rest)) = data.split_first() {
+        if marker != TAG_MARKER {
+            if data.iter().all(|&b| b == 0) {
+                break;
+            }
+            return Err(invalid());
+        }
+
+        let (header, rest) = match rest {
+            [record, dataset, high, low, rest @ ..] => ([*record, *dataset, *high, *low], rest),
+            _ => return Err(invalid()),
+        };
+        let length = u16::from_be_bytes([header[2], header[3]]);
+
+        // Extended datasets store the number of length bytes instead, with the high bit set.
+        let (length, rest) = if length & 0x8000 != 0 {
+            let size = usize::from(length & 0x7fff);
+            if size > 8 || rest.len() < size {
+                return Err(invalid());
+            }
+            let length = rest[..size]
+                .iter()
+                .fold(0u64, |length, &b| length << 8 | u64::from(b));
+            (length, &rest[size..])
+        } else {
+            (u64::from(length), rest)
+        };
+
+        if length > rest.len() as u64 {
+            return Err(invalid());
+        }
+        let (value, rest) = rest.split_at(length as usize);
+        datasets.push(IptcDataset {
+            record: header[0],
+            dataset: header[1],
+            data: value.to_vec(),
+        });
+        data = rest;
+    }
+    Ok(datasets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_datasets() {
+        let data = [
+            0x1c, 2, 0, 0, 2, 0, 4, // record version
+            0x1c, 2, 25, 0, 5, b'a', b'l', b'p', b'h', b'a', // keyword
+            0x1c, 2, 120, 0x80, 2, 0, 3, b'f', b'o', b'o', // extended caption
+            0, 0, // padding
+        ];
+        assert_eq!(
+            parse(&data).unwrap(),
+            [
+                IptcDataset {
+                    record: 2,
+                    dataset: 0,
+                    data: vec![0, 4],
+                },
+                IptcDataset {
+                    record: 2,
+                    dataset: 25,
+                    data: b"alpha".to_vec(),
+                },
+                IptcDataset {
+                    record: 2,
+                    dataset: 120,
+                    data: b"foo".to_vec(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_truncated_datasets() {
+        assert!(parse(&[0x1c, 2, 25, 0, 5, b'a']).is_err());
+        assert!(parse(&[0x1c, 2, 25]).is_err());
+        assert!(parse(&[0x1c, 2, 25, 0x80, 9]).is_err());
+        assert!(parse(&[0, 1]).is_err());
+    }
+}
@@ -2,24 +2,59 @@ use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::io::{self, Read, Seek};
 use std::ops::Range;
+use std::time::{Duration, Instant};
 
 use crate::{
     bytecast, ColorType, TiffError, TiffFormatError, TiffResult, TiffUnsupportedError, UsageError,
 };
 
+use self::buffer_pool::BufferPool;
+use self::chunk_cache::ChunkCache;
 use self::ifd::Directory;
 use self::image::Image;
-use crate::tags::{
-    CompressionMethod, PhotometricInterpretation, Predictor, SampleFormat, Tag, Type,
-};
-
-use self::stream::{ByteOrder, EndianReader, SmartReader};
-
+use crate::encoder::Rational;
+use crate::tags::{CompressionMethod, Predictor, ResolutionUnit, SampleFormat, Tag, Type};
+
+use self::stream::{EndianReader, SmartReader};
+use self::tag_reader::TagReader;
+
+pub use self::date_time::DateTime;
+pub use self::geo_keys::{GeoKeyDirectory, GeoKeyValue};
+pub use self::gps::GpsInfo;
+pub use self::imagej::ImageJMetadata;
+pub use self::iptc::IptcDataset;
+pub use self::ome::{OmeMetadata, OmePixels};
+pub use self::photoshop::PhotoshopResource;
+pub use self::progress::Progress;
+pub use self::read_at::{PositionedReader, ReadAt};
+pub use self::stats::{ChunkStatistics, DecodingStatistics};
+pub use self::stream::ByteOrder;
+
+mod buffer_pool;
+mod chunk_cache;
+mod date_time;
+mod geo_keys;
+mod gps;
 pub mod ifd;
 mod image;
+mod imagej;
+mod iptc;
+mod ome;
+mod photoshop;
+mod progress;
+mod read_at;
+#[cfg(feature = "readahead")]
+mod readahead;
+mod stats;
 mod stream;
 mod tag_reader;
 
+/// The size of the largest single read of consecutive chunks.
+const MAX_RUN_BYTES: u64 = 4 * 1024 * 1024;
+
+/// An upper bound on the memory used by the state of the LZW and Deflate decompressors.
+const DECOMPRESSOR_STATE_BYTES: u64 = 64 * 1024;
+
 /// Result of a decoding process
 #[derive(Debug)]
 pub enum DecodingResult {
@@ -182,6 +217,21 @@ impl<'a> DecodingBuffer<'a> {
         }
     }
 
+    fn len(&self) -> usize {
+        match *self {
+            DecodingBuffer::U8(ref buf) => buf.len(),
+            DecodingBuffer::U16(ref buf) => buf.len(),
+            DecodingBuffer::U32(ref buf) => buf.len(),
+            DecodingBuffer::U64(ref buf) => buf.len(),
+            DecodingBuffer::F32(ref buf) => buf.len(),
+            DecodingBuffer::F64(ref buf) => buf.len(),
+            DecodingBuffer::I8(ref buf) => buf.len(),
+            DecodingBuffer::I16(ref buf) => buf.len(),
+            DecodingBuffer::I32(ref buf) => buf.len(),
+            DecodingBuffer::I64(ref buf) => buf.len(),
+        }
+    }
+
     fn copy<'b>(&'b mut self) -> DecodingBuffer<'b>
     where
         'a: 'b,
@@ -232,6 +282,80 @@ impl<'a> DecodingBuffer<'a> {
             DecodingBuffer::F64(buf) => bytecast::f64_as_ne_mut_bytes(buf),
         }
     }
+
+    /// Sets all samples to `value`, converted to the sample type with saturation.
+    fn fill(&mut self, value: f64) {
+        match self {
+            DecodingBuffer::U8(buf) => buf.iter_mut().for_each(|sample| *sample = value as u8),
+            DecodingBuffer::I8(buf) => buf.iter_mut().for_each(|sample| *sample = value as i8),
+            DecodingBuffer::U16(buf) => buf.iter_mut().for_each(|sample| *sample = value as u16),
+            DecodingBuffer::I16(buf) => buf.iter_mut().for_each(|sample| *sample = value as i16),
+            DecodingBuffer::U32(buf) => buf.iter_mut().for_each(|sample| *sample = value as u32),
+            DecodingBuffer::I32(buf) => buf.iter_mut().for_each(|sample| *sample = value as i32),
+            DecodingBuffer::U64(buf) => buf.iter_mut().for_each(|sample| *sample = value as u64),
+            DecodingBuffer::I64(buf) => buf.iter_mut().for_each(|sample| *sample = value as i64),
+            DecodingBuffer::F32(buf) => buf.iter_mut().for_each(|sample| *sample = value as f32),
+            DecodingBuffer::F64(buf) => buf.iter_mut().for_each(|sample| *sample = value),
+        }
+    }
+}
+
+/// The type of the samples in a `DecodingResult`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SampleType {
+    /// Unsigned bytes
+    U8,
+    /// Unsigned words
+    U16,
+    /// 32 bit unsigned ints
+    U32,
+    /// 64 bit unsigned ints
+    U64,
+    /// 32 bit IEEE floats
+    F32,
+    /// 64 bit IEEE floats
+    F64,
+    /// 8 bit signed ints
+    I8,
+    /// 16 bit signed ints
+    I16,
+    /// 32 bit signed ints
+    I32,
+    /// 64 bit signed ints
+    I64,
+}
+
+impl SampleType {
+    /// The size of a sample in bytes.
+    pub fn byte_len(self) -> usize {
+        match self {
+            SampleType::U8 | SampleType::I8 => 1,
+            SampleType::U16 | SampleType::I16 => 2,
+            SampleType::U32 | SampleType::I32 | SampleType::F32 => 4,
+            SampleType::U64 | SampleType::I64 | SampleType::F64 => 8,
+        }
+    }
+}
+
+/// The memory needed to decode the current image, see `Decoder::estimate`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodingEstimate {
+    /// The type of the samples of the decoded image.
+    pub sample_type: SampleType,
+    /// The number of samples in the `DecodingResult` of `read_image`.
+    pub samples: u64,
+    /// The size of the `DecodingResult` of `read_image` in bytes.
+    pub buffer_bytes: u64,
+    /// The size of the largest `DecodingResult` of `read_chunk` in bytes.
+    pub chunk_buffer_bytes: u64,
+    /// An upper bound on the memory `read_image` or `read_image_pipelined` use besides their
+    /// result and the memory counted in `retained_bytes`, in bytes.
+    pub intermediate_bytes: u64,
+    /// An upper bound on the memory the decoder keeps between decodes, in bytes.
+    ///
+    /// This is the scratch buffers that are reused across chunks and images, and the chunk cache
+    /// if it is enabled. It doesn't depend on the image.
+    pub retained_bytes: u64,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -241,6 +365,113 @@ pub enum ChunkType {
     Tile,
 }
 
+/// The kind of image in a directory, from its `NewSubfileType` or `SubfileType` tag.
+///
+/// The flags can be combined, e.g. a reduced-resolution transparency mask accompanies the
+/// overviews of a masked image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubfileType {
+    /// A reduced-resolution version of another image, such as an overview or a thumbnail.
+    pub reduced_resolution: bool,
+    /// A single page of a multi-page document.
+    pub page: bool,
+    /// A transparency mask for another image.
+    pub transparency_mask: bool,
+}
+
+impl SubfileType {
+    /// Interprets the bits of a `NewSubfileType` value.
+    pub fn from_new_subfile_type(value: u32) -> SubfileType {
+        SubfileType {
+            reduced_resolution: value & 1 != 0,
+            page: value & 2 != 0,
+            transparency_mask: value & 4 != 0,
+        }
+    }
+
+    /// Interprets a value of the deprecated `SubfileType` tag.
+    pub fn from_subfile_type(value: u16) -> SubfileType {
+        SubfileType {
+            reduced_resolution: value == 2,
+            page: value == 3,
+            transparency_mask: false,
+        }
+    }
+
+    /// Whether this is a full-resolution image rather than an overview or a mask.
+    pub fn is_full_resolution(&self) -> bool {
+        !self.reduced_resolution && !self.transparency_mask
+    }
+}
+
+/// How the decoder treats files that deviate from the specification.
+///
+/// Many writers get details of the format wrong while their intent is still clear. Lenient
+/// decoding, the default, accepts such files, while strict decoding rejects them when the image
+/// data or its resolution is read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodingMode {
+    /// Accept common writer bugs: baseline tags stored with an unexpected field type, a single
+    /// `BitsPerSample` value for all samples, and missing `YResolution` or `ResolutionUnit` tags.
+    Lenient,
+    /// Reject files with any of the deviations accepted in lenient mode. Tiles whose size is not
+    /// a multiple of 16 are still decoded, but reported as a `DecodingWarning`.
+    Strict,
+}
+
+impl Default for DecodingMode {
+    fn default() -> DecodingMode {
+        DecodingMode::Lenient
+    }
+}
+
+/// A problem in the current image that the decoder recovered from.
+#[derive(Debug)]
+pub enum DecodingWarning {
+    /// A strip or tile could not be decoded and was filled with zeros instead.
+    CorruptChunk {
+        /// The index of the chunk.
+        chunk: u32,
+        /// The error that occurred while decoding it.
+        error: TiffError,
+    },
+    /// The tiles of the image are not a multiple of 16 pixels wide and long, as the specification
+    /// requires. Only reported in strict mode, the image is decoded regardless.
+    TileSize {
+        /// The width of the tiles.
+        width: u32,
+        /// The length of the tiles.
+        length: u32,
+    },
+}
+
+/// Field types the specification allows for the tags describing the image data.
+const BASELINE_FIELD_TYPES: &[(Tag, &[Type])] = &[
+    (Tag::ImageWidth, &[Type::SHORT, Type::LONG]),
+    (Tag::ImageLength, &[Type::SHORT, Type::LONG]),
+    (Tag::BitsPerSample, &[Type::SHORT]),
+    (Tag::Compression, &[Type::SHORT]),
+    (Tag::PhotometricInterpretation, &[Type::SHORT]),
+    (Tag::StripOffsets, &[Type::SHORT, Type::LONG, Type::LONG8]),
+    (Tag::SamplesPerPixel, &[Type::SHORT]),
+    (Tag::RowsPerStrip, &[Type::SHORT, Type::LONG]),
+    (
+        Tag::StripByteCounts,
+        &[Type::SHORT, Type::LONG, Type::LONG8],
+    ),
+    (Tag::XResolution, &[Type::RATIONAL]),
+    (Tag::YResolution, &[Type::RATIONAL]),
+    (Tag::PlanarConfiguration, &[Type::SHORT]),
+    (Tag::ResolutionUnit, &[Type::SHORT]),
+    (Tag::Predictor, &[Type::SHORT]),
+    (Tag::TileWidth, &[Type::SHORT, Type::LONG]),
+    (Tag::TileLength, &[Type::SHORT, Type::LONG]),
+    (Tag::TileOffsets, &[Type::LONG, Type::LONG8]),
+    (Tag::TileByteCounts, &[Type::SHORT, Type::LONG, Type::LONG8]),
+    (Tag::SampleFormat, &[Type::SHORT]),
+];
+
 /// Decoding limits
 #[derive(Clone, Debug)]
 pub struct Limits {
@@ -255,6 +486,11 @@ pub struct Limits {
     /// Maximum size for intermediate buffer which may be used to limit the amount of data read per
     /// segment even if the entire image is decoded at once.
     pub intermediate_buffer_size: usize,
+    /// The maximum number of entries in any ifd, the default is 4096.
+    pub ifd_entries: usize,
+    /// The maximum number of ifds followed in the chain of images, the
+    /// default is 65536.
+    pub ifd_count: usize,
     /// The purpose of this is to prevent all the fields of the struct from
     /// being public, as this would make adding new fields a major version
     /// bump.
@@ -274,6 +510,8 @@ impl Limits {
             decoding_buffer_size: usize::max_value(),
             ifd_value_size: usize::max_value(),
             intermediate_buffer_size: usize::max_value(),
+            ifd_entries: usize::max_value(),
+            ifd_count: usize::max_value(),
             _non_exhaustive: (),
         }
     }
@@ -285,11 +523,17 @@ impl Default for Limits {
             decoding_buffer_size: 256 * 1024 * 1024,
             intermediate_buffer_size: 128 * 1024 * 1024,
             ifd_value_size: 1024 * 1024,
+            ifd_entries: 4096,
+            ifd_count: 65536,
             _non_exhaustive: (),
         }
     }
 }
 
+/// An entry of an IFD with its tag, field type, number of values and the values in native byte
+/// order.
+pub(crate) type RawEntry = (Tag, Type, u64, Vec<u8>);
+
 /// The representation of a TIFF decoder
 ///
 /// Currently does not support decoding of interlaced images
@@ -301,7 +545,26 @@ where
     reader: SmartReader<R>,
     bigtiff: bool,
     limits: Limits,
+    mode: DecodingMode,
+    recover_chunks: bool,
+    raw_samples: bool,
+    relaxed: bool,
+    prefetch_tags: bool,
+    progress: Option<progress::ProgressCallback>,
+    chunk_cache: Option<ChunkCache>,
+    /// Counters not kept by the reader, such as positioned reads and the decoded chunks.
+    statistics: DecodingStatistics,
+    record_chunks: bool,
+    #[cfg(feature = "readahead")]
+    readahead: Option<readahead::Readahead>,
+    /// Scratch buffers for the data of chunks, reused across chunks and images.
+    buffers: BufferPool,
+    warnings: Vec<DecodingWarning>,
     current_chunk: u32,
+    /// The length of the file, looked up once per image when its chunks are first read.
+    file_length: Option<u64>,
+    /// The offset of the image file directory of the current image.
+    ifd_offset: u64,
     next_ifd: Option<u64>,
     ifd_offsets: Vec<u64>,
     seen_ifds: HashSet<u64>,
@@ -360,9 +623,43 @@ impl Wrapping for i64 {
     }
 }
 
+/// Reverses the horizontal predictor for pixels of `$samples` samples.
+///
+/// The previous pixel is kept in an array of fixed size, so the samples of a pixel are added
+/// independently of each other and without bounds checks, which the compiler can vectorize.
+macro_rules! rev_hpredict_fixed {
+    ($image:expr, $samples:expr) => {{
+        let image = $image;
+        if image.len() >= $samples {
+            let (first, rest) = image.split_at_mut($samples);
+            let mut previous = [first[0]; $samples];
+            previous.copy_from_slice(first);
+
+            let mut pixels = rest.chunks_exact_mut($samples);
+            for pixel in &mut pixels {
+                for (sample, previous) in pixel.iter_mut().zip(previous.iter_mut()) {
+                    *previous = sample.wrapping_add(*previous);
+                    *sample = *previous;
+                }
+            }
+            for (sample, previous) in pixels.into_remainder().iter_mut().zip(previous.iter()) {
+                *sample = sample.wrapping_add(*previous);
+            }
+        }
+    }};
+}
+
 fn rev_hpredict_nsamp<T: Copy + Wrapping>(image: &mut [T], samples: usize) {
-    for col in samples..image.len() {
-        image[col] = image[col].wrapping_add(image[col - samples]);
+    match samples {
+        1 => rev_hpredict_fixed!(image, 1),
+        2 => rev_hpredict_fixed!(image, 2),
+        3 => rev_hpredict_fixed!(image, 3),
+        4 => rev_hpredict_fixed!(image, 4),
+        _ => {
+            for col in samples..image.len() {
+                image[col] = image[col].wrapping_add(image[col - samples]);
+            }
+        }
     }
 }
 
@@ -434,46 +731,111 @@ fn fix_endianness_and_predict(
     }
 }
 
-fn invert_colors_unsigned<T>(buffer: &mut [T], max: T)
-where
-    T: std::ops::Sub<T> + std::ops::Sub<Output = T> + Copy,
-{
-    for datum in buffer.iter_mut() {
-        *datum = max - *datum
+/// Fixes the endianness and reverses the predictor of each row of `row_samples` samples, in
+/// parallel if the `rayon` feature is enabled.
+fn fix_endianness_and_predict_rows(
+    mut image: DecodingBuffer,
+    row_samples: usize,
+    samples: usize,
+    byte_order: ByteOrder,
+    predictor: Predictor,
+) {
+    // Without a predictor only the byte order is fixed, which is not worth splitting into rows.
+    if predictor == Predictor::None || row_samples == 0 {
+        fix_endianness(&mut image, byte_order);
+        return;
+    }
+
+    macro_rules! predict_rows {
+        ($buf:expr, $variant:ident) => {{
+            let predict_row = |row| {
+                fix_endianness_and_predict(
+                    DecodingBuffer::$variant(row),
+                    samples,
+                    byte_order,
+                    predictor,
+                )
+            };
+            #[cfg(feature = "rayon")]
+            {
+                use rayon::prelude::*;
+                $buf.par_chunks_mut(row_samples).for_each(predict_row)
+            }
+            #[cfg(not(feature = "rayon"))]
+            {
+                $buf.chunks_mut(row_samples).for_each(predict_row)
+            }
+        }};
+    }
+
+    match image {
+        DecodingBuffer::U8(buf) => predict_rows!(buf, U8),
+        DecodingBuffer::U16(buf) => predict_rows!(buf, U16),
+        DecodingBuffer::U32(buf) => predict_rows!(buf, U32),
+        DecodingBuffer::U64(buf) => predict_rows!(buf, U64),
+        DecodingBuffer::F32(buf) => predict_rows!(buf, F32),
+        DecodingBuffer::F64(buf) => predict_rows!(buf, F64),
+        DecodingBuffer::I8(buf) => predict_rows!(buf, I8),
+        DecodingBuffer::I16(buf) => predict_rows!(buf, I16),
+        DecodingBuffer::I32(buf) => predict_rows!(buf, I32),
+        DecodingBuffer::I64(buf) => predict_rows!(buf, I64),
     }
 }
 
-fn invert_colors_fp<T>(buffer: &mut [T], max: T)
-where
-    T: std::ops::Sub<T> + std::ops::Sub<Output = T> + Copy,
-{
-    for datum in buffer.iter_mut() {
-        // FIXME: assumes [0, 1) range for floats
-        *datum = max - *datum
+fn invert_samples<T: Copy>(buffer: &mut [T], step: usize, invert: impl Fn(T) -> T) {
+    match step {
+        1 => buffer.iter_mut().for_each(|datum| *datum = invert(*datum)),
+        _ => buffer
+            .chunks_mut(step)
+            .for_each(|pixel| pixel[0] = invert(pixel[0])),
     }
 }
 
-fn invert_colors(buf: &mut DecodingBuffer, color_type: ColorType) {
-    match (color_type, buf) {
-        (ColorType::Gray(64), DecodingBuffer::U64(ref mut buffer)) => {
-            invert_colors_unsigned(buffer, 0xffff_ffff_ffff_ffff);
-        }
-        (ColorType::Gray(32), DecodingBuffer::U32(ref mut buffer)) => {
-            invert_colors_unsigned(buffer, 0xffff_ffff);
-        }
-        (ColorType::Gray(16), DecodingBuffer::U16(ref mut buffer)) => {
-            invert_colors_unsigned(buffer, 0xffff);
-        }
-        (ColorType::Gray(n), DecodingBuffer::U8(ref mut buffer)) if n <= 8 => {
-            invert_colors_unsigned(buffer, 0xff);
+/// Complements the first of every `step` integer samples, given as bytes.
+///
+/// The bytes are XORed with a mask in blocks of a fixed size, which the compiler vectorizes.
+fn invert_integer_samples(bytes: &mut [u8], sample_bytes: usize, step: usize) {
+    // A multiple of the size of any pixel with up to two samples of up to eight bytes.
+    const BLOCK: usize = 64;
+
+    let pixel_bytes = sample_bytes * step;
+    let mut mask = [0u8; BLOCK];
+    for (i, mask) in mask.iter_mut().enumerate() {
+        if i % pixel_bytes < sample_bytes {
+            *mask = 0xff;
         }
-        (ColorType::Gray(32), DecodingBuffer::F32(ref mut buffer)) => {
-            invert_colors_fp(buffer, 1.0);
+    }
+
+    let mut blocks = bytes.chunks_exact_mut(BLOCK);
+    for block in &mut blocks {
+        for (byte, mask) in block.iter_mut().zip(mask.iter()) {
+            *byte ^= mask;
         }
-        (ColorType::Gray(64), DecodingBuffer::F64(ref mut buffer)) => {
-            invert_colors_fp(buffer, 1.0);
+    }
+    for (byte, mask) in blocks.into_remainder().iter_mut().zip(mask.iter()) {
+        *byte ^= mask;
+    }
+}
+
+/// Inverts the gray samples of a `WhiteIsZero` image, after any predictor has been reversed.
+///
+/// Integer samples are complemented, which maps the full range of signed as well as unsigned
+/// samples onto itself and also inverts samples of less than a byte in their packed form. An
+/// alpha sample is left as is.
+fn invert_colors(buf: &mut DecodingBuffer, color_type: ColorType) {
+    let step = match color_type {
+        ColorType::Gray(_) => 1,
+        ColorType::GrayA(_) => 2,
+        _ => return,
+    };
+    match buf {
+        // FIXME: assumes [0, 1) range for floats
+        DecodingBuffer::F32(buffer) => invert_samples(buffer, step, |datum| 1.0 - datum),
+        DecodingBuffer::F64(buffer) => invert_samples(buffer, step, |datum| 1.0 - datum),
+        _ => {
+            let sample_bytes = buf.byte_len();
+            invert_integer_samples(buf.as_bytes_mut(), sample_bytes, step);
         }
-        _ => {}
     }
 }
 
@@ -513,9 +875,31 @@ fn fix_endianness(buf: &mut DecodingBuffer, byte_order: ByteOrder) {
     };
 }
 
+/// Checks that `length` bytes of data at `offset` lie within a file of `file_length` bytes.
+fn check_file_range(offset: u64, length: u64, file_length: u64) -> TiffResult<()> {
+    match offset.checked_add(length) {
+        Some(end) if end <= file_length => Ok(()),
+        _ => Err(TiffFormatError::OffsetOutOfBounds { offset, length }.into()),
+    }
+}
+
 impl<R: Read + Seek> Decoder<R> {
     /// Create a new decoder that decodes from the stream ```r```
-    pub fn new(mut r: R) -> TiffResult<Decoder<R>> {
+    pub fn new(r: R) -> TiffResult<Decoder<R>> {
+        Self::open(r, false)
+    }
+
+    /// Create a new decoder that also accepts TIFF-structured raw camera formats.
+    ///
+    /// Besides regular TIFF and BigTIFF files, this accepts the header variants of Panasonic RW2
+    /// (magic number 85) and Olympus ORF (`IIRO`, `IIRS` and `MMOR`) files. Their directories can
+    /// be walked and their tags read, but directories that do not describe an image this decoder
+    /// supports are reported with dimensions of zero instead of failing.
+    pub fn new_relaxed(r: R) -> TiffResult<Decoder<R>> {
+        Self::open(r, true)
+    }
+
+    fn open(mut r: R, relaxed: bool) -> TiffResult<Decoder<R>> {
         let mut endianess = Vec::with_capacity(2);
         (&mut r).take(2).read_to_end(&mut endianess)?;
         let byte_order = match &*endianess {
@@ -546,6 +930,8 @@ impl<R: Read + Seek> Decoder<R> {
                 }
                 true
             }
+            // Panasonic RW2 and the two byte orders of Olympus ORF, "RO" and "RS" or "OR".
+            85 | 0x4f52 | 0x5352 if relaxed => false,
             _ => {
                 return Err(TiffError::FormatError(
                     TiffFormatError::TiffSignatureInvalid,
@@ -566,37 +952,210 @@ impl<R: Read + Seek> Decoder<R> {
             reader,
             bigtiff,
             limits: Default::default(),
+            mode: DecodingMode::default(),
+            recover_chunks: false,
+            raw_samples: false,
+            relaxed,
+            prefetch_tags: false,
+            progress: None,
+            chunk_cache: None,
+            statistics: DecodingStatistics::default(),
+            record_chunks: false,
+            #[cfg(feature = "readahead")]
+            readahead: None,
+            buffers: BufferPool::default(),
+            warnings: Vec::new(),
             next_ifd,
             ifd_offsets,
             seen_ifds,
-            image: Image {
-                ifd: None,
-                width: 0,
-                height: 0,
-                bits_per_sample: vec![1],
-                samples: 1,
-                sample_format: vec![SampleFormat::Uint],
-                photometric_interpretation: PhotometricInterpretation::BlackIsZero,
-                compression_method: CompressionMethod::None,
-                jpeg_tables: None,
-                predictor: Predictor::None,
-                chunk_type: ChunkType::Strip,
-                strip_decoder: None,
-                tile_attributes: None,
-                chunk_offsets: Vec::new(),
-                chunk_bytes: Vec::new(),
-            },
+            image: Image::without_data(None),
             current_chunk: 0,
+            file_length: None,
+            ifd_offset: 0,
         };
         decoder.next_image()?;
         Ok(decoder)
     }
 
     pub fn with_limits(mut self, limits: Limits) -> Decoder<R> {
+        // Reused scratch buffers count against the intermediate buffer limit.
+        self.buffers
+            .set_max_bytes(BufferPool::MAX_BYTES.min(limits.intermediate_buffer_size));
         self.limits = limits;
         self
     }
 
+    /// Selects how strictly the decoder follows the specification, see `DecodingMode`.
+    pub fn with_mode(mut self, mode: DecodingMode) -> Decoder<R> {
+        self.mode = mode;
+        self
+    }
+
+    /// Keeps decoding an image when one of its strips or tiles is corrupt.
+    ///
+    /// When enabled, `read_image` fills chunks that fail to decode with zeros and records a
+    /// `DecodingWarning` for each of them instead of returning the error, so the intact parts of a
+    /// damaged image can still be recovered. Disabled by default.
+    pub fn with_chunk_recovery(mut self, recover: bool) -> Decoder<R> {
+        self.recover_chunks = recover;
+        self
+    }
+
+    /// Decode images whose photometric interpretation is unknown or unsupported as raw samples.
+    ///
+    /// Instead of an `UnsupportedError`, such images then have the color type
+    /// `ColorType::Multiband` and their samples are returned exactly as stored, as long as all of
+    /// them have the same number of bits. Disabled by default.
+    pub fn with_raw_samples(mut self, raw_samples: bool) -> Decoder<R> {
+        self.raw_samples = raw_samples;
+        self.image.raw_samples = raw_samples;
+        self
+    }
+
+    /// Buffers reads from the underlying reader in blocks of `capacity` bytes.
+    ///
+    /// The decoder reads directories and tag values in many small reads, with a seek before each
+    /// value. Wrapping the reader in a `BufReader` doesn't help much with these, as it discards
+    /// its buffer on every seek. The buffer of the decoder is kept when seeking to data that it
+    /// already holds, so for storage where each read is expensive, such as network filesystems, a
+    /// buffer of a few kilobytes can save most reads. Reads larger than the buffer bypass it. A
+    /// capacity of zero, the default, disables buffering.
+    pub fn with_read_buffer(mut self, capacity: usize) -> TiffResult<Decoder<R>> {
+        self.reader.set_buffer_capacity(capacity)?;
+        Ok(self)
+    }
+
+    /// Reads the values of all tags of an image directory as soon as the directory is loaded.
+    ///
+    /// Values that don't fit into their IFD entry are otherwise read when they are accessed, with
+    /// a seek for each of them. With prefetching, they are read in order of their offsets with a
+    /// few large reads instead, which is faster for readers where seeking is expensive, such as
+    /// files on network filesystems. Disabled by default.
+    pub fn with_tag_prefetch(mut self, prefetch: bool) -> Decoder<R> {
+        self.prefetch_tags = prefetch;
+        if prefetch {
+            if let Some(ifd) = self.image.ifd.as_mut() {
+                ifd::prefetch_values(ifd, &self.limits, self.bigtiff, &mut self.reader);
+            }
+        }
+        self
+    }
+
+    /// Keeps up to `capacity` bytes of chunks decoded by `read_chunk` in memory.
+    ///
+    /// Reading a chunk that is still in the cache copies it instead of decompressing it again,
+    /// which helps viewers of tiled images that decode overlapping sets of tiles as the view is
+    /// panned or zoomed. Chunks of all images of the file share the cache, and the least recently
+    /// used are evicted first. The cache is not counted against the `Limits` of the decoder. A
+    /// capacity of zero, the default, disables caching.
+    pub fn with_chunk_cache(mut self, capacity: usize) -> Decoder<R> {
+        self.chunk_cache = if capacity > 0 {
+            Some(ChunkCache::new(capacity))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Sets a callback that is invoked after every strip or tile decoded by `read_image`.
+    ///
+    /// Returning `false` from the callback cancels the decode: `read_image` fails with an error
+    /// without reading further chunks, and the decoder can still be used afterwards. The callback
+    /// runs on the decoding thread, so a viewer can pass the progress to its interface thread and
+    /// cancel by checking a shared flag.
+    pub fn with_progress<F>(mut self, callback: F) -> Decoder<R>
+    where
+        F: FnMut(&Progress) -> bool + Send + 'static,
+    {
+        self.progress = Some(progress::ProgressCallback(Box::new(callback)));
+        self
+    }
+
+    /// Records the compression, size and decoding time of every strip or tile that is decoded.
+    ///
+    /// The records are part of the `statistics` of the decoder. They are off by default, as they
+    /// take memory for every chunk.
+    pub fn with_chunk_statistics(mut self) -> Decoder<R> {
+        self.record_chunks = true;
+        self
+    }
+
+    /// Returns the I/O and decoding counters since the decoder was created or the counters were
+    /// last reset.
+    ///
+    /// This helps diagnosing slow or pathological files, such as ones whose data is scattered
+    /// across the file and needs many seeks to read.
+    pub fn statistics(&self) -> DecodingStatistics {
+        DecodingStatistics {
+            bytes_read: self.reader.bytes_read() + self.statistics.bytes_read,
+            seeks: self.reader.seeks() + self.statistics.seeks,
+            chunks: self.statistics.chunks.clone(),
+        }
+    }
+
+    /// Resets all counters returned by `statistics`.
+    pub fn reset_statistics(&mut self) {
+        self.reader.reset_counters();
+        self.statistics = DecodingStatistics::default();
+    }
+
+    /// Starts timing the reading or decoding of a chunk if chunk statistics are recorded.
+    fn chunk_timer(&self) -> Option<Instant> {
+        start_timer(self.record_chunks)
+    }
+
+    /// Records the times spent reading and decoding `chunk_index`, if decoding was timed.
+    fn record_chunk(
+        &mut self,
+        chunk_index: u32,
+        read_time: Option<Duration>,
+        decode_time: Option<Duration>,
+    ) {
+        if let Some(decode_time) = decode_time {
+            let compressed_bytes = self
+                .image()
+                .chunk_file_range(chunk_index)
+                .map_or(0, |(_, length)| length);
+            self.statistics.chunks.push(ChunkStatistics {
+                chunk_index,
+                compression: self.image().compression_method,
+                compressed_bytes,
+                read_time: read_time.unwrap_or_default(),
+                decode_time,
+            });
+        }
+    }
+
+    /// Hints the chunks read after `chunk` to the operating system, if enabled.
+    #[cfg(feature = "readahead")]
+    fn readahead(&mut self, chunk: usize, end: usize) {
+        if let Some(readahead) = self.readahead.as_mut() {
+            readahead.advance(&self.image, chunk, end);
+        }
+    }
+
+    /// Notifies the progress callback, if any, of the chunks decoded so far.
+    fn report_progress(&mut self, chunks_read: u64, bytes_read: u64) -> TiffResult<()> {
+        let progress = Progress {
+            chunks_read,
+            total_chunks: self.image().chunk_offsets.len() as u64,
+            bytes_read,
+        };
+        let proceed = match self.progress.as_mut() {
+            Some(callback) => (callback.0)(&progress),
+            None => true,
+        };
+        if !proceed {
+            return Err(io::Error::new(io::ErrorKind::Other, "Decoding was cancelled").into());
+        }
+        Ok(())
+    }
+
+    /// The problems the decoder recovered from in the current image so far.
+    pub fn warnings(&self) -> &[DecodingWarning] {
+        &self.warnings
+    }
+
     pub fn dimensions(&mut self) -> TiffResult<(u32, u32)> {
         Ok((self.image().width, self.image().height))
     }
@@ -636,12 +1195,10 @@ impl<R: Read + Seek> Decoder<R> {
 
         // If the index is within the list of ifds then we can load the selected image/IFD
         if let Some(ifd_offset) = self.ifd_offsets.get(ifd_index) {
-            let (ifd, _next_ifd) = Self::read_ifd(&mut self.reader, self.bigtiff, *ifd_offset)?;
-
-            self.current_chunk = 0;
-            self.image = Image::from_reader(&mut self.reader, ifd, &self.limits, self.bigtiff)?;
-
-            Ok(())
+            let ifd_offset = *ifd_offset;
+            let (ifd, _next_ifd) =
+                Self::read_ifd(&mut self.reader, self.bigtiff, &self.limits, ifd_offset)?;
+            self.load_image(ifd_offset, ifd)
         } else {
             Err(TiffError::FormatError(
                 TiffFormatError::ImageFileDirectoryNotFound,
@@ -663,9 +1220,15 @@ impl<R: Read + Seek> Decoder<R> {
             ));
         }
 
+        // The offset of the IFD about to be read is already the last in the list.
+        if self.ifd_offsets.len() > self.limits.ifd_count {
+            return Err(TiffError::LimitsExceeded);
+        }
+
         let (ifd, next_ifd) = Self::read_ifd(
             &mut self.reader,
             self.bigtiff,
+            &self.limits,
             self.next_ifd.take().unwrap(),
         )?;
 
@@ -684,10 +1247,41 @@ impl<R: Read + Seek> Decoder<R> {
     /// If there is no further image in the TIFF file a format error is returned.
     /// To determine whether there are more images call `TIFFDecoder::more_images` instead.
     pub fn next_image(&mut self) -> TiffResult<()> {
+        let ifd_offset = self.next_ifd.unwrap_or(0);
         let (ifd, _next_ifd) = self.next_ifd()?;
+        self.load_image(ifd_offset, ifd)
+    }
 
+    /// Makes the image described by `ifd`, read from `ifd_offset`, the current one.
+    fn load_image(&mut self, ifd_offset: u64, mut ifd: Directory) -> TiffResult<()> {
         self.current_chunk = 0;
-        self.image = Image::from_reader(&mut self.reader, ifd, &self.limits, self.bigtiff)?;
+        self.file_length = None;
+        self.ifd_offset = ifd_offset;
+        self.warnings.clear();
+        if self.prefetch_tags {
+            ifd::prefetch_values(&mut ifd, &self.limits, self.bigtiff, &mut self.reader);
+        }
+        let fallback = if self.relaxed {
+            Some(ifd.clone())
+        } else {
+            None
+        };
+        self.image = match Image::from_reader(
+            &mut self.reader,
+            ifd,
+            &self.limits,
+            self.bigtiff,
+            self.raw_samples,
+        ) {
+            Ok(image) => image,
+            // Raw camera formats keep their sensor data in directories of their own design.
+            Err(TiffError::FormatError(_)) | Err(TiffError::UnsupportedError(_))
+                if fallback.is_some() =>
+            {
+                Image::without_data(fallback)
+            }
+            Err(err) => return Err(err),
+        };
         Ok(())
     }
 
@@ -808,6 +1402,89 @@ impl<R: Read + Seek> Decoder<R> {
         self.reader.seek(io::SeekFrom::Start(offset)).map(|_| ())
     }
 
+    /// The length of the file.
+    ///
+    /// Seeking to the end discards the read buffer, so the length is only looked up once per
+    /// image and then reused for all of its chunks.
+    fn file_length(&mut self) -> TiffResult<u64> {
+        match self.file_length {
+            Some(length) => Ok(length),
+            None => {
+                let length = self.reader.seek(io::SeekFrom::End(0))?;
+                self.file_length = Some(length);
+                Ok(length)
+            }
+        }
+    }
+
+    /// Moves the cursor to the data of a chunk, checking that the data lies within the file.
+    fn goto_chunk(&mut self, chunk_index: u32) -> TiffResult<()> {
+        let (offset, length) = self.image.chunk_file_range(chunk_index)?;
+        let file_length = self.file_length()?;
+        check_file_range(offset, length, file_length)?;
+        self.goto_offset_u64(offset)?;
+        Ok(())
+    }
+
+    /// Rejects deviations from the specification in the current image when decoding strictly.
+    fn check_strict(&mut self) -> TiffResult<()> {
+        if self.mode == DecodingMode::Lenient {
+            return Ok(());
+        }
+
+        let ifd = self.image().ifd.as_ref().unwrap();
+        for &(tag, types) in BASELINE_FIELD_TYPES {
+            match ifd.get(&tag) {
+                Some(entry) if !types.contains(&entry.field_type()) => {
+                    return Err(TiffFormatError::InvalidTagValueType(tag).into());
+                }
+                _ => {}
+            }
+        }
+
+        let bits_count = ifd
+            .get(&Tag::BitsPerSample)
+            .map_or(1, |entry| entry.count());
+        if bits_count != u64::from(self.image().samples) {
+            return Err(TiffFormatError::InconsistentSizesEncountered.into());
+        }
+
+        if self.image().chunk_type == ChunkType::Tile {
+            let (width, length) = self.image().chunk_dimensions()?;
+            let reported = self
+                .warnings
+                .iter()
+                .any(|warning| matches!(warning, DecodingWarning::TileSize { .. }));
+            if (width % 16 != 0 || length % 16 != 0) && !reported {
+                self.warnings
+                    .push(DecodingWarning::TileSize { width, length });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the data of all chunks of the current image lies within the file.
+    ///
+    /// Returns the first chunk that starts within the file but reaches beyond its end, where a
+    /// truncated file stops. Chunks starting beyond the end have a corrupt offset instead.
+    fn check_chunk_ranges(&mut self) -> TiffResult<Option<usize>> {
+        let file_length = self.file_length()?;
+        let image = self.image();
+        for (chunk, (&offset, &length)) in image
+            .chunk_offsets
+            .iter()
+            .zip(&image.chunk_bytes)
+            .enumerate()
+        {
+            match check_file_range(offset, length, file_length) {
+                Ok(()) => {}
+                Err(_) if offset <= file_length => return Ok(Some(chunk)),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(None)
+    }
+
     /// Reads a IFD entry.
     // An IFD entry has four fields:
     //
@@ -824,8 +1501,13 @@ impl<R: Read + Seek> Decoder<R> {
             Some(t) => t,
             None => {
                 // Unknown type. Skip this entry according to spec.
-                reader.read_u32()?;
-                reader.read_u32()?;
+                if bigtiff {
+                    reader.read_u64()?;
+                    reader.read_u64()?;
+                } else {
+                    reader.read_u32()?;
+                    reader.read_u32()?;
+                }
                 return Ok(None);
             }
         };
@@ -846,20 +1528,26 @@ impl<R: Read + Seek> Decoder<R> {
     }
 
     /// Reads the IFD starting at the indicated location.
+    ///
+    /// The entries may appear in any order. If a tag occurs more than once, the last entry is
+    /// used and earlier ones are ignored.
     fn read_ifd(
         reader: &mut SmartReader<R>,
         bigtiff: bool,
+        limits: &Limits,
         ifd_location: u64,
     ) -> TiffResult<(Directory, Option<u64>)> {
         reader.goto_offset(ifd_location)?;
 
-        let mut dir: Directory = HashMap::new();
-
         let num_tags = if bigtiff {
             reader.read_u64()?
         } else {
             reader.read_u16()?.into()
         };
+        if num_tags > limits.ifd_entries as u64 {
+            return Err(TiffError::LimitsExceeded);
+        }
+        let mut dir: Directory = HashMap::with_capacity(num_tags as usize);
         for _ in 0..num_tags {
             let (tag, entry) = match Self::read_entry(reader, bigtiff)? {
                 Some(val) => val,
@@ -884,43 +1572,464 @@ impl<R: Read + Seek> Decoder<R> {
         Ok((dir, next_ifd))
     }
 
+    /// Raw entries of the current image directory, sorted by tag.
+    pub(crate) fn raw_entries(&mut self) -> TiffResult<Vec<RawEntry>> {
+        let directory = self.image().ifd.as_ref().unwrap().clone();
+        self.read_raw_entries(&directory)
+    }
+
+    /// Raw entries of a sub-directory such as the EXIF directory, sorted by tag.
+    pub(crate) fn raw_sub_entries(&mut self, offset: u64) -> TiffResult<Vec<RawEntry>> {
+        let (directory, _) = Self::read_ifd(&mut self.reader, self.bigtiff, &self.limits, offset)?;
+        self.read_raw_entries(&directory)
+    }
+
+    fn read_raw_entries(&mut self, directory: &Directory) -> TiffResult<Vec<RawEntry>> {
+        let mut entries = directory
+            .iter()
+            .map(|(&tag, entry)| {
+                let data = entry.raw_data(&self.limits, self.bigtiff, &mut self.reader)?;
+                Ok((tag, entry.field_type(), entry.count(), data))
+            })
+            .collect::<TiffResult<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.0.to_u16());
+        Ok(entries)
+    }
+
+    /// All tags of the current image directory, sorted by their numeric value.
+    pub fn tag_list(&self) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = self.image().ifd.as_ref().unwrap().keys().copied().collect();
+        tags.sort_by_key(|tag| tag.to_u16());
+        tags
+    }
+
+    /// All tags of the current image directory together with their values, sorted by tag.
+    ///
+    /// This reads the values of every entry, including large arrays such as the strip offsets,
+    /// subject to the decoder's limits.
+    pub fn tags(&mut self) -> TiffResult<Vec<(Tag, ifd::Value)>> {
+        let limits = &self.limits;
+        let bigtiff = self.bigtiff;
+        let reader = &mut self.reader;
+        let mut tags = self
+            .image
+            .ifd
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .map(|(&tag, entry)| Ok((tag, entry.cached_val(limits, bigtiff, reader)?)))
+            .collect::<TiffResult<Vec<_>>>()?;
+        tags.sort_by_key(|tag| tag.0.to_u16());
+        Ok(tags)
+    }
+
+    /// All tags of the directory that a pointer tag of the current image refers to, such as the
+    /// EXIF or GPS directory, sorted by tag.
+    /// Returns `Ok(None)` if the pointer tag is not present.
+    pub fn sub_directory_tags(&mut self, tag: Tag) -> TiffResult<Option<Vec<(Tag, ifd::Value)>>> {
+        match self.sub_directory(tag)? {
+            Some(directory) => self.directory_tags(&directory).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// All tags of the interoperability directory of the current image, sorted by tag.
+    ///
+    /// The directory is referenced from the EXIF directory, though some writers put the pointer
+    /// into the image directory itself. Returns `Ok(None)` if neither has one.
+    pub fn interop_tags(&mut self) -> TiffResult<Option<Vec<(Tag, ifd::Value)>>> {
+        match self.find_tag_or_exif(Tag::InteropDirectory)? {
+            Some(offset) => {
+                let offset = offset.into_u64()?;
+                let (directory, _) =
+                    Self::read_ifd(&mut self.reader, self.bigtiff, &self.limits, offset)?;
+                self.directory_tags(&directory).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Tries to retrieve a tag from the current image directory, falling back to its EXIF
+    /// directory.
+    fn find_tag_or_exif(&mut self, tag: Tag) -> TiffResult<Option<ifd::Value>> {
+        if let Some(value) = self.find_tag(tag)? {
+            return Ok(Some(value));
+        }
+        let exif = match self.sub_directory(Tag::ExifDirectory)? {
+            Some(exif) => exif,
+            None => return Ok(None),
+        };
+        match exif.get(&tag) {
+            Some(entry) => Ok(Some(entry.val(
+                &self.limits,
+                self.bigtiff,
+                &mut self.reader,
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    fn directory_tags(&mut self, directory: &Directory) -> TiffResult<Vec<(Tag, ifd::Value)>> {
+        let mut tags = directory
+            .iter()
+            .map(|(&tag, entry)| {
+                Ok((
+                    tag,
+                    entry.val(&self.limits, self.bigtiff, &mut self.reader)?,
+                ))
+            })
+            .collect::<TiffResult<Vec<_>>>()?;
+        tags.sort_by_key(|tag| tag.0.to_u16());
+        Ok(tags)
+    }
+
     /// Tries to retrieve a tag.
     /// Return `Ok(None)` if the tag is not present.
+    ///
+    /// Values are kept after they have been read once, so looking up a tag again doesn't read
+    /// the file.
     pub fn find_tag(&mut self, tag: Tag) -> TiffResult<Option<ifd::Value>> {
-        let entry = match self.image().ifd.as_ref().unwrap().get(&tag) {
+        let entry = match self.image.ifd.as_mut().unwrap().get_mut(&tag) {
             None => return Ok(None),
-            Some(entry) => entry.clone(),
+            Some(entry) => entry,
         };
 
-        Ok(Some(entry.val(
+        Ok(Some(entry.cached_val(
             &self.limits,
             self.bigtiff,
             &mut self.reader,
         )?))
     }
 
-    /// Tries to retrieve a tag and convert it to the desired unsigned type.
-    pub fn find_tag_unsigned<T: TryFrom<u64>>(&mut self, tag: Tag) -> TiffResult<Option<T>> {
-        self.find_tag(tag)?
-            .map(|v| v.into_u64())
-            .transpose()?
-            .map(|value| {
-                T::try_from(value).map_err(|_| TiffFormatError::InvalidTagValueType(tag).into())
-            })
-            .transpose()
+    /// Tries to retrieve a tag by its numeric value.
+    /// Return `Ok(None)` if the tag is not present.
+    ///
+    /// Private and vendor tags are kept as `Tag::Unknown` when reading a directory, this looks up
+    /// both those and the tags known to this crate.
+    pub fn find_tag_by_number(&mut self, tag: u16) -> TiffResult<Option<ifd::Value>> {
+        self.find_tag(Tag::from_u16_exhaustive(tag))
     }
 
-    /// Tries to retrieve a vector of all a tag's values and convert them to
-    /// the desired unsigned type.
-    pub fn find_tag_unsigned_vec<T: TryFrom<u64>>(
-        &mut self,
-        tag: Tag,
-    ) -> TiffResult<Option<Vec<T>>> {
-        self.find_tag(tag)?
-            .map(|v| v.into_u64_vec())
-            .transpose()?
-            .map(|v| {
-                v.into_iter()
+    /// Reads the position from the GPS directory of the current image.
+    /// Returns `Ok(None)` if the image has no GPS directory.
+    pub fn gps_info(&mut self) -> TiffResult<Option<GpsInfo>> {
+        let directory = match self.sub_directory(Tag::GpsDirectory)? {
+            Some(directory) => directory,
+            None => return Ok(None),
+        };
+        let mut tag_reader = TagReader {
+            reader: &mut self.reader,
+            ifd: &directory,
+            limits: &self.limits,
+            bigtiff: self.bigtiff,
+        };
+        GpsInfo::read(&mut tag_reader).map(Some)
+    }
+
+    /// Reads the GeoTIFF keys of the current image, resolving values stored in the
+    /// `GeoDoubleParams` and `GeoAsciiParams` tags.
+    /// Returns `Ok(None)` if the image has no `GeoKeyDirectory`.
+    pub fn geo_keys(&mut self) -> TiffResult<Option<GeoKeyDirectory>> {
+        let directory = match self.find_tag_unsigned_vec::<u16>(Tag::GeoKeyDirectoryTag)? {
+            Some(directory) => directory,
+            None => return Ok(None),
+        };
+        let double_params = match self.find_tag(Tag::GeoDoubleParamsTag)? {
+            Some(value) => value.into_f64_vec()?,
+            None => Vec::new(),
+        };
+        let ascii_params = match self.find_tag(Tag::GeoAsciiParamsTag)? {
+            Some(value) => value.into_string()?,
+            None => String::new(),
+        };
+        GeoKeyDirectory::parse(&directory, &double_params, &ascii_params).map(Some)
+    }
+
+    /// Reads the GeoTIFF `ModelPixelScale` of the current image as `[scale_x, scale_y, scale_z]`.
+    /// Returns `Ok(None)` if the tag is not present.
+    pub fn model_pixel_scale(&mut self) -> TiffResult<Option<[f64; 3]>> {
+        let values = match self.find_tag(Tag::ModelPixelScaleTag)? {
+            Some(value) => value.into_f64_vec()?,
+            None => return Ok(None),
+        };
+        match values[..] {
+            [x, y, z] => Ok(Some([x, y, z])),
+            _ => Err(TiffFormatError::InvalidTagValueType(Tag::ModelPixelScaleTag).into()),
+        }
+    }
+
+    /// Reads the GeoTIFF `ModelTiepoint` of the current image.
+    ///
+    /// Each tiepoint consists of six values `[i, j, k, x, y, z]`, mapping the raster point
+    /// `(i, j, k)` to the model point `(x, y, z)`. Returns `Ok(None)` if the tag is not present.
+    pub fn model_tiepoints(&mut self) -> TiffResult<Option<Vec<[f64; 6]>>> {
+        let values = match self.find_tag(Tag::ModelTiepointTag)? {
+            Some(value) => value.into_f64_vec()?,
+            None => return Ok(None),
+        };
+        if values.is_empty() || values.len() % 6 != 0 {
+            return Err(TiffFormatError::InvalidTagValueType(Tag::ModelTiepointTag).into());
+        }
+        let tiepoints = values
+            .chunks_exact(6)
+            .map(|tiepoint| {
+                let mut values = [0.0; 6];
+                values.copy_from_slice(tiepoint);
+                values
+            })
+            .collect();
+        Ok(Some(tiepoints))
+    }
+
+    /// Reads the GeoTIFF `ModelTransformation` of the current image as a row-major 4x4 matrix.
+    /// Returns `Ok(None)` if the tag is not present.
+    pub fn model_transformation(&mut self) -> TiffResult<Option<[f64; 16]>> {
+        let values = match self.find_tag(Tag::ModelTransformationTag)? {
+            Some(value) => value.into_f64_vec()?,
+            None => return Ok(None),
+        };
+        if values.len() != 16 {
+            return Err(TiffFormatError::InvalidTagValueType(Tag::ModelTransformationTag).into());
+        }
+        let mut matrix = [0.0; 16];
+        matrix.copy_from_slice(&values);
+        Ok(Some(matrix))
+    }
+
+    /// Reads the XMP metadata packet of the current image.
+    ///
+    /// The packet is returned verbatim, it is usually UTF-8 encoded XML. Returns `Ok(None)` if
+    /// the image has no `XMP` tag.
+    pub fn xmp(&mut self) -> TiffResult<Option<Vec<u8>>> {
+        self.find_tag(Tag::Xmp)?
+            .map(ifd::Value::into_u8_vec)
+            .transpose()
+    }
+
+    /// Reads the IPTC-NAA record of the current image.
+    ///
+    /// The record is returned as stored in the file, regardless of its field type. Returns
+    /// `Ok(None)` if the image has no `IPTC` tag.
+    pub fn iptc(&mut self) -> TiffResult<Option<Vec<u8>>> {
+        self.find_tag_payload(Tag::Iptc)
+    }
+
+    /// Reads the datasets of the IPTC-NAA record of the current image.
+    /// Returns `Ok(None)` if the image has no `IPTC` tag.
+    pub fn iptc_datasets(&mut self) -> TiffResult<Option<Vec<IptcDataset>>> {
+        match self.iptc()? {
+            Some(data) => iptc::parse(&data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the Photoshop image resource section of the current image.
+    ///
+    /// The section is returned as stored in the file, regardless of its field type. Returns
+    /// `Ok(None)` if the image has no `Photoshop` tag.
+    pub fn photoshop(&mut self) -> TiffResult<Option<Vec<u8>>> {
+        self.find_tag_payload(Tag::Photoshop)
+    }
+
+    /// Reads the resource blocks of the Photoshop image resource section of the current image.
+    /// Returns `Ok(None)` if the image has no `Photoshop` tag.
+    pub fn photoshop_resources(&mut self) -> TiffResult<Option<Vec<PhotoshopResource>>> {
+        match self.photoshop()? {
+            Some(data) => photoshop::parse(&data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the kind of the current image from its `NewSubfileType` tag, or the deprecated
+    /// `SubfileType` tag if the former is missing.
+    ///
+    /// Images without either tag are full-resolution images.
+    pub fn subfile_type(&mut self) -> TiffResult<SubfileType> {
+        if let Some(value) = self.find_tag_unsigned(Tag::NewSubfileType)? {
+            return Ok(SubfileType::from_new_subfile_type(value));
+        }
+        Ok(match self.find_tag_unsigned(Tag::SubfileType)? {
+            Some(value) => SubfileType::from_subfile_type(value),
+            None => SubfileType::default(),
+        })
+    }
+
+    /// Reads the horizontal and vertical resolution of the current image together with their
+    /// unit.
+    ///
+    /// The unit defaults to inches if the `ResolutionUnit` tag is missing, and the vertical
+    /// resolution to the horizontal one, unless decoding strictly. Returns `Ok(None)` if the image
+    /// has no `XResolution`.
+    pub fn resolution(&mut self) -> TiffResult<Option<(Rational, Rational, ResolutionUnit)>> {
+        let x = match self.find_tag(Tag::XResolution)? {
+            Some(value) => value.into_rational()?,
+            None => return Ok(None),
+        };
+        self.check_strict()?;
+        let strict = self.mode == DecodingMode::Strict;
+        let y = match self.find_tag(Tag::YResolution)? {
+            Some(value) => value.into_rational()?,
+            None if strict => {
+                return Err(TiffFormatError::RequiredTagNotFound(Tag::YResolution).into())
+            }
+            None => x,
+        };
+        let unit = match self.find_tag_unsigned::<u16>(Tag::ResolutionUnit)? {
+            Some(unit) => ResolutionUnit::from_u16(unit)
+                .ok_or(TiffFormatError::InvalidTagValueType(Tag::ResolutionUnit))?,
+            None if strict => {
+                return Err(TiffFormatError::RequiredTagNotFound(Tag::ResolutionUnit).into())
+            }
+            None => ResolutionUnit::Inch,
+        };
+        Ok(Some((
+            Rational { n: x.0, d: x.1 },
+            Rational { n: y.0, d: y.1 },
+            unit,
+        )))
+    }
+
+    /// Reads the `ColorMap` of the current image as one `[red, green, blue]` entry per index.
+    ///
+    /// The tag stores all red values, then all green and all blue values, 65535 being full
+    /// intensity. Returns `Ok(None)` if the image has no `ColorMap`.
+    pub fn color_map(&mut self) -> TiffResult<Option<Vec<[u16; 3]>>> {
+        let values = match self.find_tag_unsigned_vec::<u16>(Tag::ColorMap)? {
+            Some(values) => values,
+            None => return Ok(None),
+        };
+        if values.is_empty() || values.len() % 3 != 0 {
+            return Err(TiffFormatError::InvalidTagValueType(Tag::ColorMap).into());
+        }
+        let len = values.len() / 3;
+        let (red, rest) = values.split_at(len);
+        let (green, blue) = rest.split_at(len);
+        let entries = (0..len).map(|i| [red[i], green[i], blue[i]]).collect();
+        Ok(Some(entries))
+    }
+
+    /// Reads the metadata of an image written by ImageJ.
+    ///
+    /// ImageJ stores the metadata of a stack in its first directory only. Returns `Ok(None)` if
+    /// the `ImageDescription` of the current image was not written by ImageJ.
+    pub fn imagej_metadata(&mut self) -> TiffResult<Option<ImageJMetadata>> {
+        let description = match self.find_tag(Tag::ImageDescription)? {
+            Some(value) => value.into_string()?,
+            None => return Ok(None),
+        };
+        let mut metadata = match ImageJMetadata::from_description(&description) {
+            Some(metadata) => metadata,
+            None => return Ok(None),
+        };
+        let byte_counts = self.find_tag_unsigned_vec::<u32>(Tag::IJMetadataByteCounts)?;
+        if let Some(byte_counts) = byte_counts {
+            if let Some(data) = self.find_tag_payload(Tag::IJMetadata)? {
+                metadata.read_blocks(&data, &byte_counts)?;
+            }
+        }
+        Ok(Some(metadata))
+    }
+
+    /// Reads the OME-XML metadata of an OME-TIFF file.
+    ///
+    /// The metadata describing all images of the file is stored in the first directory.
+    /// Returns `Ok(None)` if the `ImageDescription` of the current image is not an OME-XML
+    /// document.
+    pub fn ome_metadata(&mut self) -> TiffResult<Option<OmeMetadata>> {
+        match self.find_tag(Tag::ImageDescription)? {
+            Some(value) => OmeMetadata::from_description(&value.into_string()?),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the `DateTime` tag of the current image, the time it was last modified.
+    ///
+    /// Returns `Ok(None)` if the tag is not present or the timestamp is marked as unknown, and
+    /// an error if it is malformed.
+    pub fn date_time(&mut self) -> TiffResult<Option<DateTime>> {
+        match self.find_tag(Tag::DateTime)? {
+            Some(value) => date_time::read(&value.into_string()?, Tag::DateTime),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the `DateTimeOriginal` tag of the current image, the time it was captured.
+    ///
+    /// The tag is looked up in the EXIF directory if the image directory does not contain it.
+    /// Returns `Ok(None)` if the tag is not present or the timestamp is marked as unknown, and
+    /// an error if it is malformed.
+    pub fn date_time_original(&mut self) -> TiffResult<Option<DateTime>> {
+        match self.find_tag_or_exif(Tag::DateTimeOriginal)? {
+            Some(value) => date_time::read(&value.into_string()?, Tag::DateTimeOriginal),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the nodata value GDAL stores in the `GdalNodata` tag of the current image.
+    ///
+    /// Pixels with this value are missing data. Returns `Ok(None)` if the tag is not present.
+    pub fn gdal_nodata(&mut self) -> TiffResult<Option<f64>> {
+        let text = match self.find_tag(Tag::GdalNodata)? {
+            Some(value) => value.into_string()?,
+            None => return Ok(None),
+        };
+        let text = text.trim();
+        let value = match text.to_ascii_lowercase().as_str() {
+            "nan" | "-nan" => f64::NAN,
+            "inf" | "+inf" | "infinity" => f64::INFINITY,
+            "-inf" | "-infinity" => f64::NEG_INFINITY,
+            _ => text
+                .parse()
+                .map_err(|_| TiffFormatError::InvalidTagValueType(Tag::GdalNodata))?,
+        };
+        Ok(Some(value))
+    }
+
+    /// Reads the bytes of a tag as stored in the file, regardless of its field type.
+    fn find_tag_payload(&mut self, tag: Tag) -> TiffResult<Option<Vec<u8>>> {
+        let entry = match self.image().ifd.as_ref().unwrap().get(&tag) {
+            None => return Ok(None),
+            Some(entry) => entry.clone(),
+        };
+        Ok(Some(entry.payload(
+            &self.limits,
+            self.bigtiff,
+            &mut self.reader,
+        )?))
+    }
+
+    /// Reads the directory that the given tag of the current image points to.
+    fn sub_directory(&mut self, tag: Tag) -> TiffResult<Option<Directory>> {
+        match self.find_tag_unsigned(tag)? {
+            Some(offset) => Ok(Some(
+                Self::read_ifd(&mut self.reader, self.bigtiff, &self.limits, offset)?.0,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Tries to retrieve a tag and convert it to the desired unsigned type.
+    pub fn find_tag_unsigned<T: TryFrom<u64>>(&mut self, tag: Tag) -> TiffResult<Option<T>> {
+        self.find_tag(tag)?
+            .map(|v| v.into_u64())
+            .transpose()?
+            .map(|value| {
+                T::try_from(value).map_err(|_| TiffFormatError::InvalidTagValueType(tag).into())
+            })
+            .transpose()
+    }
+
+    /// Tries to retrieve a vector of all a tag's values and convert them to
+    /// the desired unsigned type.
+    pub fn find_tag_unsigned_vec<T: TryFrom<u64>>(
+        &mut self,
+        tag: Tag,
+    ) -> TiffResult<Option<Vec<T>>> {
+        self.find_tag(tag)?
+            .map(|v| v.into_u64_vec())
+            .transpose()?
+            .map(|v| {
+                v.into_iter()
                     .map(|u| {
                         T::try_from(u).map_err(|_| TiffFormatError::InvalidTagValueType(tag).into())
                     })
@@ -947,6 +2056,11 @@ impl<R: Read + Seek> Decoder<R> {
         }
     }
 
+    /// Tries to retrieve a tag and convert it to the desired type.
+    pub fn get_tag_u16(&mut self, tag: Tag) -> TiffResult<u16> {
+        self.get_tag(tag)?.into_u16()
+    }
+
     /// Tries to retrieve a tag and convert it to the desired type.
     pub fn get_tag_u32(&mut self, tag: Tag) -> TiffResult<u32> {
         self.get_tag(tag)?.into_u32()
@@ -997,6 +2111,32 @@ impl<R: Read + Seek> Decoder<R> {
         self.get_tag(tag)?.into_string()
     }
 
+    /// Tries to retrieve all strings of an ASCII tag holding several NUL-separated strings.
+    pub fn get_tag_ascii_string_vec(&mut self, tag: Tag) -> TiffResult<Vec<String>> {
+        self.get_tag(tag)?.into_string_vec()
+    }
+
+    /// Tries to retrieve a rational tag as numerator and denominator.
+    pub fn get_tag_rational(&mut self, tag: Tag) -> TiffResult<(u32, u32)> {
+        self.get_tag(tag)?.into_rational()
+    }
+
+    /// Tries to retrieve a tag with rational values as pairs of numerator and denominator.
+    pub fn get_tag_rational_vec(&mut self, tag: Tag) -> TiffResult<Vec<(u32, u32)>> {
+        self.get_tag(tag)?.into_rational_vec()
+    }
+
+    /// Tries to retrieve a signed rational tag as numerator and denominator.
+    pub fn get_tag_srational(&mut self, tag: Tag) -> TiffResult<(i32, i32)> {
+        self.get_tag(tag)?.into_srational()
+    }
+
+    /// Tries to retrieve a tag with signed rational values as pairs of numerator and
+    /// denominator.
+    pub fn get_tag_srational_vec(&mut self, tag: Tag) -> TiffResult<Vec<(i32, i32)>> {
+        self.get_tag(tag)?.into_srational_vec()
+    }
+
     fn check_chunk_type(&self, expected: ChunkType) -> TiffResult<()> {
         if expected != self.image().chunk_type {
             return Err(TiffError::UsageError(UsageError::InvalidChunkType(
@@ -1022,13 +2162,8 @@ impl<R: Read + Seek> Decoder<R> {
             return Ok(0);
         }
 
-        // rows_per_strip - 1 can never fail since we know it's at least 1
-        let height = match self.image().height.checked_add(rows_per_strip - 1) {
-            Some(h) => h,
-            None => return Err(TiffError::IntSizeError),
-        };
-
-        Ok(height / rows_per_strip)
+        let height = self.image().height;
+        Ok(height / rows_per_strip + u32::from(height % rows_per_strip != 0))
     }
 
     /// Number of tiles in image
@@ -1045,19 +2180,21 @@ impl<R: Read + Seek> Decoder<R> {
     #[deprecated = "Use read_chunk_to_buffer instead"]
     pub fn read_strip_to_buffer(&mut self, mut buffer: DecodingBuffer) -> TiffResult<()> {
         self.check_chunk_type(ChunkType::Strip)?;
+        self.check_strict()?;
 
-        let offset = self.image.chunk_file_range(self.current_chunk)?.0;
-        self.goto_offset_u64(offset)?;
+        self.goto_chunk(self.current_chunk)?;
 
-        let byte_order = self.reader.byte_order;
         let output_width = usize::try_from(self.image().width)?;
+        let timer = self.chunk_timer();
         self.image.expand_chunk(
             &mut self.reader,
             buffer.copy(),
             output_width,
-            byte_order,
             self.current_chunk,
+            &self.limits,
+            &mut self.buffers,
         )?;
+        self.record_chunk(self.current_chunk, None, timer.map(|start| start.elapsed()));
 
         self.current_chunk += 1;
 
@@ -1070,31 +2207,81 @@ impl<R: Read + Seek> Decoder<R> {
         chunk_index: u32,
         output_width: usize,
     ) -> TiffResult<()> {
-        let offset = self.image.chunk_file_range(chunk_index)?.0;
-        self.goto_offset_u64(offset)?;
-
-        let byte_order = self.reader.byte_order;
+        self.check_strict()?;
+        if self.image().is_sparse_chunk(chunk_index) {
+            let fill = self.sparse_fill()?;
+            return self
+                .image
+                .fill_chunk(buffer, output_width, chunk_index, fill);
+        }
+        self.goto_chunk(chunk_index)?;
 
+        let timer = self.chunk_timer();
         self.image.expand_chunk(
             &mut self.reader,
             buffer.copy(),
             output_width,
-            byte_order,
             chunk_index,
+            &self.limits,
+            &mut self.buffers,
         )?;
+        self.record_chunk(chunk_index, None, timer.map(|start| start.elapsed()));
 
         Ok(())
     }
 
-    fn result_buffer(&self, width: usize, height: usize) -> TiffResult<DecodingResult> {
-        let buffer_size = match width
-            .checked_mul(height)
-            .and_then(|x| x.checked_mul(self.image().bits_per_sample.len()))
-        {
-            Some(s) => s,
-            None => return Err(TiffError::LimitsExceeded),
+    /// Reads the data of `first` and the chunks after it that directly follow it in the file.
+    ///
+    /// Returns `None` if the next chunk isn't stored right after `first`, such chunks are read
+    /// on their own. Runs are limited in size, and only cover chunks before `end`.
+    fn read_chunk_run(&mut self, first: usize, end: usize) -> TiffResult<Option<(u64, Vec<u8>)>> {
+        let max_bytes = MAX_RUN_BYTES.min(self.limits.intermediate_buffer_size as u64);
+        let (start, length) = self.image().chunk_file_range(first as u32)?;
+        let mut run_end = match start.checked_add(length) {
+            Some(run_end) => run_end,
+            None => return Ok(None),
         };
+        let mut chunks = 1;
+        for chunk in first + 1..end {
+            if self.image().is_sparse_chunk(chunk as u32) {
+                break;
+            }
+            let (offset, length) = self.image().chunk_file_range(chunk as u32)?;
+            match offset.checked_add(length) {
+                Some(chunk_end) if offset == run_end && chunk_end - start <= max_bytes => {
+                    run_end = chunk_end;
+                    chunks += 1;
+                }
+                _ => break,
+            }
+        }
+        if chunks == 1 {
+            return Ok(None);
+        }
 
+        let file_length = self.file_length()?;
+        check_file_range(start, run_end - start, file_length)?;
+        self.goto_offset_u64(start)?;
+        let length = usize::try_from(run_end - start)?;
+        let mut data = self.buffers.take(length);
+        data.truncate(length);
+        self.reader.read_exact(&mut data)?;
+        Ok(Some((start, data)))
+    }
+
+    /// The value of the samples in sparse chunks, which GDAL writes without any data.
+    ///
+    /// Like GDAL, this is the nodata value of the image if it has one and zero otherwise. Samples
+    /// of less than a byte are always zero.
+    fn sparse_fill(&mut self) -> TiffResult<f64> {
+        if self.image().bits_per_sample.iter().any(|&bits| bits < 8) {
+            return Ok(0.0);
+        }
+        Ok(self.gdal_nodata()?.unwrap_or(0.0))
+    }
+
+    /// The type of the samples the image is decoded to.
+    fn sample_type(&self) -> TiffResult<SampleType> {
         let max_sample_bits = self
             .image()
             .bits_per_sample
@@ -1102,6 +2289,11 @@ impl<R: Read + Seek> Decoder<R> {
             .cloned()
             .max()
             .unwrap_or(8);
+        let unsupported_bits = |n| {
+            Err(TiffError::UnsupportedError(
+                TiffUnsupportedError::UnsupportedBitsPerChannel(n),
+            ))
+        };
         match self
             .image()
             .sample_format
@@ -1109,36 +2301,150 @@ impl<R: Read + Seek> Decoder<R> {
             .unwrap_or(&SampleFormat::Uint)
         {
             SampleFormat::Uint => match max_sample_bits {
-                n if n <= 8 => DecodingResult::new_u8(buffer_size, &self.limits),
-                n if n <= 16 => DecodingResult::new_u16(buffer_size, &self.limits),
-                n if n <= 32 => DecodingResult::new_u32(buffer_size, &self.limits),
-                n if n <= 64 => DecodingResult::new_u64(buffer_size, &self.limits),
-                n => Err(TiffError::UnsupportedError(
-                    TiffUnsupportedError::UnsupportedBitsPerChannel(n),
-                )),
+                n if n <= 8 => Ok(SampleType::U8),
+                n if n <= 16 => Ok(SampleType::U16),
+                n if n <= 32 => Ok(SampleType::U32),
+                n if n <= 64 => Ok(SampleType::U64),
+                n => unsupported_bits(n),
             },
             SampleFormat::IEEEFP => match max_sample_bits {
-                32 => DecodingResult::new_f32(buffer_size, &self.limits),
-                64 => DecodingResult::new_f64(buffer_size, &self.limits),
-                n => Err(TiffError::UnsupportedError(
-                    TiffUnsupportedError::UnsupportedBitsPerChannel(n),
-                )),
+                32 => Ok(SampleType::F32),
+                64 => Ok(SampleType::F64),
+                n => unsupported_bits(n),
             },
             SampleFormat::Int => match max_sample_bits {
-                n if n <= 8 => DecodingResult::new_i8(buffer_size, &self.limits),
-                n if n <= 16 => DecodingResult::new_i16(buffer_size, &self.limits),
-                n if n <= 32 => DecodingResult::new_i32(buffer_size, &self.limits),
-                n if n <= 64 => DecodingResult::new_i64(buffer_size, &self.limits),
-                n => Err(TiffError::UnsupportedError(
-                    TiffUnsupportedError::UnsupportedBitsPerChannel(n),
-                )),
+                n if n <= 8 => Ok(SampleType::I8),
+                n if n <= 16 => Ok(SampleType::I16),
+                n if n <= 32 => Ok(SampleType::I32),
+                n if n <= 64 => Ok(SampleType::I64),
+                n => unsupported_bits(n),
             },
-            format => {
-                Err(TiffUnsupportedError::UnsupportedSampleFormat(vec![format.clone()]).into())
-            }
+            format => Err(TiffUnsupportedError::UnsupportedSampleFormat(vec![*format]).into()),
+        }
+    }
+
+    fn result_buffer(&self, width: usize, height: usize) -> TiffResult<DecodingResult> {
+        let buffer_size = match self
+            .image()
+            .row_samples(width)
+            .ok()
+            .and_then(|x| x.checked_mul(height))
+        {
+            Some(s) => s,
+            None => return Err(TiffError::LimitsExceeded),
+        };
+
+        match self.sample_type()? {
+            SampleType::U8 => DecodingResult::new_u8(buffer_size, &self.limits),
+            SampleType::U16 => DecodingResult::new_u16(buffer_size, &self.limits),
+            SampleType::U32 => DecodingResult::new_u32(buffer_size, &self.limits),
+            SampleType::U64 => DecodingResult::new_u64(buffer_size, &self.limits),
+            SampleType::F32 => DecodingResult::new_f32(buffer_size, &self.limits),
+            SampleType::F64 => DecodingResult::new_f64(buffer_size, &self.limits),
+            SampleType::I8 => DecodingResult::new_i8(buffer_size, &self.limits),
+            SampleType::I16 => DecodingResult::new_i16(buffer_size, &self.limits),
+            SampleType::I32 => DecodingResult::new_i32(buffer_size, &self.limits),
+            SampleType::I64 => DecodingResult::new_i64(buffer_size, &self.limits),
         }
     }
 
+    /// Estimates the memory needed to decode the current image, without decoding it.
+    ///
+    /// The sizes of the results of `read_image` and `read_chunk` are exact, so buffers for
+    /// `read_image` can be allocated up front, and images that are too large to decode at once
+    /// can be decoded chunk by chunk instead. The memory used while decoding, such as buffers for
+    /// compressed data and the state of decompressors, and the memory the decoder keeps for
+    /// reuse are upper bounds. Together with the result, they bound the memory of decoding the
+    /// whole image. Unlike decoding, this
+    /// doesn't check the sizes against the `Limits` of the decoder, but fails for images that
+    /// can't be decoded at all.
+    pub fn estimate(&self) -> TiffResult<DecodingEstimate> {
+        let image = self.image();
+        image.check_predictor()?;
+        image.colortype()?;
+        let sample_type = self.sample_type()?;
+        let sample_bytes = sample_type.byte_len() as u64;
+
+        let width = image.width;
+        let height = image.height;
+        let row_samples = |pixels: u32| -> TiffResult<u64> {
+            Ok(image.row_samples(usize::try_from(pixels)?)? as u64)
+        };
+        let samples = row_samples(width)?.saturating_mul(u64::from(height));
+
+        let (chunk_width, chunk_height) = image.chunk_dimensions()?;
+        let chunk_buffer_bytes = row_samples(chunk_width.min(width))?
+            .saturating_mul(u64::from(chunk_height.min(height)))
+            .saturating_mul(sample_bytes);
+        // Including the padding of the chunk, which is decompressed too.
+        let chunk_row_bytes = row_samples(chunk_width)?.saturating_mul(sample_bytes);
+        let chunk_bytes = chunk_row_bytes.saturating_mul(u64::from(chunk_height));
+
+        let largest_chunk = image.chunk_bytes.iter().cloned().max().unwrap_or(0);
+        let all_chunks = image
+            .chunk_bytes
+            .iter()
+            .fold(0u64, |sum, &bytes| sum.saturating_add(bytes));
+        let run = if image.chunk_bytes.len() > 1 {
+            MAX_RUN_BYTES
+                .min(self.limits.intermediate_buffer_size as u64)
+                .min(all_chunks)
+        } else {
+            0
+        };
+        // `read_image_pipelined` holds the data of the current and the next chunk instead.
+        let compressed = run.max(largest_chunk.saturating_mul(2));
+
+        let decompression = match image.compression_method {
+            CompressionMethod::None => 0,
+            CompressionMethod::LZW
+            | CompressionMethod::PackBits
+            | CompressionMethod::Deflate
+            | CompressionMethod::OldDeflate => {
+                image::CHUNK_INPUT_BUFFER_SIZE.min(largest_chunk) + DECOMPRESSOR_STATE_BYTES
+            }
+            CompressionMethod::ModernJPEG => {
+                // The compressed data with the tables, the decoded chunk, and the component planes
+                // and coefficients of the JPEG decoder.
+                let tables = image.jpeg_tables.as_ref().map_or(0, |tables| tables.len());
+                largest_chunk
+                    .saturating_add(tables as u64)
+                    .saturating_add(chunk_bytes.saturating_mul(3))
+            }
+            method => {
+                return Err(TiffError::UnsupportedError(
+                    TiffUnsupportedError::UnsupportedCompressionMethod(method),
+                ))
+            }
+        };
+
+        // The floating point predictor copies each row, and decodes padded rows into a buffer.
+        let prediction = if image.predictor == Predictor::FloatingPoint {
+            #[cfg(feature = "rayon")]
+            let parallel_rows = rayon::current_num_threads() as u64;
+            #[cfg(not(feature = "rayon"))]
+            let parallel_rows = 1;
+            chunk_row_bytes.saturating_mul(parallel_rows + 1)
+        } else {
+            0
+        };
+
+        Ok(DecodingEstimate {
+            sample_type,
+            samples,
+            buffer_bytes: samples.saturating_mul(sample_bytes),
+            chunk_buffer_bytes,
+            intermediate_bytes: compressed
+                .saturating_add(decompression)
+                .saturating_add(prediction),
+            retained_bytes: (self.buffers.max_bytes() as u64).saturating_add(
+                self.chunk_cache
+                    .as_ref()
+                    .map_or(0, |cache| cache.capacity() as u64),
+            ),
+        })
+    }
+
     /// Read a single strip from the image and return it as a Vector. This method does not return
     /// information on the size of the strip read and so the caller must take care of the final
     /// strip in the image as the data read may be less than the expected strip size. This interface
@@ -1169,11 +2475,46 @@ impl<R: Read + Seek> Decoder<R> {
 
         let mut result = self.result_buffer(data_dims.0 as usize, data_dims.1 as usize)?;
 
+        // Checked before the cache lookup, so chunks are treated the same whether cached or not.
+        self.check_strict()?;
+        let key = (self.ifd_offset, chunk_index);
+        if let Some(cache) = self.chunk_cache.as_mut() {
+            let mut buffer = result.as_buffer(0);
+            let bytes = buffer.as_bytes_mut();
+            match cache.get(key) {
+                Some(data) if data.len() == bytes.len() => {
+                    bytes.copy_from_slice(data);
+                    return Ok(result);
+                }
+                _ => {}
+            }
+        }
+
         self.read_chunk_to_buffer(result.as_buffer(0), chunk_index, data_dims.0 as usize)?;
 
+        if let Some(cache) = self.chunk_cache.as_mut() {
+            cache.insert(key, result.as_buffer(0).as_bytes_mut());
+        }
+
         Ok(result)
     }
 
+    /// Read the raw bytes of the chunk at `chunk_index` exactly as they are stored in the file.
+    ///
+    /// The data is neither decompressed nor converted from the byte order of the file. This
+    /// allows copying chunks into another file without recompressing them.
+    pub fn read_chunk_bytes(&mut self, chunk_index: u32) -> TiffResult<Vec<u8>> {
+        let length = usize::try_from(self.image.chunk_file_range(chunk_index)?.1)?;
+        if length > self.limits.decoding_buffer_size {
+            return Err(TiffError::LimitsExceeded);
+        }
+
+        self.goto_chunk(chunk_index)?;
+        let mut buffer = vec![0; length];
+        self.reader.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
     /// Returns the default chunk size for the current image. Any given chunk in the image is at most as large as
     /// the value returned here. For the size of the data (chunk minus padding), use `chunk_data_dimensions`.
     pub fn chunk_dimensions(&self) -> (u32, u32) {
@@ -1188,13 +2529,31 @@ impl<R: Read + Seek> Decoder<R> {
             .expect("invalid chunk_index")
     }
 
-    /// Decodes the entire image and return it as a Vector
-    pub fn read_image(&mut self) -> TiffResult<DecodingResult> {
+    /// Returns the position of the top left pixel of the chunk with the specified index.
+    ///
+    /// Together with `chunk_data_dimensions`, this gives the region of the image that the chunk
+    /// covers. The layout of the chunks is resolved when the image is loaded, so this does not
+    /// read from the file.
+    pub fn chunk_origin(&self, chunk_index: u32) -> TiffResult<(u32, u32)> {
+        self.image().chunk_origin(chunk_index)
+    }
+
+    /// Returns the index of the chunk that holds the pixel at `x`, `y`.
+    ///
+    /// Returns `None` if the pixel is outside of the image, or the image lacks the chunk.
+    pub fn chunk_at(&self, x: u32, y: u32) -> Option<u32> {
+        self.image().chunk_at(x, y)
+    }
+
+    /// Allocates the result of `read_image` and checks that the image can be decoded.
+    ///
+    /// The layout is `None` for images without any pixels, whose result is already complete.
+    fn begin_image(&mut self) -> TiffResult<(DecodingResult, Option<ChunkLayout>)> {
         let width = self.image().width;
         let height = self.image().height;
         let mut result = self.result_buffer(width as usize, height as usize)?;
         if width == 0 || height == 0 {
-            return Ok(result);
+            return Ok((result, None));
         }
 
         let chunk_dimensions = self.image().chunk_dimensions()?;
@@ -1215,25 +2574,474 @@ impl<R: Read + Seek> Decoder<R> {
             ));
         }
 
-        let chunks_across = ((width - 1) / chunk_dimensions.0 + 1) as usize;
-        let strip_samples = width as usize * chunk_dimensions.1 as usize * samples;
+        // Report unsupported images and corrupt offsets before decoding any data.
+        self.check_strict()?;
+        self.image().check_predictor()?;
+        self.image().colortype()?;
+        let truncated_chunk = if self.recover_chunks {
+            None
+        } else {
+            self.check_chunk_ranges()?
+        };
 
-        for chunk in 0..self.image().chunk_offsets.len() {
-            self.goto_offset_u64(self.image().chunk_offsets[chunk])?;
+        let row_samples = self.image().row_samples(width as usize)?;
+        let layout = ChunkLayout {
+            width: width as usize,
+            height,
+            chunk_dimensions,
+            chunks_across: ((width - 1) / chunk_dimensions.0 + 1) as usize,
+            strip_samples: row_samples
+                .checked_mul(chunk_dimensions.1 as usize)
+                .ok_or(TiffError::IntSizeError)?,
+            image_samples: result.as_buffer(0).len(),
+            truncated_chunk,
+        };
+        Ok((result, Some(layout)))
+    }
 
-            let x = chunk % chunks_across;
-            let y = chunk / chunks_across;
-            let buffer_offset = y * strip_samples + x * chunk_dimensions.0 as usize * samples;
-            let byte_order = self.reader.byte_order;
-            self.image.expand_chunk(
-                &mut self.reader,
-                result.as_buffer(buffer_offset).copy(),
-                width as usize,
-                byte_order,
-                chunk as u32,
-            )?;
+    /// Decodes the entire image and return it as a Vector
+    pub fn read_image(&mut self) -> TiffResult<DecodingResult> {
+        let (mut result, layout) = self.begin_image()?;
+        let layout = match layout {
+            Some(layout) => layout,
+            None => return Ok(result),
+        };
+        let width = layout.width;
+
+        let chunks = layout.chunks(self.image());
+        // The data of consecutive chunks stored back to back, read at once.
+        let mut run: Option<(u64, Vec<u8>)> = None;
+        let mut bytes_read = 0u64;
+        for chunk in 0..chunks {
+            #[cfg(feature = "readahead")]
+            self.readahead(chunk, chunks);
+            let buffer_offset = layout.buffer_offset(self.image(), chunk)?;
+            if self.image().is_sparse_chunk(chunk as u32) {
+                let fill = self.sparse_fill()?;
+                self.image.fill_chunk(
+                    result.as_buffer(buffer_offset),
+                    width,
+                    chunk as u32,
+                    fill,
+                )?;
+                self.report_progress(chunk as u64 + 1, bytes_read)?;
+                continue;
+            }
+
+            let range = self.image().chunk_file_range(chunk as u32).ok();
+            let in_run = |run: &Option<(u64, Vec<u8>)>| {
+                let ((start, data), (offset, length)) = (run.as_ref()?, range?);
+                let begin = offset.checked_sub(*start)?;
+                let end = begin.checked_add(length)?;
+                if end <= data.len() as u64 {
+                    Some((begin as usize, end as usize))
+                } else {
+                    None
+                }
+            };
+            let mut read_time = None;
+            if in_run(&run).is_none() {
+                // A run that fails to read is decoded chunk by chunk, which reports the error of
+                // the chunk that is actually affected.
+                if let Some((_, data)) = run.take() {
+                    self.buffers.give(data);
+                }
+                let timer = self.chunk_timer();
+                run = self.read_chunk_run(chunk, chunks).unwrap_or(None);
+                read_time = timer.map(|start| start.elapsed());
+            }
+
+            let timer = self.chunk_timer();
+            let decoded = match (in_run(&run), &run) {
+                (Some((begin, end)), Some((_, data))) => self.image.expand_chunk(
+                    &data[begin..end],
+                    result.as_buffer(buffer_offset).copy(),
+                    width,
+                    chunk as u32,
+                    &self.limits,
+                    &mut self.buffers,
+                ),
+                _ => match self.goto_chunk(chunk as u32) {
+                    Ok(()) => self.image.expand_chunk(
+                        &mut self.reader,
+                        result.as_buffer(buffer_offset).copy(),
+                        width,
+                        chunk as u32,
+                        &self.limits,
+                        &mut self.buffers,
+                    ),
+                    Err(err) => Err(err),
+                },
+            };
+
+            match decoded {
+                Ok(()) => {
+                    let decode_time = timer.map(|start| start.elapsed());
+                    self.record_chunk(chunk as u32, read_time, decode_time)
+                }
+                Err(error @ TiffError::FormatError(_)) | Err(error @ TiffError::IoError(_))
+                    if self.recover_chunks =>
+                {
+                    self.image.fill_chunk(
+                        result.as_buffer(buffer_offset),
+                        width,
+                        chunk as u32,
+                        0.0,
+                    )?;
+                    self.warnings.push(DecodingWarning::CorruptChunk {
+                        chunk: chunk as u32,
+                        error,
+                    });
+                }
+                Err(err) => return Err(err),
+            }
+
+            bytes_read = bytes_read.saturating_add(range.map_or(0, |(_, length)| length));
+            self.report_progress(chunk as u64 + 1, bytes_read)?;
+        }
+        if let Some((_, data)) = run {
+            self.buffers.give(data);
+        }
+
+        if let Some(chunk) = layout.truncated_chunk {
+            // Only the rows of chunks before the truncated one across the whole width are complete.
+            let rows = u32::try_from(chunk / layout.chunks_across)
+                .unwrap_or(u32::MAX)
+                .saturating_mul(layout.chunk_dimensions.1)
+                .min(layout.height);
+            return Err(TiffError::Truncated {
+                rows,
+                partial: result,
+            });
         }
 
         Ok(result)
     }
 }
+
+/// Where the chunks of an image go in the result of `read_image`.
+struct ChunkLayout {
+    width: usize,
+    height: u32,
+    /// The dimensions of the chunks, limited to the size of the image.
+    chunk_dimensions: (u32, u32),
+    chunks_across: usize,
+    /// The number of samples of the rows of a row of chunks.
+    strip_samples: usize,
+    image_samples: usize,
+    /// The first chunk that is cut off by the end of the file.
+    truncated_chunk: Option<usize>,
+}
+
+impl ChunkLayout {
+    /// The number of chunks that can be decoded.
+    fn chunks(&self, image: &Image) -> usize {
+        self.truncated_chunk.unwrap_or(image.chunk_offsets.len())
+    }
+
+    /// The offset of the top left sample of `chunk` in the result.
+    fn buffer_offset(&self, image: &Image, chunk: usize) -> TiffResult<usize> {
+        let x = chunk % self.chunks_across;
+        let y = chunk / self.chunks_across;
+        let chunk_offset = image.row_samples(x * self.chunk_dimensions.0 as usize)?;
+        y.checked_mul(self.strip_samples)
+            .and_then(|offset| offset.checked_add(chunk_offset))
+            .filter(|&offset| offset <= self.image_samples)
+            .ok_or(TiffError::FormatError(
+                TiffFormatError::InconsistentSizesEncountered,
+            ))
+    }
+}
+
+impl<T: ReadAt + Sync> Decoder<PositionedReader<T>> {
+    /// Reads the chunks at `chunk_indices`, like `read_chunk` does for each of them.
+    ///
+    /// The data of every chunk is fetched with a positioned read of the source, rather than by
+    /// seeking the reader of the decoder. With the `rayon` feature, the chunks are fetched and
+    /// decoded concurrently, which hides the latency of each read for sources such as object
+    /// stores. Otherwise they are read one after another.
+    pub fn read_chunks(&mut self, chunk_indices: &[u32]) -> TiffResult<Vec<DecodingResult>> {
+        self.check_strict()?;
+        let mut results = chunk_indices
+            .iter()
+            .map(|&chunk_index| {
+                let data_dims = self.image().chunk_data_dimensions(chunk_index)?;
+                self.result_buffer(data_dims.0 as usize, data_dims.1 as usize)
+            })
+            .collect::<TiffResult<Vec<_>>>()?;
+        let fill = if chunk_indices
+            .iter()
+            .any(|&chunk_index| self.image().is_sparse_chunk(chunk_index))
+        {
+            self.sparse_fill()?
+        } else {
+            0.0
+        };
+
+        // Chunks in the cache are copied from it and not read again.
+        let cached = chunk_indices
+            .iter()
+            .zip(results.iter_mut())
+            .map(|(&chunk_index, result)| {
+                let cache = match self.chunk_cache.as_mut() {
+                    Some(cache) => cache,
+                    None => return false,
+                };
+                let mut buffer = result.as_buffer(0);
+                let bytes = buffer.as_bytes_mut();
+                match cache.get((self.ifd_offset, chunk_index)) {
+                    Some(data) if data.len() == bytes.len() => {
+                        bytes.copy_from_slice(data);
+                        true
+                    }
+                    _ => false,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let source = self.reader.get_ref().get_ref();
+        let file_length = source.size()?;
+        let image = &self.image;
+        let limits = &self.limits;
+        let record_chunks = self.record_chunks;
+        // Returns the number of bytes read and the reading and decoding times, if they are
+        // recorded.
+        let read_chunk =
+            |((&chunk_index, &cached), result): ((&u32, &bool), &mut DecodingResult)| {
+                if cached {
+                    return Ok((0, None, None));
+                }
+                let output_width = image.chunk_data_dimensions(chunk_index)?.0 as usize;
+                if image.is_sparse_chunk(chunk_index) {
+                    image.fill_chunk(result.as_buffer(0), output_width, chunk_index, fill)?;
+                    return Ok((0, None, None));
+                }
+
+                let (offset, length) = image.chunk_file_range(chunk_index)?;
+                check_file_range(offset, length, file_length)?;
+                let (data, read_time) = timed(record_chunks, || {
+                    fetch_chunk(source, image, limits, chunk_index)
+                })?;
+                let ((), decode_time) = timed(record_chunks, || {
+                    image.expand_chunk(
+                        &data[..],
+                        result.as_buffer(0),
+                        output_width,
+                        chunk_index,
+                        limits,
+                        &mut BufferPool::default(),
+                    )
+                })?;
+                Ok((data.len() as u64, read_time, decode_time))
+            };
+
+        #[cfg(feature = "rayon")]
+        let reads = {
+            use rayon::prelude::*;
+            chunk_indices
+                .par_iter()
+                .zip(cached.par_iter())
+                .zip(results.par_iter_mut())
+                .map(read_chunk)
+                .collect::<TiffResult<Vec<_>>>()?
+        };
+        #[cfg(not(feature = "rayon"))]
+        let reads = chunk_indices
+            .iter()
+            .zip(cached.iter())
+            .zip(results.iter_mut())
+            .map(read_chunk)
+            .collect::<TiffResult<Vec<_>>>()?;
+
+        for (&chunk_index, (bytes_read, read_time, decode_time)) in chunk_indices.iter().zip(reads)
+        {
+            self.statistics.bytes_read += bytes_read;
+            self.record_chunk(chunk_index, read_time, decode_time);
+        }
+        if let Some(cache) = self.chunk_cache.as_mut() {
+            let chunks = chunk_indices.iter().zip(&cached).zip(results.iter_mut());
+            for ((&chunk_index, &cached), result) in chunks {
+                if !cached {
+                    cache.insert(
+                        (self.ifd_offset, chunk_index),
+                        result.as_buffer(0).as_bytes_mut(),
+                    );
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// Decodes the entire image like `read_image`, reading the data of the next chunk while the
+    /// current one is decoded.
+    ///
+    /// With the `rayon` feature, the positioned read of each chunk overlaps with decompressing
+    /// the chunk before it, so that a slow source and the decoder are kept busy at the same time.
+    /// Only one chunk is read ahead, which bounds the additional memory to the compressed size of
+    /// a single chunk. Without the feature, this is equivalent to `read_image`.
+    pub fn read_image_pipelined(&mut self) -> TiffResult<DecodingResult> {
+        if self.recover_chunks {
+            return self.read_image();
+        }
+        let (mut result, layout) = self.begin_image()?;
+        let layout = match layout {
+            Some(layout) if layout.truncated_chunk.is_none() => layout,
+            Some(_) => return self.read_image(),
+            None => return Ok(result),
+        };
+
+        let chunks = layout.chunks(self.image());
+        let fill = if (0..chunks).any(|chunk| self.image().is_sparse_chunk(chunk as u32)) {
+            self.sparse_fill()?
+        } else {
+            0.0
+        };
+        let next_chunk = |image: &Image, chunk: usize| {
+            (chunk..chunks).find(|&chunk| !image.is_sparse_chunk(chunk as u32))
+        };
+
+        let record_chunks = self.record_chunks;
+        // The data of the next chunk to decode and the time it took to read it.
+        let mut pending = match next_chunk(self.image(), 0) {
+            Some(chunk) => Some(timed(record_chunks, || {
+                fetch_chunk(
+                    self.reader.get_ref().get_ref(),
+                    &self.image,
+                    &self.limits,
+                    chunk as u32,
+                )
+            })?),
+            None => None,
+        };
+        let mut bytes_read = 0u64;
+        for chunk in 0..chunks {
+            let buffer_offset = layout.buffer_offset(self.image(), chunk)?;
+            if self.image().is_sparse_chunk(chunk as u32) {
+                self.image.fill_chunk(
+                    result.as_buffer(buffer_offset),
+                    layout.width,
+                    chunk as u32,
+                    fill,
+                )?;
+                self.report_progress(chunk as u64 + 1, bytes_read)?;
+                continue;
+            }
+
+            let (data, read_time) = pending.take().ok_or(TiffError::FormatError(
+                TiffFormatError::InconsistentSizesEncountered,
+            ))?;
+            let next = next_chunk(self.image(), chunk + 1);
+            let source = self.reader.get_ref().get_ref();
+            let image = &self.image;
+            let limits = &self.limits;
+            let buffers = &mut self.buffers;
+            let fetch = || {
+                next.map(|next| {
+                    timed(record_chunks, || {
+                        fetch_chunk(source, image, limits, next as u32)
+                    })
+                })
+                .transpose()
+            };
+            let mut decode = || {
+                timed(record_chunks, || {
+                    image.expand_chunk(
+                        &data[..],
+                        result.as_buffer(buffer_offset),
+                        layout.width,
+                        chunk as u32,
+                        limits,
+                        buffers,
+                    )
+                })
+            };
+
+            #[cfg(feature = "rayon")]
+            let (fetched, decoded) = rayon::join(fetch, &mut decode);
+            #[cfg(not(feature = "rayon"))]
+            let (decoded, fetched) = (decode(), fetch());
+
+            let ((), decode_time) = decoded?;
+            pending = fetched?;
+            bytes_read += data.len() as u64;
+            self.statistics.bytes_read += data.len() as u64;
+            self.record_chunk(chunk as u32, read_time, decode_time);
+            self.report_progress(chunk as u64 + 1, bytes_read)?;
+        }
+
+        Ok(result)
+    }
+}
+
+/// Starts a timer if `enabled`.
+fn start_timer(enabled: bool) -> Option<Instant> {
+    if enabled {
+        Some(Instant::now())
+    } else {
+        None
+    }
+}
+
+/// Runs `f`, measuring how long it takes if `enabled`.
+fn timed<T>(enabled: bool, f: impl FnOnce() -> TiffResult<T>) -> TiffResult<(T, Option<Duration>)> {
+    let start = start_timer(enabled);
+    let value = f()?;
+    Ok((value, start.map(|start| start.elapsed())))
+}
+
+/// Reads the compressed data of `chunk_index` from `source` with a positioned read.
+fn fetch_chunk<T: ReadAt>(
+    source: &T,
+    image: &Image,
+    limits: &Limits,
+    chunk_index: u32,
+) -> TiffResult<Vec<u8>> {
+    let (offset, length) = image.chunk_file_range(chunk_index)?;
+    if length > limits.intermediate_buffer_size as u64 {
+        return Err(TiffError::LimitsExceeded);
+    }
+    let mut data = vec![0; usize::try_from(length)?];
+    source.read_exact_at(&mut data, offset)?;
+    Ok(data)
+}
+
+#[cfg(feature = "readahead")]
+impl Decoder<std::fs::File> {
+    /// Hints the operating system to read the strips or tiles of the file ahead of `read_image`.
+    ///
+    /// While an image is decoded, the ranges of the next few megabytes of chunks are announced
+    /// with `posix_fadvise`, so that the data is read from disk while earlier chunks are still
+    /// being decompressed. This speeds up decoding files that are not yet in the page cache. On
+    /// platforms without `posix_fadvise`, such as macOS and Windows, this has no effect.
+    pub fn with_readahead(mut self) -> Decoder<std::fs::File> {
+        self.readahead = Some(readahead::Readahead::new(self.reader.get_ref()));
+        self
+    }
+}
+
+impl<'a> Decoder<io::Cursor<&'a [u8]>> {
+    /// Borrow the data of the uncompressed chunk at `chunk_index` from the underlying slice.
+    ///
+    /// Unlike `read_chunk`, this does not copy the data, which avoids memory traffic for large
+    /// uncompressed images. A file can be decoded this way by memory mapping it and passing the
+    /// mapped bytes in a `Cursor`. The slice holds the rows of the chunk exactly as they are
+    /// stored, including any padding, in the byte order of the file. No photometric conversion is
+    /// applied, and the slice may not be aligned for samples wider than a byte.
+    ///
+    /// Only chunks without compression and without predictor can be borrowed.
+    pub fn chunk_slice(&self, chunk_index: u32) -> TiffResult<&'a [u8]> {
+        let image = self.image();
+        if image.compression_method != CompressionMethod::None || image.predictor != Predictor::None
+        {
+            return Err(UsageError::UncompressedChunkRequired.into());
+        }
+
+        let (offset, _) = image.chunk_file_range(chunk_index)?;
+        let length = image.uncompressed_chunk_bytes(chunk_index)?;
+        let data = *self.reader.get_ref().get_ref();
+        check_file_range(offset, length, data.len() as u64)?;
+
+        let start = usize::try_from(offset)?;
+        Ok(&data[start..start + usize::try_from(length)?])
+    }
+}
@@ -0,0 +1,197 @@
+//! OME-XML metadata of OME-TIFF files.
+
+use crate::tags::Tag;
+use crate::{TiffError, TiffFormatError, TiffResult};
+
+/// Namespace of the OME schemas, identifying an OME-XML description.
+const OME_NAMESPACE: &str = "http://www.openmicroscopy.org/Schemas/OME/";
+
+/// OME-XML metadata stored in the `ImageDescription` of the first directory of an OME-TIFF.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OmeMetadata {
+    /// The complete OME-XML document.
+    pub xml: String,
+    /// The dimensions of each image described by the document.
+    pub pixels: Vec<OmePixels>,
+}
+
+/// The dimensions of an image from the `Pixels` element of its OME-XML description.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OmePixels {
+    pub size_x: u32,
+    pub size_y: u32,
+    /// Number of channels.
+    pub size_c: u32,
+    /// Number of z-slices.
+    pub size_z: u32,
+    /// Number of time points.
+    pub size_t: u32,
+    /// Order of the dimensions from fastest to slowest varying, such as `XYZCT`.
+    pub dimension_order: String,
+    /// The pixel type such as `uint16`, if specified.
+    pub pixel_type: Option<String>,
+}
+
+impl OmeMetadata {
+    /// Parses the description if it is an OME-XML document, returns `Ok(None)` otherwise.
+    pub(crate) fn from_description(description: &str) -> TiffResult<Option<OmeMetadata>> {
+        if !description.contains(OME_NAMESPACE) {
+            return Ok(None);
+        }
+        let pixels = start_tags(description, "Pixels")
+            .map(OmePixels::from_attributes)
+            .collect::<TiffResult<_>>()?;
+        Ok(Some(OmeMetadata {
+            xml: description.into(),
+            pixels,
+        }))
+    }
+}
+
+impl OmePixels {
+    fn from_attributes(tag: &str) -> TiffResult<OmePixels> {
+        let invalid =
+            || TiffError::from(TiffFormatError::InvalidTagValueType(Tag::ImageDescription));
+        let size = |name: &str| -> TiffResult<u32> {
+            attribute(tag, name)
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(invalid)
+        };
+
+        let dimension_order = attribute(tag, "DimensionOrder").ok_or_else(invalid)?;
+        let mut sorted: Vec<u8> = dimension_order.bytes().collect();
+        sorted.sort_unstable();
+        if !dimension_order.starts_with("XY") || sorted != b"CTXYZ" {
+            return Err(invalid());
+        }
+
+        Ok(OmePixels {
+            size_x: size("SizeX")?,
+            size_y: size("SizeY")?,
+            size_c: size("SizeC")?,
+            size_z: size("SizeZ")?,
+            size_t: size("SizeT")?,
+            dimension_order: dimension_order.into(),
+            pixel_type: attribute(tag, "Type").map(Into::into),
+        })
+    }
+
+    /// The index of the plane with channel `c`, slice `z` and time point `t` among the planes
+    /// of this image, following the dimension order.
+    ///
+    /// Returns `None` if a coordinate is out of range. This assumes one plane per channel and
+    /// does not consider explicit `TiffData` mappings.
+    pub fn plane_index(&self, c: u32, z: u32, t: u32) -> Option<u64> {
+        if c >= self.size_c || z >= self.size_z || t >= self.size_t {
+            return None;
+        }
+        let mut index = 0;
+        let mut stride = 1;
+        for dimension in self.dimension_order.bytes().skip(2) {
+            let (coordinate, size) = match dimension {
+                b'C' => (c, self.size_c),
+                b'Z' => (z, self.size_z),
+                _ => (t, self.size_t),
+            };
+            index += u64::from(coordinate) * stride;
+            stride *= u64::from(size);
+        }
+        Some(index)
+    }
+}
+
+/// The attribute sections of all start tags with the given local name.
+fn start_tags<'a>(xml: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    xml.split('<').skip(1).filter_map(move |element| {
+        let end = element.find('>')?;
+        let element = &element[..end];
+        let name_end = element
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(element.len());
+        let tag_name = &element[..name_end];
+        // Ignore a namespace prefix.
+        let local_name = tag_name.rsplit(':').next().unwrap_or(tag_name);
+        if local_name == name {
+            Some(&element[name_end..])
+        } else {
+            None
+        }
+    })
+}
+
+/// The value of an attribute in the attribute section of a start tag.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        let equals = rest.find('=')?;
+        let key = rest[..equals].trim();
+        let value = rest[equals + 1..].trim_start();
+        let quote = value.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        let value = &value[1..];
+        let end = value.find(quote)?;
+        if key == name {
+            return Some(&value[..end]);
+        }
+        rest = &value[end + 1..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<OME xmlns="http://www.openmicroscopy.org/Schemas/OME/2016-06" Creator="test">
+  <Image ID="Image:0" Name="stack">
+    <Pixels ID="Pixels:0" DimensionOrder="XYZCT" Type='uint16' SizeX="64" SizeY="32"
+            SizeC="2" SizeZ="3" SizeT="4" PhysicalSizeX="0.5">
+      <Channel ID="Channel:0:0" SamplesPerPixel="1"/>
+      <TiffData/>
+    </Pixels>
+  </Image>
+</OME>"#;
+
+    #[test]
+    fn parse_pixels() {
+        let metadata = OmeMetadata::from_description(XML).unwrap().unwrap();
+        assert_eq!(
+            metadata.pixels,
+            [OmePixels {
+                size_x: 64,
+                size_y: 32,
+                size_c: 2,
+                size_z: 3,
+                size_t: 4,
+                dimension_order: "XYZCT".into(),
+                pixel_type: Some("uint16".into()),
+            }]
+        );
+
+        let pixels = &metadata.pixels[0];
+        assert_eq!(pixels.plane_index(0, 0, 0), Some(0));
+        assert_eq!(pixels.plane_index(0, 2, 0), Some(2));
+        assert_eq!(pixels.plane_index(1, 0, 0), Some(3));
+        assert_eq!(pixels.plane_index(1, 2, 3), Some(23));
+        assert_eq!(pixels.plane_index(2, 0, 0), None);
+    }
+
+    #[test]
+    fn prefixed_elements() {
+        let xml = r#"<ome:OME xmlns:ome="http://www.openmicroscopy.org/Schemas/OME/2016-06">
+<ome:Pixels DimensionOrder="XYCTZ" SizeX="1" SizeY="1" SizeC="3" SizeZ="2" SizeT="1"/>
+</ome:OME>"#;
+        let metadata = OmeMetadata::from_description(xml).unwrap().unwrap();
+        assert_eq!(metadata.pixels[0].plane_index(2, 1, 0), Some(5));
+        assert_eq!(metadata.pixels[0].pixel_type, None);
+    }
+
+    #[test]
+    fn reject_invalid_pixels() {
+        assert_eq!(OmeMetadata::from_description("<OME/>").unwrap(), None);
+        assert!(OmeMetadata::from_description(&XML.replace("XYZCT", "XYZCC")).is_err());
+        assert!(OmeMetadata::from_description(&XML.replace("SizeC=\"2\"", "")).is_err());
+    }
+}
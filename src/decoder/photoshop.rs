@@ -0,0 +1,101 @@
+//! Image resources embedded by Photoshop.
+
+use crate::tags::Tag;
+use crate::{TiffError, TiffFormatError, TiffResult};
+
+/// Signatures of image resource blocks, `8BIM` being by far the most common one.
+const SIGNATURES: [&[u8; 4]; 5] = [b"8BIM", b"MeSa", b"AgHg", b"PHUT", b"DCSR"];
+
+/// An image resource block, such as a clipping path, the resolution info or a thumbnail.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhotoshopResource {
+    /// The resource ID, e.g. 1005 for the resolution info or 1036 for the thumbnail.
+    pub id: u16,
+    /// The name of the resource, usually empty.
+    pub name: Vec<u8>,
+    /// The data of the resource.
+    pub data: Vec<u8>,
+}
+
+/// Splits the image resource section into its resource blocks.
+pub(crate) fn parse(mut data: &[u8]) -> TiffResult<Vec<PhotoshopResource>> {
+    let invalid = || TiffError::from(TiffFormatError::InvalidTagValueType(Tag::Photoshop));
+
+    let mut resources = Vec::new();
+    while !data.is_empty() {
+        let (id, rest) = match data {
+            [a, b, c, d, high, low, rest @ ..] if SIGNATURES.contains(&&[*a, *b, *c, *d]) => {
+                (u16::from_be_bytes([*high, *low]), rest)
+            }
+            _ if data.iter().all(|&b| b == 0) => break,
+            _ => return Err(invalid()),
+        };
+
+        // The name is a Pascal string padded to an even size.
+        let name_len = usize::from(*rest.first().ok_or_else(invalid)?);
+        let padded = (name_len + 2) & !1;
+        if rest.len() < padded + 4 {
+            return Err(invalid());
+        }
+        let name = rest[1..1 + name_len].to_vec();
+        let rest = &rest[padded..];
+
+        let size = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let rest = &rest[4..];
+        if rest.len() < size {
+            return Err(invalid());
+        }
+        resources.push(PhotoshopResource {
+            id,
+            name,
+            data: rest[..size].to_vec(),
+        });
+
+        // The data is padded to an even size as well, though the last pad byte may be missing.
+        data = rest.get((size + 1) & !1..).unwrap_or(&[]);
+    }
+    Ok(resources)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_resources() {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"8BIM\x03\xed\x00\x00\x00\x00\x00\x03abc\x00");
+        data.extend_from_slice(b"8BIM\x07\xd0\x04path\x00\x00\x00\x00\x02xy");
+        data.extend_from_slice(&[0, 0]);
+        assert_eq!(
+            parse(&data).unwrap(),
+            [
+                PhotoshopResource {
+                    id: 1005,
+                    name: Vec::new(),
+                    data: b"abc".to_vec(),
+                },
+                PhotoshopResource {
+                    id: 2000,
+                    name: b"path".to_vec(),
+                    data: b"xy".to_vec(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_truncated_resources() {
+        assert!(parse(b"8BIM\x03\xed\x00\x00\x00\x00\x00\x03ab").is_err());
+        assert!(parse(b"8BIM\x03\xed\x04ab").is_err());
+        assert!(parse(b"XXXX\x03\xed\x00\x00\x00\x00\x00\x00").is_err());
+    }
+
+    #[test]
+    fn missing_final_padding() {
+        let resources = parse(b"8BIM\x03\xed\x00\x00\x00\x00\x00\x01a").unwrap();
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].data, b"a");
+    }
+}
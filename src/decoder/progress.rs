@@ -0,0 +1,24 @@
+//! Progress reporting while image data is read.
+
+use std::fmt;
+
+/// The state of an image decode, passed to the callback set with
+/// [`Decoder::with_progress`](super::Decoder::with_progress).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// Number of strips or tiles decoded so far.
+    pub chunks_read: u64,
+    /// Total number of strips or tiles of the image.
+    pub total_chunks: u64,
+    /// Number of (compressed) image data bytes read so far.
+    pub bytes_read: u64,
+}
+
+/// Boxed progress callback, returning `false` to cancel the decode.
+pub(crate) struct ProgressCallback(pub(crate) Box<dyn FnMut(&Progress) -> bool + Send>);
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str("ProgressCallback")
+    }
+}
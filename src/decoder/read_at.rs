@@ -0,0 +1,163 @@
+//! Input based on reads at explicit offsets, which allow concurrent access.
+
+use std::convert::TryFrom;
+#[cfg(any(unix, windows))]
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+/// A source of data that is read at explicit offsets instead of from a cursor.
+///
+/// As reads don't move a shared position, they can be issued from several threads at once, e.g.
+/// to fetch the strips or tiles of an image concurrently with `Decoder::read_chunks`. Wrap a
+/// source in a `PositionedReader` to decode it.
+pub trait ReadAt {
+    /// Reads data starting at `offset` into `buf`, returning the number of bytes read.
+    ///
+    /// Like `Read::read`, this may read fewer bytes than requested, and returns 0 at the end of
+    /// the data.
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize>;
+
+    /// The total length of the data in bytes.
+    fn size(&self) -> io::Result<u64>;
+
+    /// Reads exactly enough data starting at `offset` to fill `buf`.
+    fn read_exact_at(&self, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.read_at(buf, offset) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "failed to fill whole buffer",
+                    ))
+                }
+                Ok(n) => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ReadAt for [u8] {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let start = match usize::try_from(offset) {
+            Ok(start) if start < self.len() => start,
+            _ => return Ok(0),
+        };
+        let length = buf.len().min(self.len() - start);
+        buf[..length].copy_from_slice(&self[start..start + length]);
+        Ok(length)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl ReadAt for Vec<u8> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self[..].read_at(buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+#[cfg(any(unix, windows))]
+impl ReadAt for File {
+    #[cfg(unix)]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        std::os::unix::fs::FileExt::read_at(self, buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        std::os::windows::fs::FileExt::seek_read(self, buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for &T {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for Arc<T> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+}
+
+/// Adapts a `ReadAt` source to `Read` and `Seek`, so it can be decoded.
+///
+/// The position is kept in the adapter, the source itself is only accessed with positioned reads.
+#[derive(Debug)]
+pub struct PositionedReader<T> {
+    inner: T,
+    position: u64,
+}
+
+impl<T: ReadAt> PositionedReader<T> {
+    /// Reads `inner` starting at offset zero.
+    pub fn new(inner: T) -> Self {
+        PositionedReader { inner, position: 0 }
+    }
+
+    /// The source of the data.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwraps the source of the data.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: ReadAt> Read for PositionedReader<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read_at(buf, self.position)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<T: ReadAt> Seek for PositionedReader<T> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) if delta >= 0 => self.position.checked_add(delta as u64),
+            SeekFrom::Current(delta) => self.position.checked_sub(delta.wrapping_neg() as u64),
+            SeekFrom::End(delta) if delta >= 0 => self.inner.size()?.checked_add(delta as u64),
+            SeekFrom::End(delta) => self.inner.size()?.checked_sub(delta.wrapping_neg() as u64),
+        };
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Readahead hints for decoding images from files.
+
+use std::fs::File;
+
+use super::image::Image;
+
+/// The number of bytes of upcoming chunks hinted at once.
+const READAHEAD_BYTES: u64 = 8 << 20;
+
+/// Tells the operating system which parts of a file the decoder reads next, so that it can
+/// fetch them from disk before they are needed.
+///
+/// Hints are given with `posix_fadvise` where it is available, and are ignored elsewhere.
+#[derive(Debug)]
+pub(crate) struct Readahead {
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    fd: std::os::unix::io::RawFd,
+    /// The first chunk that has not been hinted yet.
+    hinted: usize,
+    /// The chunk at which the next chunks are hinted.
+    refill: usize,
+}
+
+impl Readahead {
+    /// Creates hints for `file`, which has to outlive the hints.
+    #[cfg_attr(
+        not(any(target_os = "linux", target_os = "android", target_os = "freebsd")),
+        allow(unused_variables)
+    )]
+    pub(crate) fn new(file: &File) -> Readahead {
+        Readahead {
+            #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+            fd: std::os::unix::io::AsRawFd::as_raw_fd(file),
+            hinted: 0,
+            refill: 0,
+        }
+    }
+
+    /// Hints the chunks of `image` after `chunk` and before `end` once the decoder gets halfway
+    /// through the chunks hinted before. Decoding `chunk` 0 starts a new image.
+    pub(crate) fn advance(&mut self, image: &Image, chunk: usize, end: usize) {
+        if chunk == 0 {
+            self.hinted = 0;
+            self.refill = 0;
+        }
+        if chunk < self.refill {
+            return;
+        }
+
+        let first = self.hinted.max(chunk);
+        let mut next = first;
+        let mut bytes = 0u64;
+        // Chunks stored back to back are hinted as a single range.
+        let mut range: Option<(u64, u64)> = None;
+        while next < end && bytes < READAHEAD_BYTES {
+            if image.is_sparse_chunk(next as u32) {
+                next += 1;
+                continue;
+            }
+            if let Ok((offset, length)) = image.chunk_file_range(next as u32) {
+                let chunk_end = offset.saturating_add(length);
+                range = match range {
+                    Some((start, range_end)) if offset == range_end => Some((start, chunk_end)),
+                    Some((start, range_end)) => {
+                        self.will_need(start, range_end - start);
+                        Some((offset, chunk_end))
+                    }
+                    None => Some((offset, chunk_end)),
+                };
+                bytes = bytes.saturating_add(length);
+            }
+            next += 1;
+        }
+        if let Some((start, range_end)) = range {
+            self.will_need(start, range_end - start);
+        }
+
+        self.hinted = next;
+        self.refill = first + (next - first) / 2;
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    fn will_need(&self, offset: u64, length: u64) {
+        use std::convert::TryFrom;
+
+        let (offset, length) = match (libc::off_t::try_from(offset), libc::off_t::try_from(length))
+        {
+            (Ok(offset), Ok(length)) => (offset, length),
+            _ => return,
+        };
+        // The hint is only advisory, failing to give it costs nothing but the speedup.
+        unsafe {
+            libc::posix_fadvise(self.fd, offset, length, libc::POSIX_FADV_WILLNEED);
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+    fn will_need(&self, _offset: u64, _length: u64) {}
+}
@@ -0,0 +1,41 @@
+//! Counters collected while the file is read.
+
+use std::time::Duration;
+
+use crate::tags::CompressionMethod;
+
+/// I/O and decoding counters of a decoder, see
+/// [`Decoder::statistics`](super::Decoder::statistics).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DecodingStatistics {
+    /// Number of bytes read from the file, including metadata and data read ahead.
+    pub bytes_read: u64,
+    /// Number of seeks of the reader.
+    pub seeks: u64,
+    /// The strips or tiles decoded, in the order they were decoded.
+    ///
+    /// Only recorded if enabled with
+    /// [`Decoder::with_chunk_statistics`](super::Decoder::with_chunk_statistics). Chunks that
+    /// are not decoded from data in the file are left out: sparse chunks, chunks taken from the
+    /// chunk cache, and corrupt chunks that are filled in when recovering, which are reported as
+    /// warnings instead.
+    pub chunks: Vec<ChunkStatistics>,
+}
+
+/// The decoding of a single strip or tile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkStatistics {
+    /// The index of the chunk in its image.
+    pub chunk_index: u32,
+    /// The compression of the chunk.
+    pub compression: CompressionMethod,
+    /// The size of the chunk as stored in the file.
+    pub compressed_bytes: u64,
+    /// The time spent reading the chunk from the file before decoding it.
+    ///
+    /// Chunks stored back to back may be read at once, the time is then counted for the first of
+    /// them. This is zero for chunks whose data is read while they are decoded.
+    pub read_time: Duration,
+    /// The time spent decoding the chunk, including any reads of its data while decoding.
+    pub decode_time: Duration,
+}
@@ -1,9 +1,10 @@
 //! All IO functionality needed for TIFF decoding
 
-use std::convert::TryFrom;
-use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Take};
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Take};
 use std::sync::Arc;
 
+use crate::TiffError;
+
 /// Byte order of the TIFF file.
 #[derive(Clone, Copy, Debug)]
 pub enum ByteOrder {
@@ -126,36 +127,79 @@ pub trait EndianReader: Read {
 /// ## Deflate Reader
 ///
 
-pub type DeflateReader<R> = flate2::read::ZlibDecoder<R>;
+pub type DeflateReader<R> = flate2::bufread::ZlibDecoder<ChunkInput<R>>;
 
 ///
 /// ## LZW Reader
 ///
 
 /// Reader that decompresses LZW streams
+///
+/// Data is decompressed as it is read, only a buffer of the compressed input is kept.
 pub struct LZWReader<R: Read> {
-    reader: BufReader<Take<R>>,
+    reader: ChunkInput<R>,
     decoder: weezl::decode::Decoder,
+    remaining: usize,
 }
 
 impl<R: Read> LZWReader<R> {
     /// Wraps a reader
-    pub fn new(reader: R, compressed_length: usize) -> LZWReader<R> {
+    ///
+    /// At most `max_uncompressed_length` bytes are decompressed, reading beyond them fails with
+    /// an error that converts to `TiffError::LimitsExceeded` unless the stream ends there.
+    pub fn new(reader: ChunkInput<R>, max_uncompressed_length: usize) -> LZWReader<R> {
         Self {
-            reader: BufReader::with_capacity(
-                (32 * 1024).min(compressed_length),
-                reader.take(u64::try_from(compressed_length).unwrap()),
-            ),
+            reader,
             decoder: weezl::decode::Decoder::with_tiff_size_switch(weezl::BitOrder::Msb, 8),
+            remaining: max_uncompressed_length,
+        }
+    }
+
+    /// Takes back the buffer of the compressed input.
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.reader.into_buffer()
+    }
+
+    /// Checks that the stream ends once all of its expected output was read.
+    fn check_end(&mut self) -> io::Result<usize> {
+        let mut byte = [0];
+        loop {
+            let result = self
+                .decoder
+                .decode_bytes(self.reader.fill_buf()?, &mut byte);
+            self.reader.consume(result.consumed_in);
+
+            match result.status {
+                _ if result.consumed_out > 0 => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        TiffError::LimitsExceeded,
+                    ))
+                }
+                Ok(weezl::LzwStatus::Ok) => continue,
+                // Streams without an end code stop after their data.
+                Ok(weezl::LzwStatus::NoProgress) | Ok(weezl::LzwStatus::Done) => return Ok(0),
+                Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+            }
         }
     }
 }
 
 impl<R: Read> Read for LZWReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            return self.check_end();
+        }
+        let len = buf.len().min(self.remaining);
+        let buf = &mut buf[..len];
+
         loop {
             let result = self.decoder.decode_bytes(self.reader.fill_buf()?, buf);
             self.reader.consume(result.consumed_in);
+            self.remaining -= result.consumed_out;
 
             match result.status {
                 Ok(weezl::LzwStatus::Ok) => {
@@ -219,17 +263,12 @@ impl JpegReader {
 
         match jpeg_tables {
             Some(jpeg_tables) => {
-                assert!(
-                    jpeg_tables.len() >= 2,
-                    "jpeg_tables, if given, must be at least 2 bytes long. Got {:?}",
-                    jpeg_tables
-                );
-
-                assert!(
-                    length >= 2,
-                    "if jpeg_tables is given, length must be at least 2 bytes long, got {}",
-                    length
-                );
+                if jpeg_tables.len() < 2 || length < 2 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "jpeg_tables and the jpeg data must both hold at least a marker",
+                    ));
+                }
 
                 let mut buffer = io::Cursor::new(segment);
                 // Skip the first two bytes (marker bytes)
@@ -344,63 +383,209 @@ enum PackBitsReaderState {
 }
 
 /// Reader that unpacks Apple's `PackBits` format
+///
+/// The compressed data is read through a buffer, and each read unpacks as many runs as fit into
+/// the output.
 pub struct PackBitsReader<R: Read> {
-    reader: Take<R>,
+    reader: ChunkInput<R>,
     state: PackBitsReaderState,
     count: usize,
 }
 
 impl<R: Read> PackBitsReader<R> {
     /// Wraps a reader
-    pub fn new(reader: R, length: u64) -> Self {
+    pub fn new(reader: ChunkInput<R>) -> Self {
         Self {
-            reader: reader.take(length),
+            reader,
             state: PackBitsReaderState::Header,
             count: 0,
         }
     }
+
+    /// Takes back the buffer of the compressed input.
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.reader.into_buffer()
+    }
 }
 
 impl<R: Read> Read for PackBitsReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        while let PackBitsReaderState::Header = self.state {
-            if self.reader.limit() == 0 {
-                return Ok(0);
+        let mut written = 0;
+        while written < buf.len() {
+            let input = self.reader.fill_buf()?;
+
+            // Unpack the runs in the buffered input.
+            let mut consumed = 0;
+            while written < buf.len() {
+                let length = (buf.len() - written).min(self.count);
+                match self.state {
+                    PackBitsReaderState::Header => {
+                        let h = match input.get(consumed) {
+                            Some(&h) => h as i8,
+                            None => break,
+                        };
+                        if (-127..=-1).contains(&h) {
+                            // The value of the run may not have been buffered yet.
+                            let value = match input.get(consumed + 1) {
+                                Some(&value) => value,
+                                None => break,
+                            };
+                            consumed += 2;
+                            self.state = PackBitsReaderState::Repeat { value };
+                            self.count = (1 - h as isize) as usize;
+                        } else {
+                            consumed += 1;
+                            if h >= 0 {
+                                self.state = PackBitsReaderState::Literal;
+                                self.count = h as usize + 1;
+                            }
+                            // h = -128 is a no-op.
+                        }
+                        continue;
+                    }
+                    PackBitsReaderState::Literal => {
+                        let length = length.min(input.len() - consumed);
+                        if length == 0 {
+                            break;
+                        }
+                        buf[written..written + length]
+                            .copy_from_slice(&input[consumed..consumed + length]);
+                        consumed += length;
+                        written += length;
+                        self.count -= length;
+                    }
+                    PackBitsReaderState::Repeat { value } => {
+                        buf[written..written + length]
+                            .iter_mut()
+                            .for_each(|b| *b = value);
+                        written += length;
+                        self.count -= length;
+                    }
+                }
+                if self.count == 0 {
+                    self.state = PackBitsReaderState::Header;
+                }
             }
-            let mut header: [u8; 1] = [0];
-            self.reader.read_exact(&mut header)?;
-            let h = header[0] as i8;
-            if h >= -127 && h <= -1 {
-                let mut data: [u8; 1] = [0];
-                self.reader.read_exact(&mut data)?;
-                self.state = PackBitsReaderState::Repeat { value: data[0] };
+
+            let remaining = input.len() - consumed;
+            if written < buf.len() && remaining == 1 {
+                // Only the header of a repeated run is left in the buffer, read its value directly.
+                let h = input[consumed] as i8;
+                self.reader.consume(consumed + 1);
+                let mut value: [u8; 1] = [0];
+                self.reader.read_exact(&mut value)?;
+                self.state = PackBitsReaderState::Repeat { value: value[0] };
                 self.count = (1 - h as isize) as usize;
-            } else if h >= 0 {
-                self.state = PackBitsReaderState::Literal;
-                self.count = h as usize + 1;
+            } else if written < buf.len() && input.is_empty() {
+                break;
             } else {
-                // h = -128 is a no-op.
+                self.reader.consume(consumed);
             }
         }
+        Ok(written)
+    }
+}
 
-        let length = buf.len().min(self.count);
-        match self.state {
-            PackBitsReaderState::Literal => {
-                self.reader.read(&mut buf[..length])?;
-            }
-            PackBitsReaderState::Repeat { value } => {
-                for b in &mut buf[..length] {
-                    *b = value;
-                }
-            }
-            PackBitsReaderState::Header => unreachable!(),
+///
+/// ## Chunk Reader
+///
+
+/// Reader for the data of a chunk with any of the supported compression methods.
+///
+/// The data of a chunk is read with many calls, this dispatches them without boxing the reader.
+pub(crate) enum ChunkReader<R: Read> {
+    Uncompressed(R),
+    Lzw(LZWReader<R>),
+    PackBits(PackBitsReader<R>),
+    Deflate(DeflateReader<R>),
+    /// JPEG data is decoded in full up front.
+    Decoded(io::Cursor<Vec<u8>>),
+}
+
+impl<R: Read> ChunkReader<R> {
+    /// Takes back the buffer holding the compressed or decoded data, if any, for reuse.
+    pub(crate) fn into_buffer(self) -> Option<Vec<u8>> {
+        match self {
+            ChunkReader::Uncompressed(_) => None,
+            ChunkReader::Lzw(reader) => Some(reader.into_buffer()),
+            ChunkReader::PackBits(reader) => Some(reader.into_buffer()),
+            ChunkReader::Deflate(reader) => Some(reader.into_inner().into_buffer()),
+            ChunkReader::Decoded(reader) => Some(reader.into_inner()),
         }
+    }
+}
 
-        self.count -= length;
-        if self.count == 0 {
-            self.state = PackBitsReaderState::Header;
+impl<R: Read> Read for ChunkReader<R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            ChunkReader::Uncompressed(reader) => reader.read(buf),
+            ChunkReader::Lzw(reader) => reader.read(buf),
+            ChunkReader::PackBits(reader) => reader.read(buf),
+            ChunkReader::Deflate(reader) => reader.read(buf),
+            ChunkReader::Decoded(reader) => reader.read(buf),
         }
-        return Ok(length);
+    }
+}
+
+/// Buffered reader for the compressed data of a chunk.
+///
+/// Unlike `BufReader`, the buffer is passed in and can be taken back with `into_buffer`, so it
+/// can be reused for the next chunk.
+pub struct ChunkInput<R: Read> {
+    reader: Take<R>,
+    buffer: Vec<u8>,
+    pos: usize,
+    filled: usize,
+}
+
+impl<R: Read> ChunkInput<R> {
+    /// Reads at most `length` bytes from `reader`, in blocks of the length of `buffer`.
+    pub fn new(reader: R, length: u64, buffer: Vec<u8>) -> Self {
+        ChunkInput {
+            reader: reader.take(length),
+            buffer,
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    /// The buffered data that hasn't been consumed yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer[self.pos..self.filled]
+    }
+
+    /// Takes back the buffer.
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+impl<R: Read> Read for ChunkInput<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Large reads bypass the buffer when it is empty.
+        if self.pos == self.filled && buf.len() >= self.buffer.len() {
+            return self.reader.read(buf);
+        }
+        let available = self.fill_buf()?;
+        let length = available.len().min(buf.len());
+        buf[..length].copy_from_slice(&available[..length]);
+        self.consume(length);
+        Ok(length)
+    }
+}
+
+impl<R: Read> BufRead for ChunkInput<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.filled {
+            self.filled = self.reader.read(&mut self.buffer)?;
+            self.pos = 0;
+        }
+        Ok(&self.buffer[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
     }
 }
 
@@ -409,6 +594,9 @@ impl<R: Read> Read for PackBitsReader<R> {
 ///
 
 /// Reader that is aware of the byte order.
+///
+/// Reads can optionally be buffered, see `set_buffer_capacity`. Unlike `BufReader`, the buffer
+/// is kept when seeking to an offset within it.
 #[derive(Debug)]
 pub struct SmartReader<R>
 where
@@ -416,6 +604,18 @@ where
 {
     reader: R,
     pub byte_order: ByteOrder,
+    /// Data read ahead from `reader`, always empty when buffering is disabled.
+    buffer: Vec<u8>,
+    /// The read position within `buffer`.
+    pos: usize,
+    /// The offset of the start of `buffer` in the stream. `reader` is positioned at the end of
+    /// the buffer.
+    buffer_offset: u64,
+    capacity: usize,
+    /// Number of bytes read from `reader`.
+    bytes_read: u64,
+    /// Number of seeks of `reader` that may have moved its position.
+    seeks: u64,
 }
 
 impl<R> SmartReader<R>
@@ -424,16 +624,71 @@ where
 {
     /// Wraps a reader
     pub fn wrap(reader: R, byte_order: ByteOrder) -> SmartReader<R> {
-        SmartReader { reader, byte_order }
+        SmartReader {
+            reader,
+            byte_order,
+            buffer: Vec::new(),
+            pos: 0,
+            buffer_offset: 0,
+            capacity: 0,
+            bytes_read: 0,
+            seeks: 0,
+        }
     }
     pub fn into_inner(self) -> R {
         self.reader
     }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// The number of bytes read from the wrapped reader, including data read ahead.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// The number of seeks of the wrapped reader, not counting queries of the position.
+    pub fn seeks(&self) -> u64 {
+        self.seeks
+    }
+
+    /// Resets `bytes_read` and `seeks` to zero.
+    pub fn reset_counters(&mut self) {
+        self.bytes_read = 0;
+        self.seeks = 0;
+    }
+
+    fn read_inner(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.bytes_read += read as u64;
+        Ok(read)
+    }
 }
 impl<R: Read + Seek> SmartReader<R> {
     pub fn goto_offset(&mut self, offset: u64) -> io::Result<()> {
         self.seek(io::SeekFrom::Start(offset)).map(|_| ())
     }
+
+    fn seek_inner(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        if pos != io::SeekFrom::Current(0) {
+            self.seeks += 1;
+        }
+        self.reader.seek(pos)
+    }
+
+    /// Buffers reads in chunks of `capacity` bytes, or disables buffering if it is zero.
+    pub fn set_buffer_capacity(&mut self, capacity: usize) -> io::Result<()> {
+        let offset = self.seek(io::SeekFrom::Current(0))?;
+        if !self.buffer.is_empty() {
+            self.seek_inner(io::SeekFrom::Start(offset))?;
+        }
+        self.buffer = Vec::new();
+        self.pos = 0;
+        self.buffer_offset = offset;
+        self.capacity = capacity;
+        Ok(())
+    }
 }
 
 impl<R> EndianReader for SmartReader<R>
@@ -449,14 +704,76 @@ where
 impl<R: Read> Read for SmartReader<R> {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.reader.read(buf)
+        if self.capacity == 0 {
+            return self.read_inner(buf);
+        }
+
+        if self.pos == self.buffer.len() {
+            self.buffer_offset += self.buffer.len() as u64;
+            self.buffer.clear();
+            self.pos = 0;
+
+            // Reads at least as large as the buffer gain nothing from it.
+            if buf.len() >= self.capacity {
+                let read = self.read_inner(buf)?;
+                self.buffer_offset += read as u64;
+                return Ok(read);
+            }
+
+            self.buffer.resize(self.capacity, 0);
+            let read = match self.reader.read(&mut self.buffer) {
+                Ok(read) => read,
+                Err(err) => {
+                    self.buffer.clear();
+                    return Err(err);
+                }
+            };
+            self.bytes_read += read as u64;
+            self.buffer.truncate(read);
+        }
+
+        let available = &self.buffer[self.pos..];
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.pos += len;
+        Ok(len)
     }
 }
 
 impl<R: Read + Seek> Seek for SmartReader<R> {
     #[inline]
     fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
-        self.reader.seek(pos)
+        if self.capacity == 0 {
+            return self.seek_inner(pos);
+        }
+
+        let current = self.buffer_offset + self.pos as u64;
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) if delta >= 0 => current.checked_add(delta as u64),
+            SeekFrom::Current(delta) => current.checked_sub(delta.wrapping_neg() as u64),
+            SeekFrom::End(_) => None,
+        };
+        let buffer_end = self.buffer_offset + self.buffer.len() as u64;
+        let offset = match target {
+            Some(target) if target >= self.buffer_offset && target <= buffer_end => {
+                self.pos = (target - self.buffer_offset) as usize;
+                return Ok(target);
+            }
+            Some(target) => self.seek_inner(SeekFrom::Start(target))?,
+            None => match pos {
+                // The stream is positioned at the end of the buffer, not at the read position.
+                SeekFrom::Current(delta) => {
+                    let ahead = (self.buffer.len() - self.pos) as i64;
+                    self.seek_inner(SeekFrom::Current(delta - ahead))?
+                }
+                pos => self.seek_inner(pos)?,
+            },
+        };
+        self.buffer.clear();
+        self.pos = 0;
+        self.buffer_offset = offset;
+        Ok(offset)
     }
 }
 
@@ -473,7 +790,8 @@ mod test {
         let encoded_len = encoded.len();
 
         let buff = io::Cursor::new(encoded);
-        let mut decoder = PackBitsReader::new(buff, encoded_len as u64);
+        let mut decoder =
+            PackBitsReader::new(ChunkInput::new(buff, encoded_len as u64, vec![0; 8]));
 
         let mut decoded = Vec::new();
         decoder.read_to_end(&mut decoded).unwrap();
@@ -484,4 +802,105 @@ mod test {
         ];
         assert_eq!(decoded, expected);
     }
+
+    #[test]
+    fn test_packbits_partial_input() {
+        /// Returns a single byte per read, so runs are split across reads of the input.
+        struct ByteReader(io::Cursor<Vec<u8>>);
+
+        impl Read for ByteReader {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let len = buf.len().min(1);
+                self.0.read(&mut buf[..len])
+            }
+        }
+
+        let encoded = vec![0xFE, 0xAA, 0x80, 0x01, 0x2A, 0x22, 0xFF, 0x00];
+        let encoded_len = encoded.len();
+        let input = ChunkInput::new(
+            ByteReader(io::Cursor::new(encoded)),
+            encoded_len as u64,
+            vec![0; 8],
+        );
+        let mut decoder = PackBitsReader::new(input);
+
+        let mut decoded = Vec::new();
+        let mut buf = [0; 2];
+        loop {
+            match decoder.read(&mut buf).unwrap() {
+                0 => break,
+                n => decoded.extend_from_slice(&buf[..n]),
+            }
+        }
+        assert_eq!(decoded, [0xAA, 0xAA, 0xAA, 0x2A, 0x22, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_lzw_output_bound() {
+        let encoded = weezl::encode::Encoder::with_tiff_size_switch(weezl::BitOrder::Msb, 8)
+            .encode(&[0; 100_000])
+            .unwrap();
+        let encoded_len = encoded.len();
+
+        let input = ChunkInput::new(io::Cursor::new(encoded), encoded_len as u64, vec![0; 1024]);
+        let mut decoder = LZWReader::new(input, 1000);
+        let mut decoded = [0; 1000];
+        decoder.read_exact(&mut decoded).unwrap();
+        let error = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+        assert!(matches!(TiffError::from(error), TiffError::LimitsExceeded));
+
+        // A stream that ends with its expected output is read to the end.
+        let encoded = weezl::encode::Encoder::with_tiff_size_switch(weezl::BitOrder::Msb, 8)
+            .encode(&[0; 1000])
+            .unwrap();
+        let encoded_len = encoded.len();
+        let input = ChunkInput::new(io::Cursor::new(encoded), encoded_len as u64, vec![0; 1024]);
+        let mut decoder = LZWReader::new(input, 1000);
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, [0; 1000]);
+    }
+
+    #[test]
+    fn test_buffered_seeks() {
+        let data: Vec<u8> = (0..100).collect();
+        let mut reader = SmartReader::wrap(io::Cursor::new(data), ByteOrder::LittleEndian);
+        reader.goto_offset(10).unwrap();
+        reader.set_buffer_capacity(16).unwrap();
+
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [10, 11, 12, 13]);
+        // Within the buffer, only the read position moves.
+        reader.goto_offset(20).unwrap();
+        assert_eq!(reader.get_ref().position(), 26);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [20, 21, 22, 23]);
+        assert_eq!(reader.seek(SeekFrom::Current(-8)).unwrap(), 16);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [16, 17, 18, 19]);
+
+        // Reading across the end of the buffer refills it.
+        reader.goto_offset(24).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [24, 25, 26, 27]);
+        assert_eq!(reader.seek(SeekFrom::Current(2)).unwrap(), 30);
+        assert_eq!(reader.seek(SeekFrom::End(-2)).unwrap(), 98);
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, [98, 99]);
+
+        // Large reads bypass the buffer.
+        let mut large = [0; 32];
+        reader.goto_offset(50).unwrap();
+        reader.read_exact(&mut large).unwrap();
+        assert_eq!(large[0], 50);
+        assert_eq!(reader.seek(SeekFrom::Current(0)).unwrap(), 82);
+
+        // Disabling the buffer continues at the read position.
+        reader.read_exact(&mut buf).unwrap();
+        reader.set_buffer_capacity(0).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [86, 87, 88, 89]);
+    }
 }
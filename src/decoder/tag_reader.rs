@@ -17,7 +17,7 @@ pub(crate) struct TagReader<'a, R: Read + Seek> {
 impl<'a, R: Read + Seek> TagReader<'a, R> {
     pub(crate) fn find_tag(&mut self, tag: Tag) -> TiffResult<Option<Value>> {
         Ok(match self.ifd.get(&tag) {
-            Some(entry) => Some(entry.clone().val(self.limits, self.bigtiff, self.reader)?),
+            Some(entry) => Some(entry.val(self.limits, self.bigtiff, self.reader)?),
             None => None,
         })
     }
@@ -0,0 +1,93 @@
+//! Heuristic selection of compression and predictor from a sample of the image data.
+
+use std::borrow::Cow;
+
+use crate::tags::{CompressionMethod, Predictor};
+
+use super::predictor;
+
+/// Maximum number of rows that are inspected.
+const SAMPLE_ROWS: usize = 64;
+
+/// Entropy in bits per byte above which data is considered incompressible.
+const INCOMPRESSIBLE: f64 = 7.5;
+
+/// Entropy reduction in bits per byte required to prefer prediction.
+const PREDICTION_GAIN: f64 = 0.5;
+
+/// Choose a compression method and predictor for an image.
+///
+/// `bytes` are the native-endian image rows of `row_bytes` each. `predictor` is the predictor
+/// suitable for the sample format, or `Predictor::None` if none applies. The result is either
+/// no compression for incompressible data, LZW with `predictor` if prediction considerably
+/// lowers the byte entropy, or Deflate otherwise.
+pub(crate) fn choose(
+    bytes: &[u8],
+    row_bytes: usize,
+    samples: usize,
+    sample_bytes: usize,
+    predictor: Predictor,
+) -> (CompressionMethod, Predictor) {
+    let rows = bytes.len() / row_bytes.max(1);
+    if rows == 0 {
+        return (CompressionMethod::None, Predictor::None);
+    }
+
+    let step = (rows + SAMPLE_ROWS - 1) / SAMPLE_ROWS;
+    let sample: Vec<u8> = bytes
+        .chunks_exact(row_bytes)
+        .step_by(step)
+        .flatten()
+        .copied()
+        .collect();
+    let raw = entropy(&sample);
+    let predicted = if predictor == Predictor::None {
+        raw
+    } else {
+        let predicted = predictor::predict_rows(
+            predictor,
+            Cow::Borrowed(&sample),
+            row_bytes,
+            samples,
+            sample_bytes,
+        );
+        entropy(&predicted)
+    };
+
+    if raw.min(predicted) > INCOMPRESSIBLE {
+        (CompressionMethod::None, Predictor::None)
+    } else if predicted + PREDICTION_GAIN < raw {
+        (CompressionMethod::LZW, predictor)
+    } else {
+        (CompressionMethod::Deflate, Predictor::None)
+    }
+}
+
+/// Shannon entropy of the byte distribution in bits per byte.
+fn entropy(bytes: &[u8]) -> f64 {
+    let mut histogram = [0usize; 256];
+    for &byte in bytes {
+        histogram[usize::from(byte)] += 1;
+    }
+    let total = bytes.len() as f64;
+    histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entropy_bounds() {
+        assert_eq!(entropy(&[7; 100]), 0.0);
+        let all: Vec<u8> = (0..=255).collect();
+        assert!((entropy(&all) - 8.0).abs() < 1e-9);
+    }
+}
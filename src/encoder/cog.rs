@@ -0,0 +1,418 @@
+//! Encoder for Cloud Optimized GeoTIFFs.
+
+use std::{
+    collections::{btree_map, BTreeMap},
+    convert::TryFrom,
+    io::{self, Cursor, Seek, Write},
+    marker::PhantomData,
+    mem,
+};
+
+use crate::{
+    error::TiffResult,
+    tags::{PhotometricInterpretation, Tag},
+};
+
+use super::{
+    colortype::ColorType,
+    compression::{Compression, CompressionAlgorithm, Uncompressed},
+    overview, DirectoryEncoder, DirectoryEntry, TiffEncoder, TiffKind, TiffKindBig,
+    TiffKindStandard, TiffValue, REDUCED_RESOLUTION_SUBFILE,
+};
+
+/// Encoder for Cloud Optimized GeoTIFF (COG) files.
+///
+/// A COG is a tiled Tiff with optional overviews whose layout allows efficient access through
+/// HTTP range requests: the header is followed by the directories of all resolution levels and
+/// only then by the tile data, ordered from the smallest overview to the full resolution image.
+///
+/// Since all directories need to be known before any tile data is written, the complete image
+/// is compressed in memory before writing.
+///
+/// # Examples
+/// ```
+/// # extern crate tiff;
+/// # fn main() {
+/// # let mut file = std::io::Cursor::new(Vec::new());
+/// # let image_data = vec![0; 1000*1000*3];
+/// use tiff::encoder::*;
+///
+/// let mut cog = CogEncoder::new(&mut file);
+/// cog.tile_size(256, 256).unwrap();
+/// cog.write_image::<colortype::RGB8>(1000, 1000, &image_data).unwrap();
+/// # }
+/// ```
+pub struct CogEncoder<W, K: TiffKind = TiffKindStandard> {
+    writer: W,
+    tile_width: u32,
+    tile_length: u32,
+    overview_factors: Option<Vec<u32>>,
+    sparse: bool,
+    kind: PhantomData<K>,
+}
+
+impl<W: Write + Seek> CogEncoder<W> {
+    /// Creates a new encoder for standard Tiff files.
+    pub fn new(writer: W) -> CogEncoder<W, TiffKindStandard> {
+        CogEncoder::new_generic(writer)
+    }
+}
+
+impl<W: Write + Seek> CogEncoder<W, TiffKindBig> {
+    /// Creates a new encoder for BigTiff files.
+    pub fn new_big(writer: W) -> Self {
+        CogEncoder::new_generic(writer)
+    }
+}
+
+impl<W: Write + Seek, K: TiffKind> CogEncoder<W, K> {
+    /// Creates a new Tiff or BigTiff encoder, inferred from the return type.
+    ///
+    /// The default tile size is 256x256, overviews are generated automatically.
+    pub fn new_generic(writer: W) -> Self {
+        CogEncoder {
+            writer,
+            tile_width: 256,
+            tile_length: 256,
+            overview_factors: None,
+            sparse: false,
+            kind: PhantomData,
+        }
+    }
+
+    /// Set the tile size.
+    ///
+    /// Both dimensions must be a non-zero multiple of 16 as required by the specification.
+    pub fn tile_size(&mut self, width: u32, length: u32) -> TiffResult<()> {
+        super::check_tile_size(width, length)?;
+        self.tile_width = width;
+        self.tile_length = length;
+        Ok(())
+    }
+
+    /// Set the reduction factors of the overviews, e.g. `&[2, 4, 8]`.
+    ///
+    /// Factors must be at least 2 and strictly increasing. By default, overviews are halved
+    /// in size until they fit into a single tile.
+    pub fn overviews(&mut self, factors: &[u32]) -> TiffResult<()> {
+        let increasing = factors.windows(2).all(|pair| pair[0] < pair[1]);
+        if !increasing || factors.iter().any(|&factor| factor < 2) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Overview factors must be at least 2 and strictly increasing",
+            )
+            .into());
+        }
+        self.overview_factors = Some(factors.to_vec());
+        Ok(())
+    }
+
+    /// Omit tiles that only contain zeros.
+    ///
+    /// Omitted tiles are written with an offset and byte count of zero, following the
+    /// convention established by GDAL. Readers fill such tiles with the nodata value if one is
+    /// defined, or zero otherwise. This is disabled by default.
+    pub fn sparse(&mut self, sparse: bool) {
+        self.sparse = sparse;
+    }
+
+    /// Write an uncompressed image with its overviews.
+    pub fn write_image<C: ColorType>(
+        self,
+        width: u32,
+        height: u32,
+        data: &[C::Inner],
+    ) -> TiffResult<()>
+    where
+        C::Inner: Copy,
+        [C::Inner]: TiffValue,
+    {
+        self.write_image_with_compression::<C, _>(width, height, Uncompressed, data)
+    }
+
+    /// Write an image with its overviews with a given compression.
+    pub fn write_image_with_compression<C: ColorType, D: Compression>(
+        self,
+        width: u32,
+        height: u32,
+        compression: D,
+        data: &[C::Inner],
+    ) -> TiffResult<()>
+    where
+        C::Inner: Copy,
+        [C::Inner]: TiffValue,
+    {
+        self.write_image_with_tags::<C, D, _>(width, height, compression, data, |_| Ok(()))
+    }
+
+    /// Write an image with its overviews with a given compression and additional tags.
+    ///
+    /// `tags` is called to write additional tags, such as GeoTIFF tags, for the directory of
+    /// the full resolution image. Tags describing the image layout are always written by the
+    /// encoder and can not be overridden.
+    pub fn write_image_with_tags<C: ColorType, D: Compression, F>(
+        self,
+        width: u32,
+        height: u32,
+        compression: D,
+        data: &[C::Inner],
+        tags: F,
+    ) -> TiffResult<()>
+    where
+        C::Inner: Copy,
+        [C::Inner]: TiffValue,
+        F: FnOnce(&mut DirectoryEncoder<Cursor<Vec<u8>>, K>) -> TiffResult<()>,
+    {
+        let samples = C::BITS_PER_SAMPLE.len();
+        let (width_usize, height_usize) = (usize::try_from(width)?, usize::try_from(height)?);
+        let num_samples = width_usize
+            .checked_mul(height_usize)
+            .and_then(|pixels| pixels.checked_mul(samples))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Image width * height exceeds usize",
+                )
+            })?;
+        if data.len() < num_samples {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Input data slice is undersized for provided dimensions",
+            )
+            .into());
+        }
+
+        let factors = match self.overview_factors {
+            Some(ref factors) => factors.clone(),
+            None => self.automatic_overviews(width, height),
+        };
+
+        let mut levels = vec![Level::new(
+            width,
+            height,
+            self.compress_tiles::<C, D>(
+                &data[..num_samples],
+                width_usize,
+                height_usize,
+                &compression,
+            )?,
+        )];
+        for &factor in &factors {
+            let factor = usize::try_from(factor)?;
+            let overview = overview::downsample(data, width_usize, height_usize, samples, factor);
+            let (overview_width, overview_height) =
+                overview::overview_dimensions(width_usize, height_usize, factor);
+            levels.push(Level::new(
+                u32::try_from(overview_width)?,
+                u32::try_from(overview_height)?,
+                self.compress_tiles::<C, D>(
+                    &overview,
+                    overview_width,
+                    overview_height,
+                    &compression,
+                )?,
+            ));
+        }
+
+        let tile_size = (self.tile_width, self.tile_length);
+        let extra_tags = {
+            let mut scratch = TiffEncoder::<_, K>::new_generic(Cursor::new(Vec::new()))?;
+            let mut encoder = scratch.new_directory()?;
+            tags(&mut encoder)?;
+            mem::take(&mut encoder.ifd)
+                .into_iter()
+                .map(|(tag, entry)| {
+                    let entry = DirectoryEntry {
+                        data_type: entry.data_type,
+                        count: entry.count.into(),
+                        data: entry.data,
+                    };
+                    (tag, entry)
+                })
+                .collect()
+        };
+
+        // The size of the directories does not depend on the offsets stored in them. A dry run
+        // with placeholder offsets therefore determines where the tile data starts.
+        let mut dry_run = TiffEncoder::<_, K>::new_generic(Cursor::new(Vec::new()))?;
+        Self::write_directories::<_, C, D>(&mut dry_run, tile_size, &levels, &extra_tags)?;
+        let data_start = dry_run.writer.offset();
+
+        // Tile data is ordered from the smallest overview to the full resolution image.
+        let mut offset = data_start;
+        for level in levels.iter_mut().rev() {
+            for (tile, tile_offset) in level.tiles.iter().zip(&mut level.offsets) {
+                // Omitted tiles keep an offset of zero.
+                if !tile.is_empty() {
+                    *tile_offset = offset;
+                    offset += tile.len() as u64;
+                }
+            }
+        }
+
+        let mut tiff = TiffEncoder::<_, K>::new_generic(self.writer)?;
+        Self::write_directories::<_, C, D>(&mut tiff, tile_size, &levels, &extra_tags)?;
+        debug_assert_eq!(tiff.writer.offset(), data_start);
+
+        for level in levels.iter().rev() {
+            for tile in &level.tiles {
+                tiff.writer.write_bytes(tile)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Halve the image until it fits into a single tile.
+    fn automatic_overviews(&self, width: u32, height: u32) -> Vec<u32> {
+        let mut factors = Vec::new();
+        let mut factor = 2u32;
+        let (mut level_width, mut level_height) = (width, height);
+        while level_width > self.tile_width || level_height > self.tile_length {
+            factors.push(factor);
+            level_width = width / factor + u32::from(width % factor != 0);
+            level_height = height / factor + u32::from(height % factor != 0);
+            factor = match factor.checked_mul(2) {
+                Some(factor) => factor,
+                None => break,
+            };
+        }
+        factors
+    }
+
+    /// Split an image into tiles and compress each of them.
+    ///
+    /// Tiles on the right and bottom edge are padded with zeros. In sparse mode, tiles that only
+    /// contain zeros are returned empty. With the `rayon` feature, tiles are compressed in
+    /// parallel.
+    fn compress_tiles<C: ColorType, D: Compression>(
+        &self,
+        data: &[C::Inner],
+        width: usize,
+        height: usize,
+        compression: &D,
+    ) -> TiffResult<Vec<Vec<u8>>>
+    where
+        [C::Inner]: TiffValue,
+    {
+        let bytes = data.data();
+        let pixel_bytes = usize::from(<[C::Inner]>::BYTE_LEN) * C::BITS_PER_SAMPLE.len();
+        let tile_width = usize::try_from(self.tile_width)?;
+        let tile_length = usize::try_from(self.tile_length)?;
+        let tiles_across = (width + tile_width - 1) / tile_width;
+        let tiles_down = (height + tile_length - 1) / tile_length;
+        let compressor = compression.get_algorithm();
+        let sparse = self.sparse;
+
+        let tiles = super::compress_chunks(tiles_across * tiles_down, |index| {
+            let tile = super::extract_tile(
+                &bytes,
+                (width, height),
+                pixel_bytes,
+                (tile_width, tile_length),
+                index,
+            );
+
+            let mut compressed = Vec::new();
+            if !(sparse && tile.iter().all(|&byte| byte == 0)) {
+                compressor.clone().write_to(&mut compressed, &tile)?;
+            }
+            Ok(compressed)
+        })?;
+        Ok(tiles)
+    }
+
+    fn write_directories<V: Write + Seek, C: ColorType, D: Compression>(
+        tiff: &mut TiffEncoder<V, K>,
+        (tile_width, tile_length): (u32, u32),
+        levels: &[Level],
+        extra_tags: &BTreeMap<u16, DirectoryEntry<u64>>,
+    ) -> TiffResult<()> {
+        for (index, level) in levels.iter().enumerate() {
+            let mut encoder = tiff.new_directory()?;
+            if index > 0 {
+                encoder.write_tag(Tag::NewSubfileType, REDUCED_RESOLUTION_SUBFILE)?;
+            }
+            encoder.write_tag(Tag::ImageWidth, level.width)?;
+            encoder.write_tag(Tag::ImageLength, level.height)?;
+            encoder.write_tag(Tag::Compression, D::COMPRESSION_METHOD.to_u16())?;
+            encoder.write_tag(Tag::BitsPerSample, C::BITS_PER_SAMPLE)?;
+            let sample_format: Vec<_> = C::SAMPLE_FORMAT.iter().map(|s| s.to_u16()).collect();
+            encoder.write_tag(Tag::SampleFormat, &sample_format[..])?;
+            encoder.write_tag(Tag::PhotometricInterpretation, C::TIFF_VALUE.to_u16())?;
+            encoder.write_tag(
+                Tag::SamplesPerPixel,
+                u16::try_from(C::BITS_PER_SAMPLE.len())?,
+            )?;
+            if !C::EXTRA_SAMPLES.is_empty() {
+                let extra_samples: Vec<_> = C::EXTRA_SAMPLES.iter().map(|s| s.to_u16()).collect();
+                encoder.write_tag(Tag::ExtraSamples, &extra_samples[..])?;
+            }
+            if C::TIFF_VALUE == PhotometricInterpretation::YCbCr {
+                encoder.write_tag(Tag::YCbCrSubSampling, &[1u16, 1][..])?;
+            }
+            encoder.write_tag(Tag::TileWidth, tile_width)?;
+            encoder.write_tag(Tag::TileLength, tile_length)?;
+
+            let tile_offsets = level
+                .offsets
+                .iter()
+                .map(|&offset| K::convert_offset(offset))
+                .collect::<TiffResult<Vec<_>>>()?;
+            let tile_byte_counts = level
+                .tiles
+                .iter()
+                .map(|tile| K::convert_offset(tile.len() as u64))
+                .collect::<TiffResult<Vec<_>>>()?;
+            encoder.write_tag(Tag::TileOffsets, K::convert_slice(&tile_offsets))?;
+            encoder.write_tag(Tag::TileByteCounts, K::convert_slice(&tile_byte_counts))?;
+
+            if index == 0 {
+                for (&tag, entry) in extra_tags {
+                    if let btree_map::Entry::Vacant(vacant) = encoder.ifd.entry(tag) {
+                        vacant.insert(DirectoryEntry {
+                            data_type: entry.data_type,
+                            count: K::convert_offset(entry.count)?,
+                            data: entry.data.clone(),
+                        });
+                    }
+                }
+            }
+
+            encoder.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// A compressed resolution level.
+struct Level {
+    width: u32,
+    height: u32,
+    tiles: Vec<Vec<u8>>,
+    offsets: Vec<u64>,
+}
+
+impl Level {
+    fn new(width: u32, height: u32, tiles: Vec<Vec<u8>>) -> Self {
+        let offsets = vec![0; tiles.len()];
+        Level {
+            width,
+            height,
+            tiles,
+            offsets,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn automatic_overviews_of_widest_image() {
+        let cog = CogEncoder::new(Cursor::new(Vec::new()));
+        let factors = cog.automatic_overviews(u32::MAX, 1);
+        assert_eq!(factors.len(), 24);
+        assert_eq!(factors.last(), Some(&(1 << 24)));
+    }
+}
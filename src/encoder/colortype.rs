@@ -1,4 +1,4 @@
-use crate::tags::{PhotometricInterpretation, SampleFormat};
+use crate::tags::{ExtraSamples, PhotometricInterpretation, SampleFormat};
 
 /// Trait for different colortypes that can be encoded.
 pub trait ColorType {
@@ -10,6 +10,9 @@ pub trait ColorType {
     const BITS_PER_SAMPLE: &'static [u16];
     /// The value of the tiff tag `SampleFormat`
     const SAMPLE_FORMAT: &'static [SampleFormat];
+    /// The default value of the tiff tag `ExtraSamples`, one entry per sample beyond those
+    /// implied by `TIFF_VALUE`
+    const EXTRA_SAMPLES: &'static [ExtraSamples] = &[];
 }
 
 pub struct Gray8;
@@ -92,6 +95,60 @@ impl ColorType for Gray64Float {
     const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::IEEEFP];
 }
 
+pub struct GrayA8;
+impl ColorType for GrayA8 {
+    type Inner = u8;
+    const TIFF_VALUE: PhotometricInterpretation = PhotometricInterpretation::BlackIsZero;
+    const BITS_PER_SAMPLE: &'static [u16] = &[8, 8];
+    const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::Uint; 2];
+    const EXTRA_SAMPLES: &'static [ExtraSamples] = &[ExtraSamples::UnassociatedAlpha];
+}
+
+pub struct GrayA16;
+impl ColorType for GrayA16 {
+    type Inner = u16;
+    const TIFF_VALUE: PhotometricInterpretation = PhotometricInterpretation::BlackIsZero;
+    const BITS_PER_SAMPLE: &'static [u16] = &[16, 16];
+    const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::Uint; 2];
+    const EXTRA_SAMPLES: &'static [ExtraSamples] = &[ExtraSamples::UnassociatedAlpha];
+}
+
+pub struct GrayA32;
+impl ColorType for GrayA32 {
+    type Inner = u32;
+    const TIFF_VALUE: PhotometricInterpretation = PhotometricInterpretation::BlackIsZero;
+    const BITS_PER_SAMPLE: &'static [u16] = &[32, 32];
+    const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::Uint; 2];
+    const EXTRA_SAMPLES: &'static [ExtraSamples] = &[ExtraSamples::UnassociatedAlpha];
+}
+
+pub struct GrayA32Float;
+impl ColorType for GrayA32Float {
+    type Inner = f32;
+    const TIFF_VALUE: PhotometricInterpretation = PhotometricInterpretation::BlackIsZero;
+    const BITS_PER_SAMPLE: &'static [u16] = &[32, 32];
+    const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::IEEEFP; 2];
+    const EXTRA_SAMPLES: &'static [ExtraSamples] = &[ExtraSamples::UnassociatedAlpha];
+}
+
+pub struct GrayA64;
+impl ColorType for GrayA64 {
+    type Inner = u64;
+    const TIFF_VALUE: PhotometricInterpretation = PhotometricInterpretation::BlackIsZero;
+    const BITS_PER_SAMPLE: &'static [u16] = &[64, 64];
+    const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::Uint; 2];
+    const EXTRA_SAMPLES: &'static [ExtraSamples] = &[ExtraSamples::UnassociatedAlpha];
+}
+
+pub struct GrayA64Float;
+impl ColorType for GrayA64Float {
+    type Inner = f64;
+    const TIFF_VALUE: PhotometricInterpretation = PhotometricInterpretation::BlackIsZero;
+    const BITS_PER_SAMPLE: &'static [u16] = &[64, 64];
+    const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::IEEEFP; 2];
+    const EXTRA_SAMPLES: &'static [ExtraSamples] = &[ExtraSamples::UnassociatedAlpha];
+}
+
 pub struct RGB8;
 impl ColorType for RGB8 {
     type Inner = u8;
@@ -146,6 +203,7 @@ impl ColorType for RGBA8 {
     const TIFF_VALUE: PhotometricInterpretation = PhotometricInterpretation::RGB;
     const BITS_PER_SAMPLE: &'static [u16] = &[8, 8, 8, 8];
     const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::Uint; 4];
+    const EXTRA_SAMPLES: &'static [ExtraSamples] = &[ExtraSamples::UnassociatedAlpha];
 }
 
 pub struct RGBA16;
@@ -154,6 +212,7 @@ impl ColorType for RGBA16 {
     const TIFF_VALUE: PhotometricInterpretation = PhotometricInterpretation::RGB;
     const BITS_PER_SAMPLE: &'static [u16] = &[16, 16, 16, 16];
     const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::Uint; 4];
+    const EXTRA_SAMPLES: &'static [ExtraSamples] = &[ExtraSamples::UnassociatedAlpha];
 }
 
 pub struct RGBA32;
@@ -162,6 +221,7 @@ impl ColorType for RGBA32 {
     const TIFF_VALUE: PhotometricInterpretation = PhotometricInterpretation::RGB;
     const BITS_PER_SAMPLE: &'static [u16] = &[32, 32, 32, 32];
     const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::Uint; 4];
+    const EXTRA_SAMPLES: &'static [ExtraSamples] = &[ExtraSamples::UnassociatedAlpha];
 }
 
 pub struct RGBA32Float;
@@ -170,6 +230,7 @@ impl ColorType for RGBA32Float {
     const TIFF_VALUE: PhotometricInterpretation = PhotometricInterpretation::RGB;
     const BITS_PER_SAMPLE: &'static [u16] = &[32, 32, 32, 32];
     const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::IEEEFP; 4];
+    const EXTRA_SAMPLES: &'static [ExtraSamples] = &[ExtraSamples::UnassociatedAlpha];
 }
 
 pub struct RGBA64;
@@ -178,6 +239,7 @@ impl ColorType for RGBA64 {
     const TIFF_VALUE: PhotometricInterpretation = PhotometricInterpretation::RGB;
     const BITS_PER_SAMPLE: &'static [u16] = &[64, 64, 64, 64];
     const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::Uint; 4];
+    const EXTRA_SAMPLES: &'static [ExtraSamples] = &[ExtraSamples::UnassociatedAlpha];
 }
 
 pub struct RGBA64Float;
@@ -186,6 +248,7 @@ impl ColorType for RGBA64Float {
     const TIFF_VALUE: PhotometricInterpretation = PhotometricInterpretation::RGB;
     const BITS_PER_SAMPLE: &'static [u16] = &[64, 64, 64, 64];
     const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::IEEEFP; 4];
+    const EXTRA_SAMPLES: &'static [ExtraSamples] = &[ExtraSamples::UnassociatedAlpha];
 }
 
 pub struct CMYK8;
@@ -235,3 +298,12 @@ impl ColorType for CMYK64Float {
     const BITS_PER_SAMPLE: &'static [u16] = &[64, 64, 64, 64];
     const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::IEEEFP; 4];
 }
+
+/// 8-bit YCbCr, see `ImageBuilder::ycbcr_subsampling` to subsample the chroma channels.
+pub struct YCbCr8;
+impl ColorType for YCbCr8 {
+    type Inner = u8;
+    const TIFF_VALUE: PhotometricInterpretation = PhotometricInterpretation::YCbCr;
+    const BITS_PER_SAMPLE: &'static [u16] = &[8, 8, 8];
+    const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::Uint; 3];
+}
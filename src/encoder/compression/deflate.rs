@@ -34,6 +34,27 @@ impl Deflate {
             level: FlateCompression::new(level as u32),
         }
     }
+
+    /// Create a new deflate compressor with a numeric level of compression.
+    ///
+    /// Levels range from 0 (no compression) to 9 (best compression), other values are
+    /// rejected.
+    pub fn with_numeric_level(level: u8) -> Result<Self, io::Error> {
+        if level > 9 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Deflate level must be between 0 and 9",
+            ));
+        }
+        Ok(Self {
+            level: FlateCompression::new(u32::from(level)),
+        })
+    }
+
+    /// The numeric level of compression.
+    pub fn level(&self) -> u8 {
+        self.level.level() as u8
+    }
 }
 
 impl Default for Deflate {
@@ -80,4 +101,22 @@ mod tests {
         Deflate::default().write_to(&mut writer, TEST_DATA).unwrap();
         assert_eq!(EXPECTED_COMPRESSED_DATA, compressed_data.as_slice());
     }
+
+    #[test]
+    fn test_deflate_numeric_level() {
+        assert_eq!(Deflate::default().level(), DeflateLevel::Balanced as u8);
+        assert!(Deflate::with_numeric_level(10).is_err());
+
+        let mut stored = Vec::new();
+        let mut best = Vec::new();
+        Deflate::with_numeric_level(0)
+            .unwrap()
+            .write_to(&mut stored, TEST_DATA)
+            .unwrap();
+        Deflate::with_numeric_level(9)
+            .unwrap()
+            .write_to(&mut best, TEST_DATA)
+            .unwrap();
+        assert!(best.len() < stored.len());
+    }
 }
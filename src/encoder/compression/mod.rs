@@ -28,6 +28,7 @@ pub trait Compression: CompressionAlgorithm {
 }
 
 /// An enum to store each compression algorithm.
+#[derive(Clone)]
 pub enum Compressor {
     Uncompressed(Uncompressed),
     Lzw(Lzw),
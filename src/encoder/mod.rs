@@ -1,24 +1,40 @@
+pub use cog::CogEncoder;
+pub use progress::Progress;
+pub use stream::{StreamEncoder, StreamWriter};
 pub use tiff_value::*;
 
 use std::{
+    borrow::Cow,
     cmp,
     collections::BTreeMap,
     convert::{TryFrom, TryInto},
-    io::{self, Seek, Write},
+    io::{self, Read, Seek, Write},
     marker::PhantomData,
     mem,
     num::TryFromIntError,
 };
 
 use crate::{
-    error::TiffResult,
-    tags::{CompressionMethod, ResolutionUnit, Tag},
+    decoder::{ByteOrder, ChunkType, Decoder},
+    error::{TiffError, TiffResult, TiffUnsupportedError},
+    tags::{
+        CompressionMethod, ExtraSamples, Orientation, PhotometricInterpretation, Predictor,
+        ResolutionUnit, SampleFormat, Tag, Type,
+    },
 };
 
+mod auto;
+mod cog;
 pub mod colortype;
 pub mod compression;
+mod overview;
+mod predictor;
+mod progress;
+mod stream;
 mod tiff_value;
+mod verify;
 mod writer;
+mod ycbcr;
 
 use self::colortype::*;
 use self::compression::*;
@@ -31,6 +47,10 @@ use self::writer::*;
 ///
 /// See `DirectoryEncoder` and `ImageEncoder`.
 ///
+/// The output only depends on the input: tags are written in ascending order, data is laid out
+/// in the order it is written and padding is zeroed. Encoding the same image with the same
+/// settings therefore produces byte-identical files, also when compressing in parallel.
+///
 /// # Examples
 /// ```
 /// # extern crate tiff;
@@ -51,6 +71,9 @@ use self::writer::*;
 /// ```
 pub struct TiffEncoder<W, K: TiffKind = TiffKindStandard> {
     writer: TiffWriter<W>,
+    // Position of the offset field the next directory is linked from, if it is not the last
+    // word written. This is the case after opening a file for appending.
+    ifd_pointer_pos: Option<u64>,
     kind: PhantomData<K>,
 }
 
@@ -65,6 +88,15 @@ impl<W: Write + Seek> TiffEncoder<W> {
     }
 }
 
+impl<W: Read + Write + Seek> TiffEncoder<W> {
+    /// Opens an existing standard Tiff file to append new images to it.
+    ///
+    /// See [`append_generic`][TiffEncoder::append_generic].
+    pub fn append(writer: W) -> TiffResult<TiffEncoder<W, TiffKindStandard>> {
+        TiffEncoder::append_generic(writer)
+    }
+}
+
 /// Constructor functions to create BigTiff files.
 impl<W: Write + Seek> TiffEncoder<W, TiffKindBig> {
     /// Creates a new encoder for BigTiff files.
@@ -76,12 +108,66 @@ impl<W: Write + Seek> TiffEncoder<W, TiffKindBig> {
     }
 }
 
+impl<W: Read + Write + Seek> TiffEncoder<W, TiffKindBig> {
+    /// Opens an existing BigTiff file to append new images to it.
+    ///
+    /// See [`append_generic`][TiffEncoder::append_generic].
+    pub fn append_big(writer: W) -> TiffResult<Self> {
+        TiffEncoder::append_generic(writer)
+    }
+}
+
+/// Generic functions to append to existing Tiff and BigTiff files.
+impl<W: Read + Write + Seek, K: TiffKind> TiffEncoder<W, K> {
+    /// Opens an existing Tiff or BigTiff file to append new images to it, inferred from the
+    /// return type.
+    ///
+    /// The existing IFD chain is followed to its end, new directories are written at the end
+    /// of the file and linked from the last existing directory. Nothing else in the file is
+    /// rewritten. The file must be in native byte order, as written by this encoder.
+    pub fn append_generic(mut writer: W) -> TiffResult<Self> {
+        let bigtiff = mem::size_of::<K::OffsetType>() == 8;
+        let ifd_pointer_pos = find_last_ifd_pointer(&mut writer, bigtiff)?;
+        let end = writer.seek(io::SeekFrom::End(0))?;
+
+        let mut writer = TiffWriter::new(writer);
+        writer.goto_offset(end)?;
+
+        Ok(TiffEncoder {
+            writer,
+            ifd_pointer_pos: Some(ifd_pointer_pos),
+            kind: PhantomData,
+        })
+    }
+
+    /// Re-read the written file and check that it is consistent.
+    ///
+    /// This walks the IFD chain and validates the strip or tile offsets and byte counts of every
+    /// image against the data in the file: all chunks must be present, lie within the file
+    /// without overlapping each other and, if uncompressed, have the size implied by the image
+    /// dimensions. It is meant to catch encoder bugs before a corrupt file is passed on and is
+    /// only useful once all images are finished.
+    pub fn verify(&mut self) -> TiffResult<()> {
+        let offset = self.writer.offset();
+        let len = self.writer.inner_mut().seek(io::SeekFrom::End(0))?;
+        let header_len = if mem::size_of::<K::OffsetType>() == 8 {
+            16
+        } else {
+            8
+        };
+        let result = verify::verify(self.writer.inner_mut(), len, header_len);
+        self.writer.goto_offset(offset)?;
+        result
+    }
+}
+
 /// Generic functions that are available for both Tiff and BigTiff encoders.
 impl<W: Write + Seek, K: TiffKind> TiffEncoder<W, K> {
     /// Creates a new Tiff or BigTiff encoder, inferred from the return type.
     pub fn new_generic(writer: W) -> TiffResult<Self> {
         let mut encoder = TiffEncoder {
             writer: TiffWriter::new(writer),
+            ifd_pointer_pos: None,
             kind: PhantomData,
         };
 
@@ -92,7 +178,11 @@ impl<W: Write + Seek, K: TiffKind> TiffEncoder<W, K> {
 
     /// Create a [`DirectoryEncoder`] to encode an ifd directory.
     pub fn new_directory(&mut self) -> TiffResult<DirectoryEncoder<W, K>> {
-        DirectoryEncoder::new(&mut self.writer)
+        let mut encoder = DirectoryEncoder::new(&mut self.writer)?;
+        if let Some(ifd_pointer_pos) = self.ifd_pointer_pos.take() {
+            encoder.ifd_pointer_pos = Some(ifd_pointer_pos);
+        }
+        Ok(encoder)
     }
 
     /// Create an [`ImageEncoder`] to encode an image one slice at a time.
@@ -101,7 +191,7 @@ impl<W: Write + Seek, K: TiffKind> TiffEncoder<W, K> {
         width: u32,
         height: u32,
     ) -> TiffResult<ImageEncoder<W, C, K, Uncompressed>> {
-        let encoder = DirectoryEncoder::new(&mut self.writer)?;
+        let encoder = self.new_directory()?;
         ImageEncoder::new(encoder, width, height)
     }
 
@@ -112,10 +202,32 @@ impl<W: Write + Seek, K: TiffKind> TiffEncoder<W, K> {
         height: u32,
         compression: D,
     ) -> TiffResult<ImageEncoder<W, C, K, D>> {
-        let encoder = DirectoryEncoder::new(&mut self.writer)?;
+        let encoder = self.new_directory()?;
         ImageEncoder::with_compression(encoder, width, height, compression)
     }
 
+    /// Create an [`ImageBuilder`] to configure the layout of an image before encoding it.
+    ///
+    /// Unlike the other constructors, the builder validates the complete configuration before
+    /// anything is written, see [`ImageBuilder::build`].
+    pub fn image_builder<C: ColorType>(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> ImageBuilder<'_, W, C, K, Uncompressed> {
+        ImageBuilder {
+            encoder: self,
+            width,
+            height,
+            compression: Uncompressed,
+            predictor: Predictor::None,
+            tile_size: None,
+            rows_per_strip: None,
+            ycbcr_subsampling: None,
+            _phantom: PhantomData,
+        }
+    }
+
     /// Convenience function to write an entire image from memory.
     pub fn write_image<C: ColorType>(
         &mut self,
@@ -126,7 +238,7 @@ impl<W: Write + Seek, K: TiffKind> TiffEncoder<W, K> {
     where
         [C::Inner]: TiffValue,
     {
-        let encoder = DirectoryEncoder::new(&mut self.writer)?;
+        let encoder = self.new_directory()?;
         let image: ImageEncoder<W, C, K> = ImageEncoder::new(encoder, width, height)?;
         image.write_data(data)
     }
@@ -142,13 +254,252 @@ impl<W: Write + Seek, K: TiffKind> TiffEncoder<W, K> {
     where
         [C::Inner]: TiffValue,
     {
-        let encoder = DirectoryEncoder::new(&mut self.writer)?;
+        let encoder = self.new_directory()?;
         let image: ImageEncoder<W, C, K, D> =
             ImageEncoder::with_compression(encoder, width, height, compression)?;
         image.write_data(data)
     }
+
+    /// Convenience function to write an entire image, choosing the compression automatically.
+    ///
+    /// A sample of the rows is analyzed to pick one of: no compression for data that does not
+    /// compress (e.g. noise), LZW with a predictor if the predictor considerably reduces the
+    /// entropy (e.g. smooth gradients and high bit depths), or Deflate otherwise.
+    pub fn write_image_auto<C: ColorType>(
+        &mut self,
+        width: u32,
+        height: u32,
+        data: &[C::Inner],
+    ) -> TiffResult<()>
+    where
+        [C::Inner]: TiffValue,
+    {
+        let samples = C::BITS_PER_SAMPLE.len();
+        let row_samples = usize::try_from(width)?
+            .checked_mul(samples)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Image width * samples exceeds usize",
+                )
+            })?;
+        let image_samples = row_samples
+            .checked_mul(usize::try_from(height)?)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Image width * height * samples exceeds usize",
+                )
+            })?;
+        let sample_bytes = usize::from(<C::Inner>::BYTE_LEN);
+
+        let predictor = if C::SAMPLE_FORMAT
+            .iter()
+            .all(|&format| format == SampleFormat::IEEEFP)
+        {
+            Predictor::FloatingPoint
+        } else if C::SAMPLE_FORMAT
+            .iter()
+            .all(|&format| format == SampleFormat::Uint || format == SampleFormat::Int)
+        {
+            Predictor::Horizontal
+        } else {
+            Predictor::None
+        };
+        let bytes = data[..image_samples.min(data.len())].data();
+        let choice = auto::choose(
+            &bytes,
+            row_samples * sample_bytes,
+            samples,
+            sample_bytes,
+            predictor,
+        );
+
+        let builder = self.image_builder::<C>(width, height);
+        match choice {
+            (CompressionMethod::LZW, predictor) => builder
+                .compression(Lzw)
+                .predictor(predictor)
+                .build()?
+                .write_data(data),
+            (CompressionMethod::Deflate, _) => builder
+                .compression(Deflate::default())
+                .build()?
+                .write_data(data),
+            _ => builder.build()?.write_data(data),
+        }
+    }
+
+    /// Convenience function to write an image followed by its overviews.
+    ///
+    /// Each entry of `factors` produces one reduced-resolution page, e.g. `&[2, 4, 8]` writes
+    /// overviews at half, quarter and eighth resolution. The overviews are written as
+    /// subsequent pages with `NewSubfileType` set to mark them as reduced-resolution
+    /// versions of the full image, as expected by GDAL and other pyramid-aware readers.
+    ///
+    /// Overviews are computed by nearest neighbour sampling.
+    pub fn write_image_with_overviews<C: ColorType>(
+        &mut self,
+        width: u32,
+        height: u32,
+        data: &[C::Inner],
+        factors: &[u32],
+    ) -> TiffResult<()>
+    where
+        C::Inner: Copy,
+        [C::Inner]: TiffValue,
+    {
+        self.write_image_with_overviews_and_compression::<C, _>(
+            width,
+            height,
+            Uncompressed,
+            data,
+            factors,
+        )
+    }
+
+    /// Convenience function to write an image followed by its overviews with a given
+    /// compression.
+    ///
+    /// See `write_image_with_overviews`.
+    pub fn write_image_with_overviews_and_compression<C: ColorType, D: Compression + Clone>(
+        &mut self,
+        width: u32,
+        height: u32,
+        compression: D,
+        data: &[C::Inner],
+        factors: &[u32],
+    ) -> TiffResult<()>
+    where
+        C::Inner: Copy,
+        [C::Inner]: TiffValue,
+    {
+        if factors.iter().any(|&factor| factor < 2) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Overview factors must be at least 2",
+            )
+            .into());
+        }
+
+        self.write_image_with_compression::<C, D>(width, height, compression.clone(), data)?;
+
+        let samples = C::BITS_PER_SAMPLE.len();
+        for &factor in factors {
+            let factor = usize::try_from(factor)?;
+            let (width, height) = (usize::try_from(width)?, usize::try_from(height)?);
+            let overview = overview::downsample(data, width, height, samples, factor);
+            let (overview_width, overview_height) =
+                overview::overview_dimensions(width, height, factor);
+
+            let encoder = self.new_directory()?;
+            let mut image: ImageEncoder<W, C, K, D> = ImageEncoder::with_compression(
+                encoder,
+                u32::try_from(overview_width)?,
+                u32::try_from(overview_height)?,
+                compression.clone(),
+            )?;
+            image
+                .encoder()
+                .write_tag(Tag::NewSubfileType, REDUCED_RESOLUTION_SUBFILE)?;
+            image.write_data(&overview)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check that tile dimensions are a non-zero multiple of 16 as required by the specification.
+fn check_tile_size(width: u32, length: u32) -> TiffResult<()> {
+    if width == 0 || length == 0 || width % 16 != 0 || length % 16 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Tile dimensions must be a non-zero multiple of 16",
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Copy the tile with the given `index` out of an image, padding it with zeros at the right
+/// and bottom edge.
+fn extract_tile(
+    bytes: &[u8],
+    (width, height): (usize, usize),
+    pixel_bytes: usize,
+    (tile_width, tile_length): (usize, usize),
+    index: usize,
+) -> Vec<u8> {
+    let tiles_across = (width + tile_width - 1) / tile_width;
+    let tile_x = (index % tiles_across) * tile_width;
+    let tile_y = (index / tiles_across) * tile_length;
+    let copy_bytes = tile_width.min(width - tile_x) * pixel_bytes;
+    let copy_length = tile_length.min(height - tile_y);
+    let tile_row_bytes = tile_width * pixel_bytes;
+
+    let mut tile = vec![0; tile_row_bytes * tile_length];
+    for (row, tile_row) in tile
+        .chunks_exact_mut(tile_row_bytes)
+        .take(copy_length)
+        .enumerate()
+    {
+        let start = ((tile_y + row) * width + tile_x) * pixel_bytes;
+        tile_row[..copy_bytes].copy_from_slice(&bytes[start..start + copy_bytes]);
+    }
+    tile
 }
 
+/// Compress `count` independent chunks, in parallel if the `rayon` feature is enabled.
+///
+/// The compressed chunks are returned in order.
+fn compress_chunks<F>(count: usize, compress: F) -> io::Result<Vec<Vec<u8>>>
+where
+    F: Fn(usize) -> io::Result<Vec<u8>> + Send + Sync,
+{
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        (0..count).into_par_iter().map(compress).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        (0..count).map(compress).collect()
+    }
+}
+
+/// `NewSubfileType` bit marking a reduced-resolution version of another image.
+const REDUCED_RESOLUTION_SUBFILE: u32 = 1;
+
+/// Tags describing the layout of the image data, which are never copied by
+/// `DirectoryEncoder::copy_metadata`.
+const IMAGE_STRUCTURE_TAGS: &[Tag] = &[
+    Tag::NewSubfileType,
+    Tag::SubfileType,
+    Tag::ImageWidth,
+    Tag::ImageLength,
+    Tag::BitsPerSample,
+    Tag::Compression,
+    Tag::PhotometricInterpretation,
+    Tag::StripOffsets,
+    Tag::SamplesPerPixel,
+    Tag::RowsPerStrip,
+    Tag::StripByteCounts,
+    Tag::PlanarConfiguration,
+    Tag::FreeOffsets,
+    Tag::FreeByteCounts,
+    Tag::Predictor,
+    Tag::ColorMap,
+    Tag::TileWidth,
+    Tag::TileLength,
+    Tag::TileOffsets,
+    Tag::TileByteCounts,
+    Tag::SubIfd,
+    Tag::ExtraSamples,
+    Tag::SampleFormat,
+    Tag::JPEGTables,
+    Tag::YCbCrSubSampling,
+    Tag::InteropDirectory,
+];
+
 /// Low level interface to encode ifd directories.
 ///
 /// You should call `finish` on this when you are finished with it.
@@ -157,7 +508,7 @@ pub struct DirectoryEncoder<'a, W: 'a + Write + Seek, K: TiffKind> {
     writer: &'a mut TiffWriter<W>,
     dropped: bool,
     // We use BTreeMap to make sure tags are written in correct order
-    ifd_pointer_pos: u64,
+    ifd_pointer_pos: Option<u64>,
     ifd: BTreeMap<u16, DirectoryEntry<K::OffsetType>>,
 }
 
@@ -169,7 +520,20 @@ impl<'a, W: 'a + Write + Seek, K: TiffKind> DirectoryEncoder<'a, W, K> {
         Ok(DirectoryEncoder {
             writer,
             dropped: false,
-            ifd_pointer_pos,
+            ifd_pointer_pos: Some(ifd_pointer_pos),
+            ifd: BTreeMap::new(),
+        })
+    }
+
+    /// Create a directory that is not part of the main IFD chain.
+    ///
+    /// The caller is responsible for linking to it, e.g. through an offset tag.
+    fn new_sub_directory(writer: &'a mut TiffWriter<W>) -> TiffResult<Self> {
+        writer.pad_word_boundary()?;
+        Ok(DirectoryEncoder {
+            writer,
+            dropped: false,
+            ifd_pointer_pos: None,
             ifd: BTreeMap::new(),
         })
     }
@@ -237,6 +601,235 @@ impl<'a, W: 'a + Write + Seek, K: TiffKind> DirectoryEncoder<'a, W, K> {
         Ok(offset)
     }
 
+    /// Write a sub-directory and link it from this directory with `tag`.
+    ///
+    /// This is how private IFDs such as the EXIF directory are written. The sub-directory is
+    /// populated by `f` and written out as soon as `f` returns; its offset is stored in `tag` and
+    /// also returned.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate tiff;
+    /// # fn main() {
+    /// # let mut file = std::io::Cursor::new(Vec::new());
+    /// use tiff::encoder::*;
+    /// use tiff::tags::Tag;
+    ///
+    /// let mut tiff = TiffEncoder::new(&mut file).unwrap();
+    /// let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+    /// image
+    ///     .encoder()
+    ///     .write_sub_directory(Tag::ExifDirectory, |exif| {
+    ///         exif.write_tag(Tag::DateTimeOriginal, "2021:01:01 12:00:00")
+    ///     })
+    ///     .unwrap();
+    /// image.write_data(&[0]).unwrap();
+    /// # }
+    /// ```
+    pub fn write_sub_directory<F>(&mut self, tag: Tag, f: F) -> TiffResult<u64>
+    where
+        F: FnOnce(&mut DirectoryEncoder<W, K>) -> TiffResult<()>,
+    {
+        let offset = {
+            let mut sub = DirectoryEncoder::<W, K>::new_sub_directory(&mut *self.writer)?;
+            // Don't write a half-populated directory on error.
+            sub.dropped = true;
+            f(&mut sub)?;
+            sub.finish_internal()?
+        };
+        self.write_tag(tag, K::convert_offset(offset)?)?;
+        Ok(offset)
+    }
+
+    /// Write a reduced-resolution preview of the image as a SubIFD of this directory.
+    ///
+    /// The thumbnail is written uncompressed with `NewSubfileType` marking it as a
+    /// reduced-resolution image, and linked through the `SubIFDs` tag as expected by DNG and
+    /// TIFF/EP readers. Returns the offset of the thumbnail directory.
+    pub fn write_thumbnail<C: ColorType>(
+        &mut self,
+        width: u32,
+        height: u32,
+        data: &[C::Inner],
+    ) -> TiffResult<u64>
+    where
+        [C::Inner]: TiffValue,
+    {
+        let offset = {
+            let mut sub = DirectoryEncoder::<W, K>::new_sub_directory(&mut *self.writer)?;
+            sub.write_tag(Tag::NewSubfileType, REDUCED_RESOLUTION_SUBFILE)?;
+            let mut image: ImageEncoder<W, C, K> = ImageEncoder::new(sub, width, height)?;
+            // Don't write a half-populated directory on error.
+            image.dropped = true;
+            image.encoder.dropped = true;
+            image.write_image_data(data)?;
+            image.finish_internal()?
+        };
+        self.write_tag(Tag::SubIfd, K::convert_offset(offset)?)?;
+        Ok(offset)
+    }
+
+    /// Copy all metadata of the current image of `decoder` into this directory.
+    ///
+    /// See `copy_metadata_filtered`.
+    pub fn copy_metadata<R: Read + Seek>(&mut self, decoder: &mut Decoder<R>) -> TiffResult<()> {
+        self.copy_metadata_filtered(decoder, |_| true)
+    }
+
+    /// Copy the metadata tags of the current image of `decoder` for which `filter` returns true.
+    ///
+    /// Tags are copied with their original field type and replace any value already written to
+    /// this directory, so the encoder's defaults such as the resolution are overridden. Tags
+    /// describing the layout of the image data, such as dimensions, compression or strip
+    /// offsets, are never copied. The EXIF and GPS directories are copied as sub-directories
+    /// if their pointer tag passes the filter.
+    pub fn copy_metadata_filtered<R, F>(
+        &mut self,
+        decoder: &mut Decoder<R>,
+        mut filter: F,
+    ) -> TiffResult<()>
+    where
+        R: Read + Seek,
+        F: FnMut(Tag) -> bool,
+    {
+        for (tag, field_type, count, data) in decoder.raw_entries()? {
+            if !filter(tag) {
+                continue;
+            }
+            if tag == Tag::ExifDirectory || tag == Tag::GpsDirectory {
+                let offset = match decoder.find_tag_unsigned::<u64>(tag)? {
+                    Some(offset) => offset,
+                    None => continue,
+                };
+                let entries = decoder.raw_sub_entries(offset)?;
+                self.write_sub_directory(tag, |sub| {
+                    for (tag, field_type, count, data) in entries {
+                        sub.write_raw_entry(tag, field_type, count, data)?;
+                    }
+                    Ok(())
+                })?;
+            } else {
+                self.write_raw_entry(tag, field_type, count, data)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write an entry read by the decoder unless it is part of the image structure or points
+    /// into the source file.
+    fn write_raw_entry(
+        &mut self,
+        tag: Tag,
+        field_type: Type,
+        count: u64,
+        data: Vec<u8>,
+    ) -> TiffResult<()> {
+        if IMAGE_STRUCTURE_TAGS.contains(&tag)
+            || field_type == Type::IFD
+            || field_type == Type::IFD8
+        {
+            return Ok(());
+        }
+        self.ifd.insert(
+            tag.to_u16(),
+            DirectoryEntry {
+                data_type: field_type.to_u16(),
+                count: K::convert_offset(count)?,
+                data,
+            },
+        );
+        Ok(())
+    }
+
+    /// Copy the current image of `decoder` into this directory without recompressing it.
+    ///
+    /// The compressed strips or tiles are copied verbatim together with the tags needed to
+    /// interpret them, so the pixel data stays bit-identical. Other tags can be added to the
+    /// directory afterwards. Since the encoder always writes native byte order, images with
+    /// samples wider than 8 bits can only be copied from files in native byte order.
+    pub fn copy_raw_image<R: Read + Seek>(&mut self, decoder: &mut Decoder<R>) -> TiffResult<()> {
+        let bits_per_sample = decoder
+            .find_tag_unsigned_vec::<u16>(Tag::BitsPerSample)?
+            .unwrap_or_else(|| vec![1]);
+        let compression = decoder.get_tag_unsigned::<u16>(Tag::Compression)?;
+        let native = match decoder.byte_order() {
+            ByteOrder::LittleEndian => cfg!(target_endian = "little"),
+            ByteOrder::BigEndian => cfg!(target_endian = "big"),
+        };
+        let byte_order_free = bits_per_sample.iter().all(|&bits| bits <= 8)
+            || compression == CompressionMethod::ModernJPEG.to_u16();
+        if !native && !byte_order_free {
+            return Err(TiffError::UnsupportedError(
+                TiffUnsupportedError::NonNativeByteOrder,
+            ));
+        }
+
+        self.write_tag(Tag::ImageWidth, decoder.get_tag_u32(Tag::ImageWidth)?)?;
+        self.write_tag(Tag::ImageLength, decoder.get_tag_u32(Tag::ImageLength)?)?;
+        self.write_tag(Tag::BitsPerSample, &bits_per_sample[..])?;
+        self.write_tag(Tag::Compression, compression)?;
+        for &tag in &[
+            Tag::PhotometricInterpretation,
+            Tag::SamplesPerPixel,
+            Tag::PlanarConfiguration,
+            Tag::Predictor,
+            Tag::FillOrder,
+            Tag::YCbCrPositioning,
+        ] {
+            if let Some(value) = decoder.find_tag_unsigned::<u16>(tag)? {
+                self.write_tag(tag, value)?;
+            }
+        }
+        for &tag in &[
+            Tag::SampleFormat,
+            Tag::ExtraSamples,
+            Tag::ColorMap,
+            Tag::YCbCrSubSampling,
+        ] {
+            if let Some(values) = decoder.find_tag_unsigned_vec::<u16>(tag)? {
+                self.write_tag(tag, &values[..])?;
+            }
+        }
+        if let Some(value) = decoder.find_tag(Tag::ReferenceBlackWhite)? {
+            let reference = value
+                .into_rational_vec()?
+                .into_iter()
+                .map(|(n, d)| Rational { n, d })
+                .collect::<Vec<_>>();
+            self.write_tag(Tag::ReferenceBlackWhite, &reference[..])?;
+        }
+        if decoder.find_tag(Tag::JPEGTables)?.is_some() {
+            let tables = decoder.get_tag_u8_vec(Tag::JPEGTables)?;
+            self.write_tag(Tag::JPEGTables, Undefined(&tables))?;
+        }
+
+        let (offsets_tag, byte_counts_tag) = match decoder.get_chunk_type() {
+            ChunkType::Strip => {
+                if let Some(rows) = decoder.find_tag_unsigned::<u32>(Tag::RowsPerStrip)? {
+                    self.write_tag(Tag::RowsPerStrip, rows)?;
+                }
+                (Tag::StripOffsets, Tag::StripByteCounts)
+            }
+            ChunkType::Tile => {
+                self.write_tag(Tag::TileWidth, decoder.get_tag_u32(Tag::TileWidth)?)?;
+                self.write_tag(Tag::TileLength, decoder.get_tag_u32(Tag::TileLength)?)?;
+                (Tag::TileOffsets, Tag::TileByteCounts)
+            }
+        };
+
+        let chunk_count = decoder.get_tag_u64_vec(offsets_tag)?.len();
+        let mut offsets = Vec::with_capacity(chunk_count);
+        let mut byte_counts = Vec::with_capacity(chunk_count);
+        for chunk in 0..u32::try_from(chunk_count)? {
+            let data = decoder.read_chunk_bytes(chunk)?;
+            offsets.push(K::convert_offset(self.write_data(&data[..])?)?);
+            byte_counts.push(K::convert_offset(data.len() as u64)?);
+        }
+        self.write_tag(offsets_tag, K::convert_slice(&offsets))?;
+        self.write_tag(byte_counts_tag, K::convert_slice(&byte_counts))?;
+        Ok(())
+    }
+
     /// Write some data to the tiff file, the offset of the data is returned.
     ///
     /// This could be used to write tiff strips.
@@ -251,23 +844,26 @@ impl<'a, W: 'a + Write + Seek, K: TiffKind> DirectoryEncoder<'a, W, K> {
         self.writer.last_written()
     }
 
-    fn finish_internal(&mut self) -> TiffResult<()> {
+    fn finish_internal(&mut self) -> TiffResult<u64> {
         let ifd_pointer = self.write_directory()?;
         let curr_pos = self.writer.offset();
 
-        self.writer.goto_offset(self.ifd_pointer_pos)?;
-        K::write_offset(&mut self.writer, ifd_pointer)?;
-        self.writer.goto_offset(curr_pos)?;
+        if let Some(ifd_pointer_pos) = self.ifd_pointer_pos {
+            self.writer.goto_offset(ifd_pointer_pos)?;
+            K::write_offset(&mut self.writer, ifd_pointer)?;
+            self.writer.goto_offset(curr_pos)?;
+        }
         K::write_offset(&mut self.writer, 0)?;
 
         self.dropped = true;
 
-        Ok(())
+        Ok(ifd_pointer)
     }
 
     /// Write out the ifd directory.
     pub fn finish(mut self) -> TiffResult<()> {
-        self.finish_internal()
+        self.finish_internal()?;
+        Ok(())
     }
 }
 
@@ -279,6 +875,217 @@ impl<'a, W: Write + Seek, K: TiffKind> Drop for DirectoryEncoder<'a, W, K> {
     }
 }
 
+/// Builder to configure the layout of an image before encoding it.
+///
+/// Obtained from [`TiffEncoder::image_builder`]. Incompatible settings are reported by
+/// [`build`](ImageBuilder::build) before anything is written to the file.
+///
+/// # Examples
+/// ```
+/// # extern crate tiff;
+/// # fn main() {
+/// # let mut file = std::io::Cursor::new(Vec::new());
+/// # let image_data = vec![0u16; 100*100];
+/// use tiff::encoder::*;
+/// use tiff::tags::Predictor;
+///
+/// let mut tiff = TiffEncoder::new(&mut file).unwrap();
+/// tiff.image_builder::<colortype::Gray16>(100, 100)
+///     .compression(compression::Lzw)
+///     .predictor(Predictor::Horizontal)
+///     .tile_size(64, 64)
+///     .build()
+///     .unwrap()
+///     .write_data(&image_data)
+///     .unwrap();
+///
+/// // Floating point prediction is meaningless for integer samples.
+/// assert!(tiff.image_builder::<colortype::Gray16>(100, 100)
+///     .compression(compression::Lzw)
+///     .predictor(Predictor::FloatingPoint)
+///     .build()
+///     .is_err());
+/// # }
+/// ```
+pub struct ImageBuilder<
+    'a,
+    W: 'a + Write + Seek,
+    C: ColorType,
+    K: TiffKind,
+    D: Compression = Uncompressed,
+> {
+    encoder: &'a mut TiffEncoder<W, K>,
+    width: u32,
+    height: u32,
+    compression: D,
+    predictor: Predictor,
+    tile_size: Option<(u32, u32)>,
+    rows_per_strip: Option<u32>,
+    ycbcr_subsampling: Option<(u16, u16)>,
+    _phantom: PhantomData<C>,
+}
+
+impl<'a, W: 'a + Write + Seek, C: ColorType, K: TiffKind, D: Compression>
+    ImageBuilder<'a, W, C, K, D>
+{
+    /// Set the compression.
+    pub fn compression<E: Compression>(self, compression: E) -> ImageBuilder<'a, W, C, K, E> {
+        ImageBuilder {
+            encoder: self.encoder,
+            width: self.width,
+            height: self.height,
+            compression,
+            predictor: self.predictor,
+            tile_size: self.tile_size,
+            rows_per_strip: self.rows_per_strip,
+            ycbcr_subsampling: self.ycbcr_subsampling,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Set the predictor applied before compression.
+    ///
+    /// `Predictor::Horizontal` requires integer samples, `Predictor::FloatingPoint` requires
+    /// floating point samples. Both require LZW or Deflate compression.
+    pub fn predictor(mut self, predictor: Predictor) -> Self {
+        self.predictor = predictor;
+        self
+    }
+
+    /// Store the image in tiles of the given size instead of strips.
+    ///
+    /// Both dimensions must be a non-zero multiple of 16.
+    pub fn tile_size(mut self, width: u32, length: u32) -> Self {
+        self.tile_size = Some((width, length));
+        self
+    }
+
+    /// Set the number of rows per strip.
+    ///
+    /// Cannot be combined with `tile_size`.
+    pub fn rows_per_strip(mut self, rows: u32) -> Self {
+        self.rows_per_strip = Some(rows);
+        self
+    }
+
+    /// Subsample the chroma channels of a `YCbCr8` image.
+    ///
+    /// `horizontal` and `vertical` are the subsampling factors, e.g. `(2, 1)` for 4:2:2 and
+    /// `(2, 2)` for 4:2:0. Each factor must be 1, 2 or 4, and the vertical factor must not
+    /// exceed the horizontal one. The image data is still given at full resolution, the chroma
+    /// samples of each block are averaged. Subsampled images can only be written with
+    /// `write_data`.
+    pub fn ycbcr_subsampling(mut self, horizontal: u16, vertical: u16) -> Self {
+        self.ycbcr_subsampling = Some((horizontal, vertical));
+        self
+    }
+
+    /// Validate the configuration and create the [`ImageEncoder`].
+    ///
+    /// Returns an error without writing anything if the settings are incompatible with each
+    /// other or with the color type.
+    pub fn build(self) -> TiffResult<ImageEncoder<'a, W, C, K, D>> {
+        self.validate()?;
+
+        let encoder = self.encoder.new_directory()?;
+        let mut image =
+            ImageEncoder::with_compression(encoder, self.width, self.height, self.compression)?;
+        if let Some(rows) = self.rows_per_strip {
+            image.rows_per_strip(rows)?;
+        }
+        if let Some((tile_width, tile_length)) = self.tile_size {
+            image.encoder.ifd.remove(&Tag::RowsPerStrip.to_u16());
+            image.encoder.write_tag(Tag::TileWidth, tile_width)?;
+            image.encoder.write_tag(Tag::TileLength, tile_length)?;
+            image.tile_size = Some((tile_width, tile_length));
+        }
+        if self.predictor != Predictor::None {
+            image
+                .encoder
+                .write_tag(Tag::Predictor, self.predictor.to_u16())?;
+            image.predictor = self.predictor;
+        }
+        if let Some((horizontal, vertical)) = self.ycbcr_subsampling {
+            image
+                .encoder
+                .write_tag(Tag::YCbCrSubSampling, &[horizontal, vertical][..])?;
+            if self.tile_size.is_none() && self.rows_per_strip.is_none() {
+                // Strips must consist of whole rows of data units.
+                let vertical = u64::from(vertical);
+                let rows = (image.rows_per_strip + vertical - 1) / vertical * vertical;
+                image.rows_per_strip(u32::try_from(rows)?)?;
+            }
+            image.ycbcr_subsampling = Some((horizontal, vertical));
+        }
+        Ok(image)
+    }
+
+    fn validate(&self) -> TiffResult<()> {
+        let invalid = |message: &str| -> TiffResult<()> {
+            Err(io::Error::new(io::ErrorKind::InvalidInput, message).into())
+        };
+
+        if self.width == 0 || self.height == 0 {
+            return invalid("Image dimensions must be non-zero");
+        }
+        if let Some((tile_width, tile_length)) = self.tile_size {
+            check_tile_size(tile_width, tile_length)?;
+            if self.rows_per_strip.is_some() {
+                return invalid("An image cannot have both tiles and rows per strip");
+            }
+        }
+        if self.rows_per_strip == Some(0) {
+            return invalid("Rows per strip must be non-zero");
+        }
+        if let Some((horizontal, vertical)) = self.ycbcr_subsampling {
+            if C::TIFF_VALUE != PhotometricInterpretation::YCbCr
+                || C::BITS_PER_SAMPLE.iter().any(|&bits| bits != 8)
+            {
+                return invalid("Chroma subsampling requires 8-bit YCbCr samples");
+            }
+            let valid = |factor: u16| factor == 1 || factor == 2 || factor == 4;
+            if !valid(horizontal) || !valid(vertical) || vertical > horizontal {
+                return invalid(
+                    "Chroma subsampling factors must be 1, 2 or 4 with vertical <= horizontal",
+                );
+            }
+            if self.predictor != Predictor::None {
+                return invalid("Predictors cannot be combined with chroma subsampling");
+            }
+            if let Some(rows) = self.rows_per_strip {
+                if rows % u32::from(vertical) != 0 {
+                    return invalid(
+                        "Rows per strip must be a multiple of the vertical subsampling",
+                    );
+                }
+            }
+        }
+
+        let formats = C::SAMPLE_FORMAT;
+        match self.predictor {
+            Predictor::None => return Ok(()),
+            Predictor::Horizontal => {
+                if !formats
+                    .iter()
+                    .all(|&format| format == SampleFormat::Uint || format == SampleFormat::Int)
+                {
+                    return invalid("The horizontal predictor requires integer samples");
+                }
+            }
+            Predictor::FloatingPoint => {
+                if !formats.iter().all(|&format| format == SampleFormat::IEEEFP) {
+                    return invalid("The floating point predictor requires floating point samples");
+                }
+            }
+            _ => return invalid("Unsupported predictor"),
+        }
+        match D::COMPRESSION_METHOD {
+            CompressionMethod::LZW | CompressionMethod::Deflate => Ok(()),
+            _ => invalid("Predictors require LZW or Deflate compression"),
+        }
+    }
+}
+
 /// Type to encode images strip by strip.
 ///
 /// You should call `finish` on this when you are finished with it.
@@ -330,6 +1137,11 @@ pub struct ImageEncoder<
     strip_byte_count: Vec<K::OffsetType>,
     dropped: bool,
     compression: D,
+    predictor: Predictor,
+    tile_size: Option<(u32, u32)>,
+    ycbcr_subsampling: Option<(u16, u16)>,
+    bytes_written: u64,
+    progress: Option<progress::ProgressCallback<'a>>,
     _phantom: ::std::marker::PhantomData<C>,
 }
 
@@ -378,6 +1190,14 @@ impl<'a, W: 'a + Write + Seek, T: ColorType, K: TiffKind, D: Compression>
             Tag::SamplesPerPixel,
             u16::try_from(<T>::BITS_PER_SAMPLE.len())?,
         )?;
+        if !<T>::EXTRA_SAMPLES.is_empty() {
+            let extra_samples: Vec<_> = <T>::EXTRA_SAMPLES.iter().map(|s| s.to_u16()).collect();
+            encoder.write_tag(Tag::ExtraSamples, &extra_samples[..])?;
+        }
+        if <T>::TIFF_VALUE == PhotometricInterpretation::YCbCr {
+            // The default is 2x2 subsampling, which is only written on request.
+            encoder.write_tag(Tag::YCbCrSubSampling, &[1u16, 1][..])?;
+        }
         encoder.write_tag(Tag::XResolution, Rational { n: 1, d: 1 })?;
         encoder.write_tag(Tag::YResolution, Rational { n: 1, d: 1 })?;
         encoder.write_tag(Tag::ResolutionUnit, ResolutionUnit::None.to_u16())?;
@@ -394,13 +1214,24 @@ impl<'a, W: 'a + Write + Seek, T: ColorType, K: TiffKind, D: Compression>
             strip_byte_count: Vec::new(),
             dropped: false,
             compression: compression,
+            predictor: Predictor::None,
+            tile_size: None,
+            ycbcr_subsampling: None,
+            bytes_written: 0,
+            progress: None,
             _phantom: ::std::marker::PhantomData,
         })
     }
 
     /// Number of samples the next strip should have.
+    ///
+    /// This is always zero for tiled and subsampled images, which can only be written with
+    /// `write_data`.
     pub fn next_strip_sample_count(&self) -> u64 {
-        if self.strip_idx >= self.strip_count {
+        if self.tile_size.is_some()
+            || self.ycbcr_subsampling.is_some()
+            || self.strip_idx >= self.strip_count
+        {
             return 0;
         }
 
@@ -416,6 +1247,13 @@ impl<'a, W: 'a + Write + Seek, T: ColorType, K: TiffKind, D: Compression>
     where
         [T::Inner]: TiffValue,
     {
+        if self.tile_size.is_some() || self.ycbcr_subsampling.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Tiled and subsampled images must be written with write_data",
+            )
+            .into());
+        }
         let samples = self.next_strip_sample_count();
         if u64::try_from(value.len())? != samples {
             return Err(io::Error::new(
@@ -426,18 +1264,34 @@ impl<'a, W: 'a + Write + Seek, T: ColorType, K: TiffKind, D: Compression>
         }
 
         // Write the (possible compressed) data to the encoder.
-        let offset = self.encoder.write_data(value)?;
+        let offset = if self.predictor == Predictor::None {
+            self.encoder.write_data(value)?
+        } else {
+            let row_bytes =
+                usize::try_from(self.row_samples)? * usize::from(<[T::Inner]>::BYTE_LEN);
+            let predicted = Self::predict(self.predictor, value.data(), row_bytes);
+            self.encoder.write_data(&predicted[..])?
+        };
         let byte_count = self.encoder.last_written() as usize;
 
         self.strip_offsets.push(K::convert_offset(offset)?);
         self.strip_byte_count.push(byte_count.try_into()?);
 
         self.strip_idx += 1;
-        Ok(())
+        self.report_progress(u64::try_from(byte_count)?)
     }
 
     /// Write strips from data
     pub fn write_data(mut self, data: &[T::Inner]) -> TiffResult<()>
+    where
+        [T::Inner]: TiffValue,
+    {
+        self.write_image_data(data)?;
+        self.finish()
+    }
+
+    /// Write all strips or tiles of the image without finishing the directory.
+    fn write_image_data(&mut self, data: &[T::Inner]) -> TiffResult<()>
     where
         [T::Inner]: TiffValue,
     {
@@ -449,7 +1303,16 @@ impl<'a, W: 'a + Write + Seek, T: ColorType, K: TiffKind, D: Compression>
                     "Image width * height exceeds usize",
                 )
             })?;
-        if data.len() < num_pix {
+        // Tiles and subsampled strips are cut from the samples of the whole image at once.
+        let num_samples = num_pix
+            .checked_mul(<T>::BITS_PER_SAMPLE.len())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Image width * height * samples exceeds usize",
+                )
+            })?;
+        if data.len() < num_samples {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
                 "Input data slice is undersized for provided dimensions",
@@ -457,6 +1320,20 @@ impl<'a, W: 'a + Write + Seek, T: ColorType, K: TiffKind, D: Compression>
             .into());
         }
 
+        if self.tile_size.is_some() {
+            return self.write_tiles(data);
+        }
+        if self.ycbcr_subsampling.is_some() {
+            return self.write_subsampled_strips(data);
+        }
+
+        #[cfg(feature = "rayon")]
+        {
+            if D::COMPRESSION_METHOD != CompressionMethod::None && self.strip_idx == 0 {
+                return self.write_strips_parallel(data);
+            }
+        }
+
         self.encoder
             .writer
             .set_compression(self.compression.get_algorithm());
@@ -469,10 +1346,201 @@ impl<'a, W: 'a + Write + Seek, T: ColorType, K: TiffKind, D: Compression>
         }
 
         self.encoder.writer.reset_compression();
-        self.finish()?;
         Ok(())
     }
 
+    /// Compress all strips in parallel and write them in order.
+    #[cfg(feature = "rayon")]
+    fn write_strips_parallel(&mut self, data: &[T::Inner]) -> TiffResult<()>
+    where
+        [T::Inner]: TiffValue,
+    {
+        let total_samples = usize::try_from(u64::from(self.height) * self.row_samples)?;
+        if data.len() < total_samples {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Input data slice is undersized for provided dimensions",
+            )
+            .into());
+        }
+
+        let bytes = data[..total_samples].data();
+        let row_bytes = usize::try_from(self.row_samples)? * usize::from(<[T::Inner]>::BYTE_LEN);
+        let strip_bytes = usize::try_from(self.rows_per_strip)? * row_bytes;
+        let compressor = self.compression.get_algorithm();
+        let predictor = self.predictor;
+        let strips = compress_chunks(usize::try_from(self.strip_count)?, |index| {
+            let start = index * strip_bytes;
+            let end = bytes.len().min(start + strip_bytes);
+            let strip = Self::predict(predictor, Cow::Borrowed(&bytes[start..end]), row_bytes);
+            let mut compressed = Vec::new();
+            compressor.clone().write_to(&mut compressed, &strip)?;
+            Ok(compressed)
+        })?;
+
+        self.write_chunks(strips)
+    }
+
+    /// Subsample the chroma channels of the image, then compress and write the strips.
+    fn write_subsampled_strips(&mut self, data: &[T::Inner]) -> TiffResult<()>
+    where
+        [T::Inner]: TiffValue,
+    {
+        let subsampling = match self.ycbcr_subsampling {
+            Some((horizontal, vertical)) => (usize::from(horizontal), usize::from(vertical)),
+            None => unreachable!(),
+        };
+        let (width, height) = (usize::try_from(self.width)?, usize::try_from(self.height)?);
+        let bytes = data[..width * height * 3].data();
+        let packed = ycbcr::subsample(&bytes, width, height, subsampling);
+        let strip_bytes =
+            ycbcr::subsampled_len(width, usize::try_from(self.rows_per_strip)?, subsampling);
+
+        let compressor = self.compression.get_algorithm();
+        let strips = compress_chunks(usize::try_from(self.strip_count)?, |index| {
+            let start = index * strip_bytes;
+            let end = packed.len().min(start + strip_bytes);
+            let mut compressed = Vec::new();
+            compressor
+                .clone()
+                .write_to(&mut compressed, &packed[start..end])?;
+            Ok(compressed)
+        })?;
+        self.write_chunks(strips)
+    }
+
+    /// Write compressed strips or tiles in order.
+    fn write_chunks(&mut self, chunks: Vec<Vec<u8>>) -> TiffResult<()> {
+        for chunk in chunks {
+            let offset = self.encoder.write_data(&chunk[..])?;
+            self.strip_offsets.push(K::convert_offset(offset)?);
+            self.strip_byte_count.push(chunk.len().try_into()?);
+            self.strip_idx += 1;
+            self.report_progress(u64::try_from(chunk.len())?)?;
+        }
+        Ok(())
+    }
+
+    /// Set a callback that is invoked after every strip or tile written.
+    ///
+    /// Returning `false` from the callback cancels the encode: the current write fails with an
+    /// error and the image directory is not written, leaving a truncated file behind.
+    pub fn progress<F>(&mut self, callback: F)
+    where
+        F: FnMut(&Progress) -> bool + 'a,
+    {
+        self.progress = Some(Box::new(callback));
+    }
+
+    /// Account for a written chunk and notify the progress callback.
+    fn report_progress(&mut self, byte_count: u64) -> TiffResult<()> {
+        self.bytes_written += byte_count;
+        let progress = Progress {
+            chunks_written: self.strip_idx,
+            total_chunks: self.chunk_count(),
+            bytes_written: self.bytes_written,
+        };
+        let proceed = match self.progress.as_mut() {
+            Some(callback) => callback(&progress),
+            None => true,
+        };
+        if !proceed {
+            // Leave the file without a directory for the partial image.
+            self.dropped = true;
+            self.encoder.dropped = true;
+            return Err(io::Error::new(io::ErrorKind::Other, "Encoding was cancelled").into());
+        }
+        Ok(())
+    }
+
+    /// Number of strips or tiles of the image.
+    fn chunk_count(&self) -> u64 {
+        match self.tile_size {
+            Some((tile_width, tile_length)) => {
+                let tiles_across =
+                    (u64::from(self.width) + u64::from(tile_width) - 1) / u64::from(tile_width);
+                let tiles_down =
+                    (u64::from(self.height) + u64::from(tile_length) - 1) / u64::from(tile_length);
+                tiles_across * tiles_down
+            }
+            None => self.strip_count,
+        }
+    }
+
+    /// Split the image into tiles, then compress and write them in order.
+    fn write_tiles(&mut self, data: &[T::Inner]) -> TiffResult<()>
+    where
+        [T::Inner]: TiffValue,
+    {
+        let (tile_width, tile_length) = match self.tile_size {
+            Some((width, length)) => (usize::try_from(width)?, usize::try_from(length)?),
+            None => unreachable!(),
+        };
+        let (width, height) = (usize::try_from(self.width)?, usize::try_from(self.height)?);
+        let samples = <T>::BITS_PER_SAMPLE.len();
+        let pixel_bytes = usize::from(<[T::Inner]>::BYTE_LEN) * samples;
+        let tiles_across = (width + tile_width - 1) / tile_width;
+        let tiles_down = (height + tile_length - 1) / tile_length;
+
+        let bytes = data[..width * height * samples].data();
+        let compressor = self.compression.get_algorithm();
+        let predictor = self.predictor;
+        let subsampling = self
+            .ycbcr_subsampling
+            .map(|(horizontal, vertical)| (usize::from(horizontal), usize::from(vertical)));
+        let tiles = compress_chunks(tiles_across * tiles_down, |index| {
+            let tile = extract_tile(
+                &bytes,
+                (width, height),
+                pixel_bytes,
+                (tile_width, tile_length),
+                index,
+            );
+            let tile = match subsampling {
+                Some(subsampling) => Cow::Owned(ycbcr::subsample(
+                    &tile,
+                    tile_width,
+                    tile_length,
+                    subsampling,
+                )),
+                None => Self::predict(predictor, Cow::Owned(tile), tile_width * pixel_bytes),
+            };
+            let mut compressed = Vec::new();
+            compressor.clone().write_to(&mut compressed, &tile)?;
+            Ok(compressed)
+        })?;
+
+        self.write_chunks(tiles)
+    }
+
+    /// Apply a predictor to rows of `row_bytes` bytes.
+    fn predict<'d>(predictor: Predictor, data: Cow<'d, [u8]>, row_bytes: usize) -> Cow<'d, [u8]> {
+        predictor::predict_rows(
+            predictor,
+            data,
+            row_bytes,
+            <T>::BITS_PER_SAMPLE.len(),
+            usize::from(<T::Inner>::BYTE_LEN),
+        )
+    }
+
+    /// Declare the meaning of the extra samples, e.g. whether alpha is associated.
+    ///
+    /// The color type determines the default, which is unassociated alpha for RGBA. One value
+    /// must be given for each extra sample of the color type.
+    pub fn extra_samples(&mut self, extra_samples: &[ExtraSamples]) -> TiffResult<()> {
+        if extra_samples.len() != <T>::EXTRA_SAMPLES.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Number of extra samples does not match the color type",
+            )
+            .into());
+        }
+        let extra_samples: Vec<_> = extra_samples.iter().map(|s| s.to_u16()).collect();
+        self.encoder
+            .write_tag(Tag::ExtraSamples, &extra_samples[..])
+    }
+
     /// Set image resolution
     pub fn resolution(&mut self, unit: ResolutionUnit, value: Rational) {
         self.encoder
@@ -501,11 +1569,151 @@ impl<'a, W: 'a + Write + Seek, T: ColorType, K: TiffKind, D: Compression>
         self.encoder.write_tag(Tag::YResolution, value).unwrap();
     }
 
+    /// Set the image resolution in dots per inch.
+    ///
+    /// This is a shorthand for setting the resolution unit to inches together with both
+    /// resolution values.
+    pub fn dpi(&mut self, x: u32, y: u32) {
+        self.resolution_unit(ResolutionUnit::Inch);
+        self.x_resolution(Rational { n: x, d: 1 });
+        self.y_resolution(Rational { n: y, d: 1 });
+    }
+
+    /// Declare the orientation of the image data.
+    ///
+    /// Readers apply the corresponding transformation for display, so the pixels don't need to
+    /// be rotated or mirrored before encoding. E.g. `Orientation::RightTop` marks data that
+    /// has to be rotated 90° clockwise to be displayed upright.
+    pub fn orientation(&mut self, orientation: Orientation) -> TiffResult<()> {
+        self.encoder
+            .write_tag(Tag::Orientation, orientation.to_u16())
+    }
+
+    /// Embed an ICC color profile.
+    ///
+    /// The profile is written verbatim as the `InterColorProfile` tag.
+    pub fn icc_profile(&mut self, profile: &[u8]) -> TiffResult<()> {
+        self.encoder.write_tag(Tag::IccProfile, Undefined(profile))
+    }
+
+    /// Embed an XMP metadata packet.
+    ///
+    /// The packet is written verbatim as the `XMP` tag with field type BYTE, as done by Adobe
+    /// applications.
+    pub fn xmp(&mut self, packet: &str) -> TiffResult<()> {
+        self.encoder.write_tag(Tag::Xmp, packet.as_bytes())
+    }
+
+    /// Set the `ImageDescription` tag.
+    pub fn image_description(&mut self, value: &str) -> TiffResult<()> {
+        self.encoder.write_tag(Tag::ImageDescription, value)
+    }
+
+    /// Set the `Software` tag.
+    pub fn software(&mut self, value: &str) -> TiffResult<()> {
+        self.encoder.write_tag(Tag::Software, value)
+    }
+
+    /// Set the `Artist` tag.
+    pub fn artist(&mut self, value: &str) -> TiffResult<()> {
+        self.encoder.write_tag(Tag::Artist, value)
+    }
+
+    /// Set the `Copyright` tag.
+    pub fn copyright(&mut self, value: &str) -> TiffResult<()> {
+        self.encoder.write_tag(Tag::Copyright, value)
+    }
+
+    /// Set the `Make` tag, the manufacturer of the scanner or camera.
+    pub fn make(&mut self, value: &str) -> TiffResult<()> {
+        self.encoder.write_tag(Tag::Make, value)
+    }
+
+    /// Set the `Model` tag, the model name of the scanner or camera.
+    pub fn model(&mut self, value: &str) -> TiffResult<()> {
+        self.encoder.write_tag(Tag::Model, value)
+    }
+
+    /// Set the `DateTime` tag.
+    ///
+    /// The value must have the format `YYYY:MM:DD HH:MM:SS` required by the specification,
+    /// otherwise an error is returned.
+    pub fn date_time(&mut self, value: &str) -> TiffResult<()> {
+        let valid = value.len() == 19
+            && value.bytes().enumerate().all(|(i, b)| match i {
+                4 | 7 | 13 | 16 => b == b':',
+                10 => b == b' ',
+                _ => b.is_ascii_digit(),
+            });
+        if !valid {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "DateTime must have the format YYYY:MM:DD HH:MM:SS",
+            )
+            .into());
+        }
+        self.encoder.write_tag(Tag::DateTime, value)
+    }
+
+    /// Set the GeoTIFF `ModelPixelScale` as `[scale_x, scale_y, scale_z]`.
+    pub fn model_pixel_scale(&mut self, scale: &[f64; 3]) -> TiffResult<()> {
+        self.encoder.write_tag(Tag::ModelPixelScaleTag, &scale[..])
+    }
+
+    /// Set the GeoTIFF `ModelTiepoint`.
+    ///
+    /// Each tiepoint consists of six values `[i, j, k, x, y, z]`, mapping the raster point
+    /// `(i, j, k)` to the model point `(x, y, z)`.
+    pub fn model_tiepoint(&mut self, tiepoints: &[[f64; 6]]) -> TiffResult<()> {
+        let values: Vec<f64> = tiepoints.iter().flatten().copied().collect();
+        self.encoder.write_tag(Tag::ModelTiepointTag, &values[..])
+    }
+
+    /// Set the GeoTIFF `ModelTransformation` as a row-major 4x4 matrix.
+    pub fn model_transformation(&mut self, matrix: &[f64; 16]) -> TiffResult<()> {
+        self.encoder
+            .write_tag(Tag::ModelTransformationTag, &matrix[..])
+    }
+
+    /// Set the GeoTIFF `GeoKeyDirectory`.
+    ///
+    /// The directory is a header of four values followed by four values per key, so its
+    /// length must be a non-zero multiple of four.
+    pub fn geo_key_directory(&mut self, keys: &[u16]) -> TiffResult<()> {
+        if keys.is_empty() || !keys.chunks_exact(4).remainder().is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "GeoKeyDirectory length must be a non-zero multiple of 4",
+            )
+            .into());
+        }
+        self.encoder.write_tag(Tag::GeoKeyDirectoryTag, keys)
+    }
+
+    /// Set the GeoTIFF `GeoDoubleParams` referenced by the key directory.
+    pub fn geo_double_params(&mut self, params: &[f64]) -> TiffResult<()> {
+        self.encoder.write_tag(Tag::GeoDoubleParamsTag, params)
+    }
+
+    /// Set the GeoTIFF `GeoAsciiParams` referenced by the key directory.
+    ///
+    /// Individual parameters are separated by `|` as described by the GeoTIFF specification.
+    pub fn geo_ascii_params(&mut self, params: &str) -> TiffResult<()> {
+        self.encoder.write_tag(Tag::GeoAsciiParamsTag, params)
+    }
+
     /// Set image number of lines per strip
     ///
     /// This function needs to be called before any calls to `write_data` or
     /// `write_strip` and will return an error otherwise.
     pub fn rows_per_strip(&mut self, value: u32) -> TiffResult<()> {
+        if self.tile_size.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Cannot set the strip size of a tiled image",
+            )
+            .into());
+        }
         if self.strip_idx != 0 {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
@@ -523,13 +1731,16 @@ impl<'a, W: 'a + Write + Seek, T: ColorType, K: TiffKind, D: Compression>
         Ok(())
     }
 
-    fn finish_internal(&mut self) -> TiffResult<()> {
+    fn finish_internal(&mut self) -> TiffResult<u64> {
+        let (offsets_tag, byte_counts_tag) = if self.tile_size.is_some() {
+            (Tag::TileOffsets, Tag::TileByteCounts)
+        } else {
+            (Tag::StripOffsets, Tag::StripByteCounts)
+        };
         self.encoder
-            .write_tag(Tag::StripOffsets, K::convert_slice(&self.strip_offsets))?;
-        self.encoder.write_tag(
-            Tag::StripByteCounts,
-            K::convert_slice(&self.strip_byte_count),
-        )?;
+            .write_tag(offsets_tag, K::convert_slice(&self.strip_offsets))?;
+        self.encoder
+            .write_tag(byte_counts_tag, K::convert_slice(&self.strip_byte_count))?;
         self.dropped = true;
 
         self.encoder.finish_internal()
@@ -542,7 +1753,8 @@ impl<'a, W: 'a + Write + Seek, T: ColorType, K: TiffKind, D: Compression>
 
     /// Write out image and ifd directory.
     pub fn finish(mut self) -> TiffResult<()> {
-        self.finish_internal()
+        self.finish_internal()?;
+        Ok(())
     }
 }
 
@@ -0,0 +1,64 @@
+//! Generation of reduced-resolution overview images.
+
+/// Downsample an image by an integer `factor` using nearest neighbour sampling.
+///
+/// The top-left sample of every `factor`×`factor` block is kept, the output has
+/// `ceil(width / factor)`×`ceil(height / factor)` pixels. `data` must hold at least
+/// `width * height * samples` values in chunky (pixel-interleaved) order.
+pub(crate) fn downsample<T: Copy>(
+    data: &[T],
+    width: usize,
+    height: usize,
+    samples: usize,
+    factor: usize,
+) -> Vec<T> {
+    let (out_width, out_height) = overview_dimensions(width, height, factor);
+    let mut out = Vec::with_capacity(out_width * out_height * samples);
+    for row in (0..height).step_by(factor) {
+        let row = &data[row * width * samples..][..width * samples];
+        for pixel in row.chunks_exact(samples).step_by(factor) {
+            out.extend_from_slice(pixel);
+        }
+    }
+    out
+}
+
+/// The dimensions of an overview that is reduced by `factor`.
+pub(crate) fn overview_dimensions(width: usize, height: usize, factor: usize) -> (usize, usize) {
+    (
+        width / factor + usize::from(width % factor != 0),
+        height / factor + usize::from(height % factor != 0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downsample_odd_dimensions() {
+        #[rustfmt::skip]
+        let data = [
+            0, 1, 2,
+            3, 4, 5,
+            6, 7, 8,
+        ];
+        assert_eq!(downsample(&data, 3, 3, 1, 2), [0, 2, 6, 8]);
+        assert_eq!(downsample(&data, 3, 3, 1, 4), [0]);
+    }
+
+    #[test]
+    fn downsample_keeps_samples_together() {
+        let data = [1u16, 2, 3, 4, 5, 6, 7, 8];
+        assert_eq!(downsample(&data, 2, 2, 2, 2), [1, 2]);
+        assert_eq!(downsample(&data, 4, 1, 2, 2), [1, 2, 5, 6]);
+    }
+
+    #[test]
+    fn overview_dimensions_of_largest_image() {
+        assert_eq!(
+            overview_dimensions(usize::MAX, 3, 2),
+            (usize::MAX / 2 + 1, 2)
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Application of the horizontal and floating point predictors before compression.
+//!
+//! Both functions operate on a single row of native-endian sample data, as produced by
+//! `TiffValue::data`, and are the inverse of the prediction done by the decoder.
+
+use std::borrow::Cow;
+
+use crate::tags::Predictor;
+
+/// Apply `predictor` to every row of `data`, each `row_bytes` long.
+pub(crate) fn predict_rows<'d>(
+    predictor: Predictor,
+    data: Cow<'d, [u8]>,
+    row_bytes: usize,
+    samples: usize,
+    sample_bytes: usize,
+) -> Cow<'d, [u8]> {
+    match predictor {
+        Predictor::Horizontal => {
+            let mut data = data.into_owned();
+            for row in data.chunks_mut(row_bytes) {
+                horizontal_predict(row, samples, sample_bytes);
+            }
+            Cow::Owned(data)
+        }
+        Predictor::FloatingPoint => Cow::Owned(
+            data.chunks(row_bytes)
+                .flat_map(|row| floating_point_predict(row, samples, sample_bytes))
+                .collect(),
+        ),
+        _ => data,
+    }
+}
+
+/// Replace each sample of a row by its difference to the corresponding sample of the previous
+/// pixel (predictor 2).
+///
+/// `sample_bytes` is the width of one sample, `samples` the number of samples per pixel.
+pub(crate) fn horizontal_predict(row: &mut [u8], samples: usize, sample_bytes: usize) {
+    let stride = samples * sample_bytes;
+    // Iterate backwards so that every difference is taken against the original value.
+    let mut end = row.len();
+    while end >= stride + sample_bytes {
+        let start = end - sample_bytes;
+        let previous = start - stride;
+        match sample_bytes {
+            1 => row[start] = row[start].wrapping_sub(row[previous]),
+            2 => {
+                let value = u16::from_ne_bytes([row[start], row[start + 1]]);
+                let before = u16::from_ne_bytes([row[previous], row[previous + 1]]);
+                row[start..end].copy_from_slice(&value.wrapping_sub(before).to_ne_bytes());
+            }
+            4 => {
+                let mut value = [0; 4];
+                let mut before = [0; 4];
+                value.copy_from_slice(&row[start..end]);
+                before.copy_from_slice(&row[previous..previous + 4]);
+                let difference = u32::from_ne_bytes(value).wrapping_sub(u32::from_ne_bytes(before));
+                row[start..end].copy_from_slice(&difference.to_ne_bytes());
+            }
+            8 => {
+                let mut value = [0; 8];
+                let mut before = [0; 8];
+                value.copy_from_slice(&row[start..end]);
+                before.copy_from_slice(&row[previous..previous + 8]);
+                let difference = u64::from_ne_bytes(value).wrapping_sub(u64::from_ne_bytes(before));
+                row[start..end].copy_from_slice(&difference.to_ne_bytes());
+            }
+            _ => unreachable!("Unsupported sample size {}", sample_bytes),
+        }
+        end = start;
+    }
+}
+
+/// Apply the floating point predictor (predictor 3) to a row of floating point samples.
+///
+/// The big-endian bytes of all samples are split into byte planes, most significant bytes
+/// first, which are then differenced bytewise with a stride of `samples`.
+pub(crate) fn floating_point_predict(row: &[u8], samples: usize, sample_bytes: usize) -> Vec<u8> {
+    let count = row.len() / sample_bytes;
+    let mut output = vec![0; row.len()];
+    for (index, sample) in row.chunks_exact(sample_bytes).enumerate() {
+        for byte in 0..sample_bytes {
+            // The most significant byte of a native-endian sample.
+            let significance = if cfg!(target_endian = "little") {
+                sample_bytes - 1 - byte
+            } else {
+                byte
+            };
+            output[byte * count + index] = sample[significance];
+        }
+    }
+    for index in (samples..output.len()).rev() {
+        output[index] = output[index].wrapping_sub(output[index - samples]);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn horizontal_u16() {
+        let mut row = Vec::new();
+        for value in &[1u16, 10, 3, 40, 2, 5] {
+            row.extend_from_slice(&value.to_ne_bytes());
+        }
+        horizontal_predict(&mut row, 2, 2);
+        let values: Vec<u16> = row
+            .chunks_exact(2)
+            .map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]]))
+            .collect();
+        assert_eq!(values, [1, 10, 2, 30, 0xffff, 0xffdd]);
+    }
+
+    #[test]
+    fn floating_point_f32() {
+        let mut row = Vec::new();
+        for value in &[1.0f32, 2.0] {
+            row.extend_from_slice(&value.to_ne_bytes());
+        }
+        let predicted = floating_point_predict(&row, 1, 4);
+        // Byte planes of 0x3f800000 and 0x40000000, differenced.
+        assert_eq!(predicted, [0x3f, 0x01, 0x40, 0x80, 0x00, 0x00, 0x00, 0x00]);
+    }
+}
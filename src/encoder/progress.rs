@@ -0,0 +1,16 @@
+//! Progress reporting while image data is written.
+
+/// The state of an image encode, passed to the callback set with
+/// [`ImageEncoder::progress`](super::ImageEncoder::progress).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// Number of strips or tiles written so far.
+    pub chunks_written: u64,
+    /// Total number of strips or tiles of the image.
+    pub total_chunks: u64,
+    /// Number of (compressed) image data bytes written so far.
+    pub bytes_written: u64,
+}
+
+/// Boxed progress callback, returning `false` to cancel the encode.
+pub(crate) type ProgressCallback<'a> = Box<dyn FnMut(&Progress) -> bool + 'a>;
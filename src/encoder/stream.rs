@@ -0,0 +1,180 @@
+//! Encoding to writers that can't seek.
+
+use std::{
+    convert::TryFrom,
+    io::{self, Seek, SeekFrom, Write},
+    mem,
+};
+
+use crate::error::TiffResult;
+
+use super::{TiffEncoder, TiffKind, TiffKindBig, TiffKindStandard};
+
+/// Encoder for writers that only implement `Write`, such as pipes, sockets or HTTP bodies.
+///
+/// The Tiff format links every directory from the previous one, which the regular encoder
+/// patches in place once the directory is written. This encoder keeps the output in memory
+/// from the oldest position that may still be patched, so at most the data of one image and
+/// its directory are buffered. Everything before it is passed on to the writer whenever
+/// [`encoder`][StreamEncoder::encoder] or [`finish`][StreamEncoder::finish] is called.
+///
+/// # Examples
+/// ```
+/// # extern crate tiff;
+/// # fn main() {
+/// # let image_data = vec![0; 100*100*3];
+/// use tiff::encoder::*;
+///
+/// let mut output = Vec::new();
+/// let mut tiff = StreamEncoder::new(&mut output).unwrap();
+/// tiff.encoder()
+///     .unwrap()
+///     .write_image::<colortype::RGB8>(100, 100, &image_data)
+///     .unwrap();
+/// tiff.finish().unwrap();
+/// # }
+/// ```
+pub struct StreamEncoder<W: Write, K: TiffKind = TiffKindStandard> {
+    encoder: TiffEncoder<StreamWriter<W>, K>,
+}
+
+impl<W: Write> StreamEncoder<W> {
+    /// Creates a new encoder for standard Tiff files.
+    pub fn new(writer: W) -> TiffResult<StreamEncoder<W, TiffKindStandard>> {
+        StreamEncoder::new_generic(writer)
+    }
+}
+
+impl<W: Write> StreamEncoder<W, TiffKindBig> {
+    /// Creates a new encoder for BigTiff files.
+    pub fn new_big(writer: W) -> TiffResult<Self> {
+        StreamEncoder::new_generic(writer)
+    }
+}
+
+impl<W: Write, K: TiffKind> StreamEncoder<W, K> {
+    /// Creates a new Tiff or BigTiff encoder, inferred from the return type.
+    pub fn new_generic(writer: W) -> TiffResult<Self> {
+        Ok(StreamEncoder {
+            encoder: TiffEncoder::new_generic(StreamWriter::new(writer))?,
+        })
+    }
+
+    /// Pass all completed images on to the writer and return the encoder for the next image.
+    pub fn encoder(&mut self) -> TiffResult<&mut TiffEncoder<StreamWriter<W>, K>> {
+        // Only the pointer to the next directory is still written to.
+        let pointer_pos =
+            self.encoder.writer.offset() - u64::try_from(mem::size_of::<K::OffsetType>())?;
+        self.encoder.writer.inner_mut().release(pointer_pos)?;
+        Ok(&mut self.encoder)
+    }
+
+    /// Write out the remaining data and return the writer.
+    pub fn finish(self) -> TiffResult<W> {
+        let mut writer = self.encoder.writer.into_inner();
+        writer.release(u64::MAX)?;
+        writer.inner.flush()?;
+        Ok(writer.inner)
+    }
+}
+
+/// A `Write` + `Seek` adapter over a writer that can't seek, used by [`StreamEncoder`].
+///
+/// Written data is kept in memory until it is released, seeking is only possible within that
+/// data and fails for positions that were already passed on.
+pub struct StreamWriter<W> {
+    inner: W,
+    released: u64,
+    buffer: Vec<u8>,
+    position: u64,
+}
+
+impl<W: Write> StreamWriter<W> {
+    fn new(inner: W) -> Self {
+        StreamWriter {
+            inner,
+            released: 0,
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Pass the buffered data before `offset` on to the inner writer.
+    fn release(&mut self, offset: u64) -> io::Result<()> {
+        let len = offset.saturating_sub(self.released);
+        let len = usize::try_from(len).map_or(self.buffer.len(), |len| len.min(self.buffer.len()));
+        self.inner.write_all(&self.buffer[..len])?;
+        self.buffer.drain(..len);
+        self.released += len as u64;
+        Ok(())
+    }
+
+    fn end(&self) -> u64 {
+        self.released + self.buffer.len() as u64
+    }
+}
+
+impl<W: Write> Write for StreamWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let start = usize::try_from(self.position - self.released)
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Stream buffer exceeds usize"))?;
+        if self.buffer.len() < start {
+            self.buffer.resize(start, 0);
+        }
+        let overlap = buf.len().min(self.buffer.len() - start);
+        self.buffer[start..start + overlap].copy_from_slice(&buf[..overlap]);
+        self.buffer.extend_from_slice(&buf[overlap..]);
+        self.position += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> Seek for StreamWriter<W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => offset_by(self.end(), offset),
+            SeekFrom::Current(offset) => offset_by(self.position, offset),
+        };
+        match position {
+            Some(position) if position >= self.released => {
+                self.position = position;
+                Ok(position)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Cannot seek to data that was already written out",
+            )),
+        }
+    }
+}
+
+fn offset_by(base: u64, offset: i64) -> Option<u64> {
+    if offset < 0 {
+        base.checked_sub(offset.wrapping_neg() as u64)
+    } else {
+        base.checked_add(offset as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patch_buffered_data() {
+        let mut writer = StreamWriter::new(Vec::new());
+        writer.write_all(&[1, 2, 3, 4, 5, 6]).unwrap();
+        writer.release(2).unwrap();
+        writer.seek(SeekFrom::Start(3)).unwrap();
+        writer.write_all(&[9]).unwrap();
+        assert_eq!(writer.seek(SeekFrom::End(0)).unwrap(), 6);
+        assert!(writer.seek(SeekFrom::Start(1)).is_err());
+        writer.release(u64::MAX).unwrap();
+        assert_eq!(writer.inner, [1, 2, 3, 9, 5, 6]);
+    }
+}
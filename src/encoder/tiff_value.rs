@@ -420,6 +420,19 @@ impl TiffValue for SRational {
     }
 }
 
+impl<'a> TiffValue for Undefined<'a> {
+    const BYTE_LEN: u8 = 1;
+    const FIELD_TYPE: Type = Type::UNDEFINED;
+
+    fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    fn data(&self) -> Cow<[u8]> {
+        Cow::Borrowed(self.0)
+    }
+}
+
 impl TiffValue for str {
     const BYTE_LEN: u8 = 1;
     const FIELD_TYPE: Type = Type::ASCII;
@@ -508,15 +521,23 @@ pub struct Ifd(pub u32);
 #[derive(Clone)]
 pub struct Ifd8(pub u64);
 
-/// Type to represent tiff values of type `RATIONAL`
+/// Type to represent tiff values of type `UNDEFINED`
+///
+/// The bytes are written as-is, their interpretation depends on the field.
 #[derive(Clone)]
+pub struct Undefined<'a>(pub &'a [u8]);
+
+/// Type to represent tiff values of type `RATIONAL`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rational {
     pub n: u32,
     pub d: u32,
 }
 
 /// Type to represent tiff values of type `SRATIONAL`
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SRational {
     pub n: i32,
     pub d: i32,
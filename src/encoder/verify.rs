@@ -0,0 +1,147 @@
+//! Consistency checks of a written file.
+
+use std::convert::TryFrom;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::decoder::{ChunkType, Decoder, Limits};
+use crate::error::{TiffFormatError, TiffResult};
+use crate::tags::{CompressionMethod, PhotometricInterpretation, Tag};
+
+/// Walk the IFD chain of a file `len` bytes long and check the strip or tile table of every
+/// image.
+///
+/// Each image must have one offset and byte count per chunk, all chunks must lie between the
+/// header and the end of the file without overlapping, and uncompressed chunks must have the
+/// size implied by the image dimensions. Chunks with zero offset and byte count are sparse and
+/// accepted.
+pub(crate) fn verify<R: Read + Seek>(mut reader: R, len: u64, header_len: u64) -> TiffResult<()> {
+    reader.seek(SeekFrom::Start(0))?;
+    let mut decoder = Decoder::new(reader)?.with_limits(Limits::unlimited());
+    let mut index = 0;
+    loop {
+        verify_image(&mut decoder, index, len, header_len)?;
+        if !decoder.more_images() {
+            return Ok(());
+        }
+        decoder.next_image()?;
+        index += 1;
+    }
+}
+
+fn verify_image<R: Read + Seek>(
+    decoder: &mut Decoder<R>,
+    index: usize,
+    len: u64,
+    header_len: u64,
+) -> TiffResult<()> {
+    let (width, height) = decoder.dimensions()?;
+    let (offsets_tag, byte_counts_tag, expected_chunks) = match decoder.get_chunk_type() {
+        ChunkType::Strip => (
+            Tag::StripOffsets,
+            Tag::StripByteCounts,
+            u64::from(decoder.strip_count()?),
+        ),
+        ChunkType::Tile => {
+            let tile_width = u64::from(decoder.get_tag_u32(Tag::TileWidth)?);
+            let tile_length = u64::from(decoder.get_tag_u32(Tag::TileLength)?);
+            let across = (u64::from(width) + tile_width - 1) / tile_width;
+            let down = (u64::from(height) + tile_length - 1) / tile_length;
+            (Tag::TileOffsets, Tag::TileByteCounts, across * down)
+        }
+    };
+    let offsets = decoder.get_tag_u64_vec(offsets_tag)?;
+    let byte_counts = decoder.get_tag_u64_vec(byte_counts_tag)?;
+    if u64::try_from(offsets.len())? != expected_chunks || byte_counts.len() != offsets.len() {
+        return Err(TiffFormatError::ChunkCountMismatch {
+            image: index,
+            offsets: offsets.len(),
+            byte_counts: byte_counts.len(),
+            expected: expected_chunks,
+        }
+        .into());
+    }
+
+    let mut ranges = Vec::with_capacity(offsets.len());
+    for (&offset, &count) in offsets.iter().zip(&byte_counts) {
+        if offset == 0 && count == 0 {
+            continue;
+        }
+        match offset.checked_add(count) {
+            Some(end) if offset >= header_len && end <= len => ranges.push((offset, end)),
+            _ => {
+                return Err(TiffFormatError::ChunkOutOfFile {
+                    image: index,
+                    offset,
+                    length: count,
+                }
+                .into())
+            }
+        }
+    }
+    ranges.sort_unstable();
+    if let Some(pair) = ranges.windows(2).find(|pair| pair[0].1 > pair[1].0) {
+        return Err(TiffFormatError::OverlappingChunks {
+            image: index,
+            first: pair[0].0,
+            second: pair[1].0,
+        }
+        .into());
+    }
+
+    if let Some(expected) = uncompressed_chunk_bytes(decoder)? {
+        let mismatch = byte_counts
+            .iter()
+            .enumerate()
+            .find(|&(chunk, &count)| count != 0 && count != expected(chunk));
+        if let Some((chunk, &count)) = mismatch {
+            return Err(TiffFormatError::UncompressedChunkSize {
+                image: index,
+                chunk,
+                actual: count,
+                expected: expected(chunk),
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Returns the expected size of every chunk if the image is stored uncompressed and without
+/// chroma subsampling.
+fn uncompressed_chunk_bytes<R: Read + Seek>(
+    decoder: &mut Decoder<R>,
+) -> TiffResult<Option<impl Fn(usize) -> u64>> {
+    let compression = decoder.find_tag_unsigned::<u16>(Tag::Compression)?;
+    if compression.and_then(CompressionMethod::from_u16) != Some(CompressionMethod::None) {
+        return Ok(None);
+    }
+    let photometric = decoder.find_tag_unsigned::<u16>(Tag::PhotometricInterpretation)?;
+    if photometric.and_then(PhotometricInterpretation::from_u16)
+        == Some(PhotometricInterpretation::YCbCr)
+    {
+        let subsampling = decoder.find_tag_unsigned_vec::<u16>(Tag::YCbCrSubSampling)?;
+        if subsampling.as_deref() != Some(&[1, 1][..]) {
+            return Ok(None);
+        }
+    }
+
+    let bits_per_pixel: u64 = decoder
+        .get_tag_u32_vec(Tag::BitsPerSample)?
+        .into_iter()
+        .map(u64::from)
+        .sum();
+    let chunk_type = decoder.get_chunk_type();
+    let (chunk_width, chunk_length) = decoder.chunk_dimensions();
+    let height = decoder.dimensions()?.1;
+    Ok(Some(move |chunk: usize| {
+        let rows = match chunk_type {
+            // The last strip only holds the remaining rows.
+            ChunkType::Strip => {
+                let start = chunk as u64 * u64::from(chunk_length);
+                u64::from(chunk_length).min(u64::from(height).saturating_sub(start))
+            }
+            ChunkType::Tile => u64::from(chunk_length),
+        };
+        (u64::from(chunk_width) * bits_per_pixel + 7) / 8 * rows
+    }))
+}
@@ -1,6 +1,7 @@
 use crate::encoder::compression::*;
-use crate::error::TiffResult;
-use std::io::{self, Seek, SeekFrom, Write};
+use crate::error::{TiffError, TiffFormatError, TiffResult, TiffUnsupportedError};
+use std::collections::HashSet;
+use std::io::{self, Read, Seek, SeekFrom, Write};
 
 pub fn write_tiff_header<W: Write>(writer: &mut TiffWriter<W>) -> TiffResult<()> {
     #[cfg(target_endian = "little")]
@@ -40,6 +41,91 @@ pub fn write_bigtiff_header<W: Write>(writer: &mut TiffWriter<W>) -> TiffResult<
     Ok(())
 }
 
+/// Walks the IFD chain of an existing file and returns the position of the last "next IFD"
+/// offset field, i.e. the field that has to be patched to append another IFD.
+///
+/// Only files in native byte order are supported since that is the order the encoder writes.
+pub fn find_last_ifd_pointer<R: Read + Seek>(reader: &mut R, bigtiff: bool) -> TiffResult<u64> {
+    fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+        let mut buf = [0; 2];
+        reader.read_exact(&mut buf)?;
+        Ok(u16::from_ne_bytes(buf))
+    }
+
+    fn read_offset<R: Read>(reader: &mut R, bigtiff: bool) -> io::Result<u64> {
+        if bigtiff {
+            let mut buf = [0; 8];
+            reader.read_exact(&mut buf)?;
+            Ok(u64::from_ne_bytes(buf))
+        } else {
+            let mut buf = [0; 4];
+            reader.read_exact(&mut buf)?;
+            Ok(u32::from_ne_bytes(buf).into())
+        }
+    }
+
+    reader.seek(SeekFrom::Start(0))?;
+    let mut byte_order = [0; 2];
+    reader.read_exact(&mut byte_order)?;
+    #[cfg(target_endian = "little")]
+    let boi: u8 = 0x49;
+    #[cfg(not(target_endian = "little"))]
+    let boi: u8 = 0x4d;
+
+    match byte_order {
+        [a, b] if a == boi && b == boi => {}
+        [b'I', b'I'] | [b'M', b'M'] => {
+            return Err(TiffError::UnsupportedError(
+                TiffUnsupportedError::NonNativeByteOrder,
+            ))
+        }
+        _ => {
+            return Err(TiffError::FormatError(
+                TiffFormatError::TiffSignatureNotFound,
+            ))
+        }
+    }
+
+    let expected_version = if bigtiff { 43 } else { 42 };
+    if read_u16(reader)? != expected_version {
+        return Err(TiffError::FormatError(
+            TiffFormatError::TiffSignatureInvalid,
+        ));
+    }
+    if bigtiff && (read_u16(reader)? != 8 || read_u16(reader)? != 0) {
+        return Err(TiffError::FormatError(
+            TiffFormatError::TiffSignatureInvalid,
+        ));
+    }
+
+    let (entry_size, count_size) = if bigtiff { (20, 8) } else { (12, 2) };
+    let mut seen = HashSet::new();
+    let mut pointer_pos = reader.seek(SeekFrom::Current(0))?;
+    loop {
+        let ifd_offset = read_offset(reader, bigtiff)?;
+        if ifd_offset == 0 {
+            return Ok(pointer_pos);
+        }
+        if !seen.insert(ifd_offset) {
+            return Err(TiffError::FormatError(TiffFormatError::CycleInOffsets));
+        }
+
+        reader.seek(SeekFrom::Start(ifd_offset))?;
+        let entry_count = if bigtiff {
+            read_offset(reader, true)?
+        } else {
+            read_u16(reader)?.into()
+        };
+        pointer_pos = entry_count
+            .checked_mul(entry_size)
+            .and_then(|size| size.checked_add(ifd_offset + count_size))
+            .ok_or(TiffError::FormatError(
+                TiffFormatError::InconsistentSizesEncountered,
+            ))?;
+        reader.seek(SeekFrom::Start(pointer_pos))?;
+    }
+}
+
 pub struct TiffWriter<W> {
     writer: W,
     offset: u64,
@@ -73,6 +159,14 @@ impl<W: Write> TiffWriter<W> {
         self.byte_count
     }
 
+    pub fn inner_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
     pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), io::Error> {
         self.byte_count = self.compressor.write_to(&mut self.writer, bytes)?;
         self.offset += self.byte_count;
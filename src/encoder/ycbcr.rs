@@ -0,0 +1,66 @@
+//! Packing of YCbCr samples into subsampled data units.
+
+/// Pack an image of interleaved 8-bit YCbCr pixels into the data units of a subsampled image.
+///
+/// Every `horizontal`×`vertical` block of pixels becomes one data unit, holding the luma
+/// samples of the block in row-major order followed by the averages of its two chroma
+/// samples. Blocks extending past the right or bottom edge are filled by replicating the last
+/// column or row of the image.
+pub(crate) fn subsample(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    (horizontal, vertical): (usize, usize),
+) -> Vec<u8> {
+    let units_across = (width + horizontal - 1) / horizontal;
+    let units_down = (height + vertical - 1) / vertical;
+    let block = horizontal * vertical;
+    let mut out = Vec::with_capacity(units_across * units_down * (block + 2));
+
+    for unit_y in 0..units_down {
+        for unit_x in 0..units_across {
+            let (mut cb, mut cr) = (0usize, 0usize);
+            for dy in 0..vertical {
+                let y = (unit_y * vertical + dy).min(height - 1);
+                for dx in 0..horizontal {
+                    let x = (unit_x * horizontal + dx).min(width - 1);
+                    let pixel = &data[(y * width + x) * 3..][..3];
+                    out.push(pixel[0]);
+                    cb += usize::from(pixel[1]);
+                    cr += usize::from(pixel[2]);
+                }
+            }
+            out.push(((cb + block / 2) / block) as u8);
+            out.push(((cr + block / 2) / block) as u8);
+        }
+    }
+    out
+}
+
+/// Size in bytes of `rows` subsampled rows of an image `width` pixels wide.
+pub(crate) fn subsampled_len(
+    width: usize,
+    rows: usize,
+    (horizontal, vertical): (usize, usize),
+) -> usize {
+    let units_across = (width + horizontal - 1) / horizontal;
+    let units_down = (rows + vertical - 1) / vertical;
+    units_across * units_down * (horizontal * vertical + 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subsample_4_2_0() {
+        // 3x2 pixels, the last column is replicated into the second data unit.
+        let data = [
+            10, 100, 200, 20, 102, 202, 30, 50, 60, //
+            40, 104, 204, 50, 106, 206, 60, 70, 80,
+        ];
+        let packed = subsample(&data, 3, 2, (2, 2));
+        assert_eq!(packed, [10, 20, 40, 50, 103, 203, 30, 30, 60, 60, 60, 70]);
+        assert_eq!(packed.len(), subsampled_len(3, 2, (2, 2)));
+    }
+}
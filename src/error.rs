@@ -4,15 +4,18 @@ use std::io;
 use std::str;
 use std::string;
 
-use crate::decoder::{ifd::Value, ChunkType};
+use crate::decoder::{ifd::Value, ChunkType, DecodingResult};
 use crate::tags::{
-    CompressionMethod, PhotometricInterpretation, PlanarConfiguration, SampleFormat, Tag,
+    CompressionMethod, PhotometricInterpretation, PlanarConfiguration, Predictor, SampleFormat, Tag,
 };
 use crate::ColorType;
 
 use crate::weezl::LzwError;
 
 /// Tiff error kinds.
+///
+/// The list of variants may grow to incorporate errors of future features. Matching against this
+/// exhaustively is not covered by interface stability guarantees.
 #[derive(Debug)]
 pub enum TiffError {
     /// The Image is not formatted properly.
@@ -33,6 +36,15 @@ pub enum TiffError {
 
     /// The image does not support the requested operation
     UsageError(UsageError),
+
+    /// The file ends within the data of the image.
+    ///
+    /// The first `rows` rows of `partial` were decoded completely, the remaining ones are zero.
+    Truncated { rows: u32, partial: DecodingResult },
+
+    #[doc(hidden)]
+    /// Do not match against this variant. It may get removed.
+    __NonExhaustive,
 }
 
 /// The image is not formatted properly.
@@ -63,10 +75,54 @@ pub enum TiffFormatError {
     ByteExpected(Value),
     UnsignedIntegerExpected(Value),
     SignedIntegerExpected(Value),
+    /// A free-form format error.
+    ///
+    /// The decoder and encoder report structured variants instead. This is kept so that code
+    /// constructing or matching it keeps compiling.
     Format(String),
     RequiredTagEmpty(Tag),
     StripTileTagConflict,
     CycleInOffsets,
+    /// Data referenced by the file lies beyond its end.
+    OffsetOutOfBounds {
+        offset: u64,
+        length: u64,
+    },
+    /// Compressed data could not be decompressed.
+    CompressedDataCorrupt(String),
+    /// The number of strip or tile offsets or byte counts of an image does not match its layout.
+    ChunkCountMismatch {
+        image: usize,
+        offsets: usize,
+        byte_counts: usize,
+        expected: u64,
+    },
+    /// A strip or tile of an image lies outside of the file or overlaps its header.
+    ChunkOutOfFile {
+        image: usize,
+        offset: u64,
+        length: u64,
+    },
+    /// Two strips or tiles of an image share bytes of the file.
+    OverlappingChunks {
+        image: usize,
+        first: u64,
+        second: u64,
+    },
+    /// An uncompressed strip or tile does not have the size implied by the image layout.
+    UncompressedChunkSize {
+        image: usize,
+        chunk: usize,
+        actual: u64,
+        expected: u64,
+    },
+    /// A predictor is set for a palette image, whose samples are indices rather than intensities.
+    PaletteWithPredictor(Predictor),
+    /// The number of samples per pixel contradicts the photometric interpretation.
+    SamplesPerPixelMismatch {
+        interpretation: PhotometricInterpretation,
+        samples: u8,
+    },
     #[doc(hidden)]
     /// Do not match against this variant. It may get removed.
     __NonExhaustive,
@@ -119,6 +175,65 @@ impl fmt::Display for TiffFormatError {
             RequiredTagEmpty(ref val) => write!(fmt, "Required tag {:?} was empty.", val),
             StripTileTagConflict => write!(fmt, "File should contain either (StripByteCounts and StripOffsets) or (TileByteCounts and TileOffsets), other combination was found."),
             CycleInOffsets => write!(fmt, "File contained a cycle in the list of IFDs"),
+            OffsetOutOfBounds { offset, length } => write!(
+                fmt,
+                "Data of {} bytes at offset {} lies outside of the file.",
+                length, offset
+            ),
+            CompressedDataCorrupt(ref message) => {
+                write!(fmt, "Compressed data is corrupt: {}.", message)
+            }
+            ChunkCountMismatch {
+                image,
+                offsets,
+                byte_counts,
+                expected,
+            } => write!(
+                fmt,
+                "Image {}: {} offsets and {} byte counts for {} chunks.",
+                image, offsets, byte_counts, expected
+            ),
+            ChunkOutOfFile {
+                image,
+                offset,
+                length,
+            } => write!(
+                fmt,
+                "Image {}: chunk of {} bytes at offset {} is outside of the file.",
+                image, length, offset
+            ),
+            OverlappingChunks {
+                image,
+                first,
+                second,
+            } => write!(
+                fmt,
+                "Image {}: chunks at offsets {} and {} overlap.",
+                image, first, second
+            ),
+            UncompressedChunkSize {
+                image,
+                chunk,
+                actual,
+                expected,
+            } => write!(
+                fmt,
+                "Image {}: uncompressed chunk {} has {} bytes, expected {}.",
+                image, chunk, actual, expected
+            ),
+            PaletteWithPredictor(predictor) => write!(
+                fmt,
+                "Predictor {:?} cannot be applied to the indices of a palette image.",
+                predictor
+            ),
+            SamplesPerPixelMismatch {
+                interpretation,
+                samples,
+            } => write!(
+                fmt,
+                "SamplesPerPixel {} contradicts PhotometricInterpretation {:?}.",
+                samples, interpretation
+            ),
             __NonExhaustive => unreachable!(),
         }
     }
@@ -146,6 +261,7 @@ pub enum TiffUnsupportedError {
     UnsupportedBitsPerChannel(u8),
     UnsupportedPlanarConfig(Option<PlanarConfiguration>),
     UnsupportedDataType,
+    NonNativeByteOrder,
     #[doc(hidden)]
     /// Do not match against this variant. It may get removed.
     __NonExhaustive,
@@ -194,6 +310,7 @@ impl fmt::Display for TiffUnsupportedError {
                 write!(fmt, "Unsupported planar configuration “{:?}”.", config)
             }
             UnsupportedDataType => write!(fmt, "Unsupported data type."),
+            NonNativeByteOrder => write!(fmt, "Writing in non-native byte order is unsupported."),
             __NonExhaustive => unreachable!(),
         }
     }
@@ -202,10 +319,17 @@ impl fmt::Display for TiffUnsupportedError {
 /// User attempted to use the Decoder in a way that is incompatible with a specific image.
 ///
 /// For example: attempting to read a tile from a stripped image.
+///
+/// The list of variants may grow to incorporate errors of future features. Matching against this
+/// exhaustively is not covered by interface stability guarantees.
 #[derive(Debug)]
 pub enum UsageError {
     InvalidChunkType(ChunkType, ChunkType),
     InvalidChunkIndex(u32),
+    UncompressedChunkRequired,
+    #[doc(hidden)]
+    /// Do not match against this variant. It may get removed.
+    __NonExhaustive,
 }
 
 impl fmt::Display for UsageError {
@@ -220,6 +344,11 @@ impl fmt::Display for UsageError {
                 )
             }
             InvalidChunkIndex(index) => write!(fmt, "Image chunk index ({}) requested.", index),
+            UncompressedChunkRequired => write!(
+                fmt,
+                "Requested operation is only valid for chunks without compression or predictor."
+            ),
+            __NonExhaustive => unreachable!(),
         }
     }
 }
@@ -238,6 +367,12 @@ impl fmt::Display for TiffError {
             TiffError::LimitsExceeded => write!(fmt, "The Decoder limits are exceeded"),
             TiffError::IntSizeError => write!(fmt, "Platform or format size limits exceeded"),
             TiffError::UsageError(ref e) => write!(fmt, "Usage error: {}", e),
+            TiffError::Truncated { rows, .. } => write!(
+                fmt,
+                "The file ends after {} complete rows of the image",
+                rows
+            ),
+            TiffError::__NonExhaustive => unreachable!(),
         }
     }
 }
@@ -251,6 +386,8 @@ impl Error for TiffError {
             TiffError::LimitsExceeded => "Decoder limits exceeded",
             TiffError::IntSizeError => "Platform or format size limits exceeded",
             TiffError::UsageError(..) => "Invalid usage",
+            TiffError::Truncated { .. } => "Truncated file",
+            TiffError::__NonExhaustive => unreachable!(),
         }
     }
 
@@ -264,6 +401,10 @@ impl Error for TiffError {
 
 impl From<io::Error> for TiffError {
     fn from(err: io::Error) -> TiffError {
+        // Decompressing readers report exceeded limits as wrapped decoder errors.
+        if err.get_ref().map_or(false, |inner| inner.is::<TiffError>()) {
+            return *err.into_inner().unwrap().downcast::<TiffError>().unwrap();
+        }
         TiffError::IoError(err)
     }
 }
@@ -307,9 +448,9 @@ impl From<std::num::TryFromIntError> for TiffError {
 impl From<LzwError> for TiffError {
     fn from(err: LzwError) -> TiffError {
         match err {
-            LzwError::InvalidCode => TiffError::FormatError(TiffFormatError::Format(String::from(
-                "LZW compressed data corrupted",
-            ))),
+            LzwError::InvalidCode => TiffError::FormatError(
+                TiffFormatError::CompressedDataCorrupt(String::from("invalid LZW code")),
+            ),
         }
     }
 }
@@ -18,6 +18,9 @@ pub mod tags;
 pub use self::error::{TiffError, TiffFormatError, TiffResult, TiffUnsupportedError, UsageError};
 
 /// An enumeration over supported color types and their bit depths
+///
+/// The list of variants may grow to incorporate color types of future features. Matching against
+/// this exhaustively is not covered by interface stability guarantees.
 #[derive(Copy, PartialEq, Eq, Debug, Clone, Hash)]
 pub enum ColorType {
     /// Pixel is grayscale
@@ -37,4 +40,11 @@ pub enum ColorType {
 
     /// Pixel is CMYK
     CMYK(u8),
+
+    /// Pixel has samples whose meaning the decoder does not interpret, all of the same bit depth
+    Multiband { bit_depth: u8, num_samples: u16 },
+
+    #[doc(hidden)]
+    /// Do not match against this variant. It may get removed.
+    __NonExhaustive,
 }
@@ -9,6 +9,7 @@ macro_rules! tags {
     } => {
         $( #[$enum_attr] )*
         #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub enum $name {
             $($(#[$ident_attr])* $tag,)*
             // FIXME: switch to non_exhaustive once stabilized and compiler requirement new enough
@@ -83,7 +84,7 @@ pub enum Tag(u16) unknown("A private or extension tag") {
     Compression = 259, // TODO add support for 2 and 32773
     Copyright = 33_432,
     DateTime = 306,
-    ExtraSamples = 338, // TODO add support
+    ExtraSamples = 338,
     FillOrder = 266, // TODO add support
     FreeByteCounts = 289, // TODO add support
     FreeOffsets = 288, // TODO add support
@@ -98,7 +99,7 @@ pub enum Tag(u16) unknown("A private or extension tag") {
     MinSampleValue = 280, // TODO add support
     Model = 272,
     NewSubfileType = 254, // TODO add support
-    Orientation = 274, // TODO add support
+    Orientation = 274,
     PhotometricInterpretation = 262,
     PlanarConfiguration = 284,
     ResolutionUnit = 296, // TODO add support
@@ -117,12 +118,55 @@ pub enum Tag(u16) unknown("A private or extension tag") {
     TileLength = 323,
     TileOffsets = 324,
     TileByteCounts = 325,
+    SubIfd = 330,
+    // Document storage and retrieval
+    DocumentName = 269,
+    PageName = 285,
+    XPosition = 286,
+    YPosition = 287,
+    PageNumber = 297,
     // Data Sample Format
     SampleFormat = 339,
     SMinSampleValue = 340, // TODO add support
     SMaxSampleValue = 341, // TODO add support
     // JPEG
     JPEGTables = 347,
+    // YCbCr
+    YCbCrCoefficients = 529,
+    YCbCrSubSampling = 530,
+    YCbCrPositioning = 531,
+    ReferenceBlackWhite = 532,
+    // XMP metadata
+    Xmp = 700,
+    // IPTC metadata
+    Iptc = 33723,
+    // Photoshop image resources
+    Photoshop = 34377,
+    // ICC color management
+    IccProfile = 34675,
+    // EXIF
+    ExifDirectory = 34665,
+    GpsDirectory = 34853,
+    InteropDirectory = 40965,
+    ExposureTime = 33434,
+    FNumber = 33437,
+    ExposureProgram = 34850,
+    PhotographicSensitivity = 34855,
+    ExifVersion = 36864,
+    DateTimeOriginal = 36867,
+    DateTimeDigitized = 36868,
+    ShutterSpeedValue = 37377,
+    ApertureValue = 37378,
+    ExposureBiasValue = 37380,
+    MeteringMode = 37383,
+    Flash = 37385,
+    FocalLength = 37386,
+    MakerNote = 37500,
+    UserComment = 37510,
+    ColorSpace = 40961,
+    PixelXDimension = 40962,
+    PixelYDimension = 40963,
+    LensModel = 42036,
     // GeoTIFF
     ModelPixelScaleTag = 33550, // (SoftDesk)
     ModelTransformationTag = 34264, // (JPL Carto Group)
@@ -131,6 +175,9 @@ pub enum Tag(u16) unknown("A private or extension tag") {
     GeoDoubleParamsTag = 34736, // (SPOT)
     GeoAsciiParamsTag = 34737, // (SPOT)
     GdalNodata = 42113, // Contains areas with missing data
+    // ImageJ
+    IJMetadataByteCounts = 50838,
+    IJMetadata = 50839,
 }
 }
 
@@ -191,7 +238,7 @@ pub enum CompressionMethod(u16) {
 }
 
 tags! {
-pub enum PhotometricInterpretation(u16) {
+pub enum PhotometricInterpretation(u16) unknown("An unknown photometric interpretation") {
     WhiteIsZero = 0,
     BlackIsZero = 1,
     RGB = 2,
@@ -203,6 +250,41 @@ pub enum PhotometricInterpretation(u16) {
 }
 }
 
+tags! {
+/// Type to represent the meaning of extra samples
+pub enum ExtraSamples(u16) unknown("An unknown extra sample type") {
+    Unspecified = 0,
+    /// Alpha that is premultiplied into the color samples
+    AssociatedAlpha = 1,
+    /// Alpha that is independent of the color samples
+    UnassociatedAlpha = 2,
+}
+}
+
+tags! {
+/// The orientation of the image with respect to the rows and columns, named by the position of
+/// the 0th row and 0th column as the image should be displayed
+pub enum Orientation(u16) unknown("An unknown orientation") {
+    /// The 0th row is the top, the 0th column the left side (no transformation)
+    TopLeft = 1,
+    /// The 0th row is the top, the 0th column the right side (mirrored horizontally)
+    TopRight = 2,
+    /// The 0th row is the bottom, the 0th column the right side (rotated 180°)
+    BottomRight = 3,
+    /// The 0th row is the bottom, the 0th column the left side (mirrored vertically)
+    BottomLeft = 4,
+    /// The 0th row is the left side, the 0th column the top (transposed)
+    LeftTop = 5,
+    /// The 0th row is the right side, the 0th column the top (rotate 90° clockwise to display)
+    RightTop = 6,
+    /// The 0th row is the right side, the 0th column the bottom (transversed)
+    RightBottom = 7,
+    /// The 0th row is the left side, the 0th column the bottom (rotate 90° counter-clockwise
+    /// to display)
+    LeftBottom = 8,
+}
+}
+
 tags! {
 pub enum PlanarConfiguration(u16) {
     Chunky = 1,
@@ -235,3 +317,75 @@ pub enum SampleFormat(u16) unknown("An unknown extension sample format") {
     Void = 4,
 }
 }
+
+tags! {
+/// GeoTIFF keys of the `GeoKeyDirectory`
+pub enum GeoKey(u16) unknown("A private or unknown geokey") {
+    // GeoTIFF configuration keys
+    GTModelType = 1024,
+    GTRasterType = 1025,
+    GTCitation = 1026,
+    // Geographic coordinate system keys
+    GeographicType = 2048,
+    GeogCitation = 2049,
+    GeogGeodeticDatum = 2050,
+    GeogPrimeMeridian = 2051,
+    GeogLinearUnits = 2052,
+    GeogLinearUnitSize = 2053,
+    GeogAngularUnits = 2054,
+    GeogAngularUnitSize = 2055,
+    GeogEllipsoid = 2056,
+    GeogSemiMajorAxis = 2057,
+    GeogSemiMinorAxis = 2058,
+    GeogInvFlattening = 2059,
+    GeogAzimuthUnits = 2060,
+    GeogPrimeMeridianLong = 2061,
+    // Projected coordinate system keys
+    ProjectedCSType = 3072,
+    PCSCitation = 3073,
+    Projection = 3074,
+    ProjCoordTrans = 3075,
+    ProjLinearUnits = 3076,
+    ProjLinearUnitSize = 3077,
+    ProjStdParallel1 = 3078,
+    ProjStdParallel2 = 3079,
+    ProjNatOriginLong = 3080,
+    ProjNatOriginLat = 3081,
+    ProjFalseEasting = 3082,
+    ProjFalseNorthing = 3083,
+    ProjFalseOriginLong = 3084,
+    ProjFalseOriginLat = 3085,
+    ProjFalseOriginEasting = 3086,
+    ProjFalseOriginNorthing = 3087,
+    ProjCenterLong = 3088,
+    ProjCenterLat = 3089,
+    ProjCenterEasting = 3090,
+    ProjCenterNorthing = 3091,
+    ProjScaleAtNatOrigin = 3092,
+    ProjScaleAtCenter = 3093,
+    ProjAzimuthAngle = 3094,
+    ProjStraightVertPoleLong = 3095,
+    // Vertical coordinate system keys
+    VerticalCSType = 4096,
+    VerticalCitation = 4097,
+    VerticalDatum = 4098,
+    VerticalUnits = 4099,
+}
+}
+
+tags! {
+/// GeoTIFF model types, the value of `GeoKey::GTModelType`
+pub enum ModelType(u16) unknown("An unknown or user-defined model type") {
+    Projected = 1,
+    Geographic = 2,
+    Geocentric = 3,
+}
+}
+
+tags! {
+/// GeoTIFF raster types, the value of `GeoKey::GTRasterType`
+pub enum RasterType(u16) unknown("An unknown or user-defined raster type") {
+    PixelIsArea = 1,
+    PixelIsPoint = 2,
+}
+}
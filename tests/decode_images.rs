@@ -1,13 +1,37 @@
 extern crate tiff;
 
-use tiff::decoder::{ifd, Decoder, DecodingResult};
-use tiff::ColorType;
-
+use tiff::decoder::{
+    ifd, Decoder, DecodingEstimate, DecodingMode, DecodingResult, DecodingStatistics,
+    DecodingWarning, Limits, PositionedReader, SampleType,
+};
+use tiff::encoder::{colortype, compression, Rational, SRational, TiffEncoder, TiffValue};
+use tiff::tags::{
+    CompressionMethod, PhotometricInterpretation, Predictor, ResolutionUnit, Tag, Type,
+};
+use tiff::{ColorType, TiffError, TiffFormatError, TiffUnsupportedError, UsageError};
+
+use std::cell::Cell;
 use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
 use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
 const TEST_IMAGE_DIR: &str = "./tests/images/";
 
+/// Encodes a file in memory, to test how the decoder handles its contents.
+fn encode(write: impl FnOnce(&mut TiffEncoder<&mut Cursor<Vec<u8>>>)) -> Vec<u8> {
+    let mut file = Cursor::new(Vec::new());
+    write(&mut TiffEncoder::new(&mut file).unwrap());
+    file.into_inner()
+}
+
+/// Appends to a file encoded in memory, e.g. a directory reusing the data of an earlier image.
+fn append(file: &mut Vec<u8>, write: impl FnOnce(&mut TiffEncoder<&mut Cursor<&mut Vec<u8>>>)) {
+    let mut file = Cursor::new(file);
+    write(&mut TiffEncoder::append(&mut file).unwrap());
+}
+
 macro_rules! test_image_sum {
     ($name:ident, $buffer:ident, $sum_ty:ty) => {
         fn $name(file: &str, expected_type: ColorType, expected_sum: $sum_ty) {
@@ -53,6 +77,31 @@ fn test_image_color_type_unsupported(file: &str, expected_type: ColorType) {
     });
 }
 
+#[test]
+fn test_gray_u1_white_is_zero() {
+    let path = PathBuf::from(TEST_IMAGE_DIR).join("miniswhite-1c-1b.tiff");
+    let mut decoder = Decoder::new(File::open(path).unwrap()).unwrap();
+    assert_eq!(decoder.colortype().unwrap(), ColorType::Gray(1));
+    let decoded = match decoder.read_image().unwrap() {
+        DecodingResult::U8(decoded) => decoded,
+        _ => panic!("Wrong bit depth"),
+    };
+
+    // Rows are packed like those of the reference bitmap, in which set bits are black.
+    let reference =
+        std::fs::read(PathBuf::from(TEST_IMAGE_DIR).join("miniswhite-1c-1b.pbm")).unwrap();
+    let row_bytes = (157 + 7) / 8;
+    assert_eq!(decoded.len(), row_bytes * 151);
+    let pixels = &reference[reference.len() - decoded.len()..];
+    for (decoded, pixels) in decoded.chunks(row_bytes).zip(pixels.chunks(row_bytes)) {
+        for (x, (&decoded, &pixel)) in decoded.iter().zip(pixels).enumerate() {
+            // The padding bits at the end of a row are unspecified.
+            let mask = if x == row_bytes - 1 { 0xf8 } else { 0xff };
+            assert_eq!(decoded & mask, !pixel & mask);
+        }
+    }
+}
+
 #[test]
 fn test_cmyk_u8() {
     test_image_sum_u8("cmyk-3c-8b.tiff", ColorType::CMYK(8), 8522658);
@@ -287,7 +336,7 @@ fn test_tiled_incremental() {
 
 #[test]
 fn test_div_zero() {
-    use tiff::{TiffError, TiffFormatError};
+    use tiff::TiffError;
 
     let image = [
         73, 73, 42, 0, 8, 0, 0, 0, 8, 0, 0, 1, 4, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 40, 1, 0, 0,
@@ -297,10 +346,12 @@ fn test_div_zero() {
         178, 178, 178,
     ];
 
-    let err = tiff::decoder::Decoder::new(std::io::Cursor::new(&image)).unwrap_err();
+    // The missing strip byte counts are derived from the dimensions, which exceed the data.
+    let mut decoder = tiff::decoder::Decoder::new(std::io::Cursor::new(&image)).unwrap();
+    let err = decoder.read_image().unwrap_err();
 
     match err {
-        TiffError::FormatError(TiffFormatError::StripTileTagConflict) => {}
+        TiffError::Truncated { rows: 0, .. } => {}
         unexpected => panic!("Unexpected error {}", unexpected),
     }
 }
@@ -315,6 +366,28 @@ fn test_too_many_value_bytes() {
         0, 89, 89, 89, 89, 89, 89, 89, 89, 96, 1, 20, 89, 89, 89, 89, 18,
     ];
 
+    // Skipping the unknown entries at their full BigTiff size runs past the end of the file.
+    let error = tiff::decoder::Decoder::new(std::io::Cursor::new(&image)).unwrap_err();
+
+    match error {
+        tiff::TiffError::IoError(ref err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {}
+        unexpected => panic!("Unexpected error {}", unexpected),
+    }
+}
+
+#[test]
+fn test_too_many_value_bytes_full_size_entries() {
+    // Entries of unknown type are padded to the 20 bytes of a BigTiff entry.
+    let image = [
+        73, 73, 43, 0, 8, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 0, 8, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 23, 0, 12, 0, 0, 65, 4, 0, 1, 6, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+        16, 0, 1, 0, 0, 0, 0, 0, 0, 128, 0, 0, 0, 0, 0, 0, 0, 3, 0, 1, 0, 0, 0, 1, 0, 0, 0, 59, 73,
+        84, 0, 0, 0, 0, 0, 0, 0, 0, 186, 202, 83, 240, 66, 1, 53, 22, 56, 47, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 1, 222, 4, 0, 58, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1,
+        0, 0, 1, 1, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 100, 0, 0, 89, 89, 89, 89, 89, 89, 89, 89, 0,
+        0, 0, 0, 0, 0, 0, 0, 96, 1, 20, 89, 89, 89, 89, 18,
+    ];
+
     let error = tiff::decoder::Decoder::new(std::io::Cursor::new(&image)).unwrap_err();
 
     match error {
@@ -323,6 +396,35 @@ fn test_too_many_value_bytes() {
     }
 }
 
+#[test]
+fn test_intermediate_buffer_limit() {
+    let path = PathBuf::from(TEST_IMAGE_DIR).join("tiled-jpeg-rgb-u8.tif");
+    let mut decoder = Decoder::new(File::open(&path).unwrap()).unwrap();
+    assert!(decoder.read_chunk(0).is_ok());
+
+    let mut limits = tiff::decoder::Limits::default();
+    limits.intermediate_buffer_size = 1024;
+    let mut decoder = Decoder::new(File::open(&path).unwrap())
+        .unwrap()
+        .with_limits(limits);
+    match decoder.read_chunk(0) {
+        Err(tiff::TiffError::LimitsExceeded) => {}
+        unexpected => panic!("Unexpected result {:?}", unexpected.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_lzw_without_intermediate_buffer() {
+    // LZW strips are decompressed straight into the output as they are read.
+    let path = PathBuf::from(TEST_IMAGE_DIR).join("issue_69_lzw.tiff");
+    let mut limits = tiff::decoder::Limits::default();
+    limits.intermediate_buffer_size = 0;
+    let mut decoder = Decoder::new(File::open(&path).unwrap())
+        .unwrap()
+        .with_limits(limits);
+    decoder.read_image().unwrap();
+}
+
 #[test]
 fn fuzzer_testcase5() {
     let image = [
@@ -380,14 +482,13 @@ fn oom() {
         0, 0, 0, 40, 0, 0, 0, 23, 1, 4, 0, 1, 0, 0, 0, 178, 48, 178, 178, 178, 178, 162, 178,
     ];
 
-    let mut decoder = tiff::decoder::Decoder::new(std::io::Cursor::new(&image)).unwrap();
-
-    let err = decoder.read_image().unwrap_err();
+    let err = tiff::decoder::Decoder::new(std::io::Cursor::new(&image)).unwrap_err();
 
     match err {
-        tiff::TiffError::UnsupportedError(tiff::TiffUnsupportedError::InterpretationWithBits(
-            ..,
-        )) => {}
+        tiff::TiffError::FormatError(tiff::TiffFormatError::SamplesPerPixelMismatch {
+            interpretation: tiff::tags::PhotometricInterpretation::RGB,
+            samples: 1,
+        }) => {}
         unexpected => panic!("Unexpected error {}", unexpected),
     }
 }
@@ -406,7 +507,7 @@ fn fuzzer_testcase4() {
     let err = decoder.read_image().unwrap_err();
 
     match err {
-        tiff::TiffError::IoError(_) => {}
+        tiff::TiffError::Truncated { rows: 0, .. } => {}
         unexpected => panic!("Unexpected error {}", unexpected),
     }
 }
@@ -490,6 +591,28 @@ fn timeout() {
     }
 }
 
+#[cfg(feature = "readahead")]
+#[test]
+fn test_readahead() {
+    for file in &[
+        "rgb-3c-8b.tiff",
+        "tiled-rgb-u8.tif",
+        "minisblack-1c-8b.tiff",
+    ] {
+        let path = PathBuf::from(TEST_IMAGE_DIR).join(file);
+        let mut decoder = Decoder::new(File::open(&path).unwrap()).unwrap();
+        let mut hinted = Decoder::new(File::open(&path).unwrap())
+            .unwrap()
+            .with_readahead();
+        match (decoder.read_image().unwrap(), hinted.read_image().unwrap()) {
+            (DecodingResult::U8(expected), DecodingResult::U8(decoded)) => {
+                assert_eq!(decoded, expected)
+            }
+            _ => panic!("Wrong bit depth"),
+        }
+    }
+}
+
 #[test]
 fn test_no_rows_per_strip() {
     test_image_sum_u8("no_rows_per_strip.tiff", ColorType::RGB(8), 99448840);
@@ -504,3 +627,2395 @@ fn test_predictor_3_rgb_f32() {
 fn test_predictor_3_gray_f32() {
     test_image_sum_f32("predictor-3-gray-f32.tif", ColorType::Gray(32), 20008.275);
 }
+
+#[test]
+fn test_tag_list() {
+    use tiff::tags::Tag;
+
+    let path = PathBuf::from(TEST_IMAGE_DIR).join("minisblack-1c-8b.tiff");
+    let img_file = File::open(path).expect("Cannot find test image!");
+    let mut decoder = Decoder::new(img_file).expect("Cannot create decoder");
+
+    let tag_list = decoder.tag_list();
+    assert!(tag_list
+        .windows(2)
+        .all(|pair| pair[0].to_u16() < pair[1].to_u16()));
+    assert_eq!(tag_list[0], Tag::ImageWidth);
+
+    let tags = decoder.tags().unwrap();
+    assert_eq!(tags.len(), tag_list.len());
+    let (width, height) = decoder.dimensions().unwrap();
+    for (tag, value) in tags {
+        match tag {
+            Tag::ImageWidth => assert_eq!(value.into_u32().unwrap(), width),
+            Tag::ImageLength => assert_eq!(value.into_u32().unwrap(), height),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn test_ifd_limits() {
+    let img_file = encode(|tiff| {
+        for _ in 0..3 {
+            tiff.write_image::<colortype::Gray8>(2, 2, &[1, 2, 3, 4])
+                .unwrap();
+        }
+    });
+    let data = img_file;
+
+    let mut limits = Limits::default();
+    limits.ifd_count = 2;
+    let mut decoder = Decoder::new(Cursor::new(&data))
+        .unwrap()
+        .with_limits(limits);
+    decoder.next_image().unwrap();
+    assert!(matches!(
+        decoder.next_image(),
+        Err(TiffError::LimitsExceeded)
+    ));
+
+    let mut limits = Limits::default();
+    limits.ifd_entries = 4;
+    let mut decoder = Decoder::new(Cursor::new(&data))
+        .unwrap()
+        .with_limits(limits);
+    assert!(matches!(
+        decoder.next_image(),
+        Err(TiffError::LimitsExceeded)
+    ));
+}
+
+#[test]
+fn test_relaxed_header() {
+    let img_file = encode(|tiff| {
+        // Raw formats such as RW2 describe their sensor data with their own tags.
+        let mut directory = tiff.new_directory().unwrap();
+        directory.write_tag(Tag::Make, "Panasonic").unwrap();
+        directory.finish().unwrap();
+        tiff.write_image::<colortype::Gray8>(2, 2, &[1, 2, 3, 4])
+            .unwrap();
+    });
+    let data = img_file;
+
+    for magic in [*b"U\0", *b"RO", *b"RS"].iter() {
+        let mut data = data.clone();
+        data[2..4].copy_from_slice(magic);
+
+        assert!(matches!(
+            Decoder::new(Cursor::new(&data)),
+            Err(TiffError::FormatError(
+                TiffFormatError::TiffSignatureInvalid
+            ))
+        ));
+
+        let mut decoder = Decoder::new_relaxed(Cursor::new(&data)).unwrap();
+        assert_eq!(decoder.dimensions().unwrap(), (0, 0));
+        assert_eq!(
+            decoder.get_tag_ascii_string(Tag::Make).unwrap(),
+            "Panasonic"
+        );
+        decoder.next_image().unwrap();
+        assert_eq!(decoder.dimensions().unwrap(), (2, 2));
+        match decoder.read_image().unwrap() {
+            DecodingResult::U8(decoded) => assert_eq!(decoded, [1, 2, 3, 4]),
+            _ => panic!("Wrong data type"),
+        }
+    }
+}
+
+#[test]
+fn test_chunk_slice() {
+    let image: Vec<u16> = (0..15).collect();
+    let img_file = encode(|tiff| {
+        tiff.image_builder::<colortype::Gray16>(3, 5)
+            .rows_per_strip(2)
+            .build()
+            .unwrap()
+            .write_data(&image)
+            .unwrap();
+        tiff.new_image_with_compression::<colortype::Gray16, _>(3, 5, compression::Lzw::default())
+            .unwrap()
+            .write_data(&image)
+            .unwrap();
+    });
+    let data = img_file;
+
+    let mut decoder = Decoder::new(Cursor::new(&data[..])).unwrap();
+    let mut borrowed = Vec::new();
+    for chunk in 0..decoder.strip_count().unwrap() {
+        borrowed.extend_from_slice(decoder.chunk_slice(chunk).unwrap());
+    }
+    let expected: Vec<u8> = image
+        .iter()
+        .flat_map(|v| v.to_ne_bytes().to_vec())
+        .collect();
+    assert_eq!(borrowed, expected);
+
+    decoder.next_image().unwrap();
+    assert!(matches!(
+        decoder.chunk_slice(0),
+        Err(TiffError::UsageError(UsageError::UncompressedChunkRequired))
+    ));
+}
+
+/// Counts the reads from the wrapped reader.
+struct CountingReader<R> {
+    inner: R,
+    reads: Rc<Cell<usize>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reads.set(self.reads.get() + 1);
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for CountingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[test]
+fn test_tag_values_cached() {
+    let img_file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image
+            .encoder()
+            .write_tag(Tag::Artist, "Image-tiff")
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+    });
+
+    let reads = Rc::new(Cell::new(0));
+    let reader = CountingReader {
+        inner: Cursor::new(img_file),
+        reads: reads.clone(),
+    };
+    let mut decoder = Decoder::new(reader).unwrap();
+    let tags = decoder.tags().unwrap();
+    assert!(tags.contains(&(Tag::Artist, ifd::Value::Ascii("Image-tiff".into()))));
+    let after_first = reads.get();
+    assert_eq!(
+        decoder.get_tag_ascii_string(Tag::Artist).unwrap(),
+        "Image-tiff"
+    );
+    assert_eq!(decoder.tags().unwrap(), tags);
+    assert_eq!(reads.get(), after_first);
+}
+
+#[test]
+fn test_tag_prefetch() {
+    let img_file = encode(|tiff| {
+        for _ in 0..2 {
+            let mut image = tiff
+                .image_builder::<colortype::Gray8>(2, 4)
+                .rows_per_strip(1)
+                .build()
+                .unwrap();
+            let encoder = image.encoder();
+            encoder.write_tag(Tag::Artist, "Image-tiff").unwrap();
+            encoder.write_tag(Tag::Software, "image-tiff").unwrap();
+            encoder
+                .write_tag(Tag::ImageDescription, "Prefetched tags")
+                .unwrap();
+            encoder
+                .write_tag(Tag::XResolution, Rational { n: 72, d: 1 })
+                .unwrap();
+            image.write_data(&[0; 8]).unwrap();
+        }
+    });
+    let data = img_file;
+
+    let mut decoder = Decoder::new(Cursor::new(&data)).unwrap();
+    let expected = decoder.tags().unwrap();
+    decoder.next_image().unwrap();
+    let expected_next = decoder.tags().unwrap();
+
+    let reads = Rc::new(Cell::new(0));
+    let reader = CountingReader {
+        inner: Cursor::new(&data),
+        reads: reads.clone(),
+    };
+    let mut decoder = Decoder::new(reader).unwrap().with_tag_prefetch(true);
+    let before = reads.get();
+    assert_eq!(decoder.tags().unwrap(), expected);
+    assert_eq!(reads.get(), before);
+
+    decoder.next_image().unwrap();
+    let before = reads.get();
+    assert_eq!(
+        decoder.get_tag_ascii_string(Tag::ImageDescription).unwrap(),
+        "Prefetched tags"
+    );
+    assert_eq!(decoder.tags().unwrap(), expected_next);
+    assert_eq!(reads.get(), before);
+}
+
+#[test]
+fn test_contiguous_strips_read_at_once() {
+    let image: Vec<u8> = (0..16 * 64).map(|i| i as u8).collect();
+    let img_file = encode(|tiff| {
+        tiff.image_builder::<colortype::Gray8>(16, 64)
+            .rows_per_strip(1)
+            .build()
+            .unwrap()
+            .write_data(&image)
+            .unwrap();
+    });
+
+    let reads = Rc::new(Cell::new(0));
+    let reader = CountingReader {
+        inner: Cursor::new(img_file),
+        reads: reads.clone(),
+    };
+    let mut decoder = Decoder::new(reader).unwrap();
+    assert_eq!(decoder.strip_count().unwrap(), 64);
+    let before = reads.get();
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(decoded) => assert_eq!(decoded, image),
+        _ => panic!("Wrong data type"),
+    }
+    assert!(reads.get() - before < 8);
+}
+
+#[test]
+fn test_read_buffer() {
+    let image: Vec<u16> = (0..32 * 32).map(|i| i as u16).collect();
+    let img_file = encode(|tiff| {
+        let mut encoder = tiff
+            .image_builder::<colortype::Gray16>(32, 32)
+            .compression(compression::Lzw)
+            .rows_per_strip(4)
+            .build()
+            .unwrap();
+        encoder
+            .encoder()
+            .write_tag(Tag::Artist, "Image-tiff")
+            .unwrap();
+        encoder.write_data(&image).unwrap();
+    });
+    let data = img_file;
+
+    let decode = |capacity: usize| {
+        let reads = Rc::new(Cell::new(0));
+        let reader = CountingReader {
+            inner: Cursor::new(&data),
+            reads: reads.clone(),
+        };
+        let mut decoder = Decoder::new(reader)
+            .unwrap()
+            .with_read_buffer(capacity)
+            .unwrap();
+        let before = reads.get();
+        let tags = decoder.tags().unwrap();
+        let decoded = decoder.read_image().unwrap();
+        (tags, decoded, reads.get() - before)
+    };
+
+    let (tags, decoded, unbuffered_reads) = decode(0);
+    let (buffered_tags, buffered, buffered_reads) = decode(16 * 1024);
+    assert_eq!(buffered_tags, tags);
+    match (decoded, buffered) {
+        (DecodingResult::U16(decoded), DecodingResult::U16(buffered)) => {
+            assert_eq!(decoded, image);
+            assert_eq!(buffered, image);
+        }
+        _ => panic!("Wrong data type"),
+    }
+    assert!(buffered_reads < unbuffered_reads);
+}
+
+#[test]
+fn test_read_buffer_across_chunks() {
+    let image: Vec<u16> = (0..32 * 32).map(|i| i as u16).collect();
+    let img_file = encode(|tiff| {
+        let encoder = tiff
+            .image_builder::<colortype::Gray16>(32, 32)
+            .rows_per_strip(4)
+            .build()
+            .unwrap();
+        encoder.write_data(&image).unwrap();
+    });
+    let data = img_file;
+
+    let reads = Rc::new(Cell::new(0));
+    let reader = CountingReader {
+        inner: Cursor::new(&data),
+        reads: reads.clone(),
+    };
+    let mut decoder = Decoder::new(reader)
+        .unwrap()
+        .with_read_buffer(16 * 1024)
+        .unwrap();
+    let mut decoded = Vec::new();
+    match decoder.read_chunk(0).unwrap() {
+        DecodingResult::U16(chunk) => decoded.extend(chunk),
+        _ => panic!("Wrong data type"),
+    }
+
+    // The buffer filled for the first strip holds the following ones as well.
+    let before = reads.get();
+    for chunk in 1..8 {
+        match decoder.read_chunk(chunk).unwrap() {
+            DecodingResult::U16(chunk) => decoded.extend(chunk),
+            _ => panic!("Wrong data type"),
+        }
+    }
+    assert_eq!(reads.get(), before);
+    assert_eq!(decoded, image);
+}
+
+#[test]
+fn test_estimate() {
+    let data: Vec<u16> = (0..50 * 30 * 3).map(|i| i as u16).collect();
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::RGB16>(50, 30).unwrap();
+        image.rows_per_strip(8).unwrap();
+        image.write_data(&data).unwrap();
+
+        let image = tiff
+            .image_builder::<colortype::Gray32Float>(40, 40)
+            .compression(compression::Lzw)
+            .predictor(Predictor::FloatingPoint)
+            .tile_size(16, 16)
+            .build()
+            .unwrap();
+        image.write_data(&[0.5f32; 40 * 40]).unwrap();
+    });
+
+    let decoder = Decoder::new(Cursor::new(file)).unwrap();
+    let estimate = decoder.estimate().unwrap();
+    assert_eq!(
+        estimate,
+        DecodingEstimate {
+            sample_type: SampleType::U16,
+            samples: 50 * 30 * 3,
+            buffer_bytes: 50 * 30 * 3 * 2,
+            chunk_buffer_bytes: 50 * 8 * 3 * 2,
+            // Uncompressed strips stored back to back are read at once.
+            intermediate_bytes: 50 * 30 * 3 * 2,
+            retained_bytes: 8 * 1024 * 1024,
+        }
+    );
+    let mut limits = Limits::default();
+    limits.intermediate_buffer_size = 1024 * 1024;
+    let decoder = decoder.with_limits(limits).with_chunk_cache(4096);
+    assert_eq!(
+        decoder.estimate().unwrap().retained_bytes,
+        1024 * 1024 + 4096
+    );
+    let mut decoder = decoder.with_limits(Limits::default());
+    match decoder.read_chunk(0).unwrap() {
+        DecodingResult::U16(chunk) => assert_eq!(chunk.len() * 2, 50 * 8 * 3 * 2),
+        _ => panic!("Wrong data type"),
+    }
+
+    decoder.next_image().unwrap();
+    let estimate = decoder.estimate().unwrap();
+    assert_eq!(estimate.sample_type, SampleType::F32);
+    assert_eq!(estimate.buffer_bytes, 40 * 40 * 4);
+    assert_eq!(estimate.chunk_buffer_bytes, 16 * 16 * 4);
+    assert!(estimate.intermediate_bytes >= 2 * 16 * 4);
+    match decoder.read_image().unwrap() {
+        DecodingResult::F32(decoded) => assert_eq!(decoded.len() as u64 * 4, estimate.buffer_bytes),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+#[test]
+fn test_positioned_reads() {
+    let data: Vec<u16> = (0..70 * 50).map(|i| (i * 3) as u16).collect();
+    let file = encode(|tiff| {
+        let image = tiff
+            .image_builder::<colortype::Gray16>(70, 50)
+            .compression(compression::Lzw)
+            .tile_size(32, 32)
+            .build()
+            .unwrap();
+        image.write_data(&data).unwrap();
+    });
+    let file = Arc::new(file);
+
+    let mut decoder = Decoder::new(PositionedReader::new(Arc::clone(&file))).unwrap();
+    match decoder.read_image().unwrap() {
+        DecodingResult::U16(decoded) => assert_eq!(decoded, data),
+        _ => panic!("Wrong data type"),
+    }
+
+    let indices = [5, 0, 3, 3];
+    let chunks = decoder.read_chunks(&indices).unwrap();
+    assert_eq!(chunks.len(), indices.len());
+    let mut reference = Decoder::new(Cursor::new(&file[..])).unwrap();
+    for (&index, chunk) in indices.iter().zip(chunks) {
+        match (chunk, reference.read_chunk(index).unwrap()) {
+            (DecodingResult::U16(chunk), DecodingResult::U16(expected)) => {
+                assert_eq!(chunk, expected)
+            }
+            _ => panic!("Wrong data type"),
+        }
+    }
+
+    assert!(matches!(
+        decoder.read_chunks(&[6]),
+        Err(TiffError::FormatError(
+            TiffFormatError::InconsistentSizesEncountered
+        ))
+    ));
+
+    // Cached chunks are returned without reading the source again.
+    let mut decoder = Decoder::new(PositionedReader::new(Arc::clone(&file)))
+        .unwrap()
+        .with_chunk_cache(1 << 20);
+    let first = decoder.read_chunks(&[1, 4]).unwrap();
+    let bytes_read = decoder.statistics().bytes_read;
+    let second = decoder.read_chunks(&[4, 1]).unwrap();
+    assert_eq!(decoder.statistics().bytes_read, bytes_read);
+    match (&first[1], &second[0]) {
+        (DecodingResult::U16(first), DecodingResult::U16(second)) => assert_eq!(first, second),
+        _ => panic!("Wrong data type"),
+    }
+
+    // Point the last tile past the end of the file, the offsets follow the image data.
+    let offsets = reference.get_tag_u64_vec(Tag::TileOffsets).unwrap();
+    let counts = reference.get_tag_u64_vec(Tag::TileByteCounts).unwrap();
+    let data_end = (offsets[5] + counts[5]) as usize;
+    let mut corrupt = file.to_vec();
+    let last = (offsets[5] as u32).to_le_bytes();
+    let position = data_end
+        + corrupt[data_end..]
+            .windows(4)
+            .position(|window| window == last)
+            .unwrap();
+    corrupt[position..position + 4].copy_from_slice(&0x00ff_ffffu32.to_le_bytes());
+    let mut decoder = Decoder::new(PositionedReader::new(corrupt)).unwrap();
+    assert!(decoder.read_chunks(&[0]).is_ok());
+    assert!(matches!(
+        decoder.read_chunks(&[0, 5]),
+        Err(TiffError::FormatError(TiffFormatError::OffsetOutOfBounds {
+            offset: 0x00ff_ffff,
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn test_pipelined_read() {
+    let data: Vec<u8> = (0..61 * 45).map(|i| (i % 251) as u8).collect();
+    let file = encode(|tiff| {
+        let mut image = tiff
+            .new_image_with_compression::<colortype::Gray8, _>(
+                61,
+                45,
+                compression::Deflate::default(),
+            )
+            .unwrap();
+        image.rows_per_strip(4).unwrap();
+        image.write_data(&data).unwrap();
+    });
+    let file = Arc::new(file);
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let callback_reports = Arc::clone(&reports);
+    let mut decoder = Decoder::new(PositionedReader::new(Arc::clone(&file)))
+        .unwrap()
+        .with_progress(move |progress| {
+            callback_reports.lock().unwrap().push(*progress);
+            true
+        });
+    match decoder.read_image_pipelined().unwrap() {
+        DecodingResult::U8(decoded) => assert_eq!(decoded, data),
+        _ => panic!("Wrong data type"),
+    }
+
+    let counts = decoder.get_tag_u64_vec(Tag::StripByteCounts).unwrap();
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), counts.len());
+    let last = reports.last().unwrap();
+    assert_eq!(last.chunks_read, counts.len() as u64);
+    assert_eq!(last.bytes_read, counts.iter().sum::<u64>());
+}
+
+#[test]
+fn test_decoding_statistics() {
+    let data: Vec<u16> = (0..40 * 30).map(|i| (i * 11) as u16).collect();
+    let file = encode(|tiff| {
+        let mut image = tiff
+            .new_image_with_compression::<colortype::Gray16, _>(40, 30, compression::Lzw::default())
+            .unwrap();
+        image.rows_per_strip(8).unwrap();
+        image.write_data(&data).unwrap();
+    });
+    let file_len = file.len() as u64;
+
+    let mut decoder = Decoder::new(Cursor::new(&file[..]))
+        .unwrap()
+        .with_chunk_statistics();
+    match decoder.read_image().unwrap() {
+        DecodingResult::U16(decoded) => assert_eq!(decoded, data),
+        _ => panic!("Wrong data type"),
+    }
+
+    let counts = decoder.get_tag_u64_vec(Tag::StripByteCounts).unwrap();
+    let statistics = decoder.statistics();
+    assert!(statistics.bytes_read >= counts.iter().sum::<u64>());
+    assert!(statistics.bytes_read <= file_len);
+    assert_eq!(statistics.chunks.len(), counts.len());
+    for (index, (chunk, &count)) in statistics.chunks.iter().zip(&counts).enumerate() {
+        assert_eq!(chunk.chunk_index, index as u32);
+        assert_eq!(chunk.compression, CompressionMethod::LZW);
+        assert_eq!(chunk.compressed_bytes, count);
+    }
+
+    // The strips are contiguous: the file length is looked up once, and a single seek reaches
+    // all of them when reading the image, or one seek per strip when reading them one by one.
+    let mut decoder = Decoder::new(Cursor::new(&file[..])).unwrap();
+    decoder.reset_statistics();
+    decoder.read_image().unwrap();
+    assert_eq!(decoder.statistics().seeks, 2);
+    let mut decoder = Decoder::new(Cursor::new(&file[..])).unwrap();
+    decoder.reset_statistics();
+    for chunk in 0..counts.len() {
+        decoder.read_chunk(chunk as u32).unwrap();
+    }
+    assert_eq!(decoder.statistics().seeks, counts.len() as u64 + 1);
+
+    let mut decoder = Decoder::new(Cursor::new(&file[..]))
+        .unwrap()
+        .with_chunk_statistics();
+    decoder.reset_statistics();
+    assert_eq!(decoder.statistics(), DecodingStatistics::default());
+    decoder.read_chunk(2).unwrap();
+    let statistics = decoder.statistics();
+    assert_eq!(statistics.chunks.len(), 1);
+    assert_eq!(statistics.chunks[0].chunk_index, 2);
+    assert!(statistics.bytes_read >= counts[2]);
+
+    // Chunks are only recorded when asked for.
+    let mut decoder = Decoder::new(Cursor::new(&file[..])).unwrap();
+    decoder.read_image().unwrap();
+    assert!(decoder.statistics().chunks.is_empty());
+
+    // Positioned reads record the same fields, whichever way the chunks are read.
+    let file = Arc::new(file);
+    let mut decoder = Decoder::new(PositionedReader::new(Arc::clone(&file)))
+        .unwrap()
+        .with_chunk_statistics();
+    decoder.read_chunks(&[3, 1]).unwrap();
+    decoder.read_image_pipelined().unwrap();
+    let chunks = decoder.statistics().chunks;
+    let indices: Vec<u32> = chunks.iter().map(|chunk| chunk.chunk_index).collect();
+    assert_eq!(indices, [3, 1, 0, 1, 2, 3]);
+    for chunk in &chunks {
+        assert_eq!(chunk.compressed_bytes, counts[chunk.chunk_index as usize]);
+    }
+}
+
+#[test]
+fn test_chunk_cache() {
+    let first: Vec<u8> = (0..48 * 48).map(|i| (i * 7 % 251) as u8).collect();
+    let second: Vec<u8> = first.iter().map(|&v| !v).collect();
+    let file = encode(|tiff| {
+        for data in [&first, &second].iter() {
+            let image = tiff
+                .image_builder::<colortype::Gray8>(48, 48)
+                .compression(compression::Deflate::default())
+                .tile_size(32, 32)
+                .build()
+                .unwrap();
+            image.write_data(data).unwrap();
+        }
+    });
+
+    let reads = Rc::new(Cell::new(0));
+    let reader = CountingReader {
+        inner: Cursor::new(file),
+        reads: reads.clone(),
+    };
+    // Room for the first two tiles, of 32x32 and 16x32 pixels.
+    let mut decoder = Decoder::new(reader).unwrap().with_chunk_cache(1536);
+    let tile = |decoder: &mut Decoder<_>, index| match decoder.read_chunk(index).unwrap() {
+        DecodingResult::U8(data) => data,
+        _ => panic!("Wrong data type"),
+    };
+    let expected = |data: &[u8], index: usize| -> Vec<u8> {
+        let (x, width) = if index == 0 { (0, 32) } else { (32, 16) };
+        (0..32)
+            .flat_map(|row| data[row * 48 + x..row * 48 + x + width].to_vec())
+            .collect()
+    };
+
+    assert_eq!(tile(&mut decoder, 0), expected(&first, 0));
+    assert_eq!(tile(&mut decoder, 1), expected(&first, 1));
+    let before = reads.get();
+    assert_eq!(tile(&mut decoder, 0), expected(&first, 0));
+    assert_eq!(tile(&mut decoder, 1), expected(&first, 1));
+    assert_eq!(reads.get(), before);
+
+    // The tiles of another image don't hit the cache, and evict the least recently used.
+    decoder.next_image().unwrap();
+    assert_eq!(tile(&mut decoder, 0), expected(&second, 0));
+    decoder.seek_to_image(0).unwrap();
+    let before = reads.get();
+    assert_eq!(tile(&mut decoder, 1), expected(&first, 1));
+    assert_eq!(reads.get(), before);
+    assert_eq!(tile(&mut decoder, 0), expected(&first, 0));
+    assert!(reads.get() > before);
+}
+
+#[test]
+fn test_chunk_locations() {
+    let image = vec![0u8; 40 * 20];
+    let img_file = encode(|tiff| {
+        tiff.image_builder::<colortype::Gray8>(40, 20)
+            .rows_per_strip(8)
+            .build()
+            .unwrap()
+            .write_data(&image)
+            .unwrap();
+        tiff.image_builder::<colortype::Gray8>(40, 20)
+            .tile_size(16, 16)
+            .build()
+            .unwrap()
+            .write_data(&image)
+            .unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(img_file)).unwrap();
+    assert_eq!(decoder.chunk_origin(0).unwrap(), (0, 0));
+    assert_eq!(decoder.chunk_origin(2).unwrap(), (0, 16));
+    assert!(decoder.chunk_origin(3).is_err());
+    assert_eq!(decoder.chunk_at(39, 7), Some(0));
+    assert_eq!(decoder.chunk_at(0, 19), Some(2));
+    assert_eq!(decoder.chunk_at(0, 20), None);
+
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.chunk_origin(2).unwrap(), (32, 0));
+    assert_eq!(decoder.chunk_origin(4).unwrap(), (16, 16));
+    assert!(matches!(
+        decoder.chunk_origin(6),
+        Err(TiffError::UsageError(UsageError::InvalidChunkIndex(6)))
+    ));
+    assert_eq!(decoder.chunk_at(17, 3), Some(1));
+    assert_eq!(decoder.chunk_at(39, 19), Some(5));
+    assert_eq!(decoder.chunk_at(40, 0), None);
+}
+
+#[test]
+fn test_ifd_value_size_limit() {
+    let values: Vec<u32> = (0..1000).collect();
+    let img_file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(2, 2).unwrap();
+        image
+            .encoder()
+            .write_tag(Tag::Unknown(65000), &values[..])
+            .unwrap();
+        image.write_data(&[1, 2, 3, 4]).unwrap();
+    });
+    let data = img_file;
+
+    let mut decoder = Decoder::new(Cursor::new(&data)).unwrap();
+    assert_eq!(
+        decoder.get_tag_u32_vec(Tag::Unknown(65000)).unwrap(),
+        values
+    );
+
+    let mut limits = Limits::default();
+    limits.ifd_value_size = 1024;
+    let mut decoder = Decoder::new(Cursor::new(&data))
+        .unwrap()
+        .with_limits(limits);
+    assert!(matches!(
+        decoder.get_tag_u32_vec(Tag::Unknown(65000)),
+        Err(TiffError::LimitsExceeded)
+    ));
+}
+
+#[test]
+fn test_huge_ascii_count() {
+    let img_file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(2, 2).unwrap();
+        image
+            .encoder()
+            .write_tag(Tag::ImageDescription, "a description")
+            .unwrap();
+        image.write_data(&[1, 2, 3, 4]).unwrap();
+    });
+    let mut data = img_file;
+
+    // Claim a count of 4 GB in the ImageDescription entry.
+    let entry = data
+        .windows(4)
+        .position(|field| field == [0x0e, 0x01, 2, 0])
+        .unwrap();
+    data[entry + 4..entry + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+
+    let mut decoder = Decoder::new(Cursor::new(&data)).unwrap();
+    assert!(matches!(
+        decoder.get_tag_ascii_string(Tag::ImageDescription),
+        Err(TiffError::LimitsExceeded)
+    ));
+
+    // Without limits, the read stops at the end of the file.
+    let mut decoder = Decoder::new(Cursor::new(&data))
+        .unwrap()
+        .with_limits(Limits::unlimited());
+    assert!(matches!(
+        decoder.get_tag_ascii_string(Tag::ImageDescription),
+        Err(TiffError::IoError(_))
+    ));
+}
+
+#[test]
+fn test_geotiff_model_tags_invalid() {
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        let encoder = image.encoder();
+        encoder
+            .write_tag(Tag::ModelPixelScaleTag, &[1.0, 1.0][..])
+            .unwrap();
+        encoder
+            .write_tag(Tag::ModelTiepointTag, &[0.0; 7][..])
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+
+        let image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.write_data(&[0]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert!(decoder.model_pixel_scale().is_err());
+    assert!(decoder.model_tiepoints().is_err());
+    assert_eq!(decoder.model_transformation().unwrap(), None);
+
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.model_pixel_scale().unwrap(), None);
+    assert_eq!(decoder.model_tiepoints().unwrap(), None);
+    assert_eq!(decoder.geo_keys().unwrap(), None);
+}
+
+#[test]
+fn test_gdal_nodata() {
+    let file = encode(|tiff| {
+        for nodata in ["-9999", " 3.5e38\n", "nan", "-inf", "none"].iter() {
+            let mut image = tiff.new_image::<colortype::Gray32Float>(1, 1).unwrap();
+            image.encoder().write_tag(Tag::GdalNodata, *nodata).unwrap();
+            image.write_data(&[0.0]).unwrap();
+        }
+        let image = tiff.new_image::<colortype::Gray32Float>(1, 1).unwrap();
+        image.write_data(&[0.0]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert_eq!(decoder.gdal_nodata().unwrap(), Some(-9999.0));
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.gdal_nodata().unwrap(), Some(3.5e38));
+    decoder.next_image().unwrap();
+    assert!(decoder.gdal_nodata().unwrap().unwrap().is_nan());
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.gdal_nodata().unwrap(), Some(f64::NEG_INFINITY));
+    decoder.next_image().unwrap();
+    assert!(decoder.gdal_nodata().is_err());
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.gdal_nodata().unwrap(), None);
+}
+
+#[test]
+fn test_iptc() {
+    let mut record = vec![0x1c, 2, 0, 0, 2, 0, 4];
+    record.extend_from_slice(&[0x1c, 2, 25, 0, 5]);
+    record.extend_from_slice(b"alpha");
+    record.resize(20, 0);
+    // Records are usually written as LONG values in the byte order of the file.
+    let longs: Vec<u32> = record
+        .chunks_exact(4)
+        .map(|chunk| u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect();
+
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.encoder().write_tag(Tag::Iptc, &longs[..]).unwrap();
+        image.write_data(&[0]).unwrap();
+
+        let image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.write_data(&[0]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert_eq!(decoder.iptc().unwrap().unwrap(), record);
+    let datasets = decoder.iptc_datasets().unwrap().unwrap();
+    assert_eq!(datasets.len(), 2);
+    assert_eq!((datasets[1].record, datasets[1].dataset), (2, 25));
+    assert_eq!(datasets[1].data, b"alpha");
+
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.iptc().unwrap(), None);
+    assert_eq!(decoder.iptc_datasets().unwrap(), None);
+}
+
+#[test]
+fn test_photoshop_resources() {
+    let mut section = Vec::new();
+    section.extend_from_slice(b"8BIM\x03\xed\x00\x00\x00\x00\x00\x10");
+    section.extend_from_slice(&[0, 72, 0, 0, 0, 1, 0, 1, 0, 72, 0, 0, 0, 1, 0, 1]);
+
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image
+            .encoder()
+            .write_tag(Tag::Photoshop, &section[..])
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+
+        let image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.write_data(&[0]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert_eq!(decoder.photoshop().unwrap().unwrap(), section);
+    let resources = decoder.photoshop_resources().unwrap().unwrap();
+    assert_eq!(resources.len(), 1);
+    assert_eq!(resources[0].id, 1005);
+    assert_eq!(resources[0].data, &section[12..]);
+
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.photoshop().unwrap(), None);
+    assert_eq!(decoder.photoshop_resources().unwrap(), None);
+}
+
+#[test]
+fn test_date_time() {
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.date_time("2021:03:14 15:09:26").unwrap();
+        image
+            .encoder()
+            .write_sub_directory(Tag::ExifDirectory, |exif| {
+                exif.write_tag(Tag::DateTimeOriginal, "2021:03:13 08:00:00")
+            })
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        let encoder = image.encoder();
+        encoder.write_tag(Tag::DateTime, "last tuesday").unwrap();
+        encoder
+            .write_tag(Tag::DateTimeOriginal, "    :  :     :  :  ")
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    let modified = decoder.date_time().unwrap().unwrap();
+    assert_eq!(modified.to_string(), "2021:03:14 15:09:26");
+    let original = decoder.date_time_original().unwrap().unwrap();
+    assert_eq!((original.year, original.month, original.day), (2021, 3, 13));
+    assert!(original < modified);
+
+    decoder.next_image().unwrap();
+    assert!(decoder.date_time().is_err());
+    assert_eq!(decoder.date_time_original().unwrap(), None);
+}
+
+#[test]
+fn test_color_map() {
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image
+            .encoder()
+            .write_tag(Tag::ColorMap, &[0u16, 65535, 1, 2, 3, 4][..])
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image
+            .encoder()
+            .write_tag(Tag::ColorMap, &[0u16, 1][..])
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+
+        let image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.write_data(&[0]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert_eq!(
+        decoder.color_map().unwrap(),
+        Some(vec![[0, 1, 3], [65535, 2, 4]])
+    );
+    decoder.next_image().unwrap();
+    assert!(decoder.color_map().is_err());
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.color_map().unwrap(), None);
+}
+
+#[test]
+fn test_imagej_metadata() {
+    let description = "ImageJ=1.53t\nimages=2\nchannels=2\nhyperstack=true\nmin=0.0\nmax=4095.0\n";
+    // Header in little-endian order with one label per plane and the range of each channel.
+    let mut metadata = b"JIJIlbal\x02\0\0\0gnar\x01\0\0\0".to_vec();
+    metadata.extend_from_slice(b"D\0A\0P\0I\0");
+    metadata.extend_from_slice(b"G\0F\0P\0");
+    for value in [0.0f64, 4095.0, 100.0, 200.0].iter() {
+        metadata.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray16>(1, 1).unwrap();
+        image.image_description(description).unwrap();
+        let encoder = image.encoder();
+        encoder
+            .write_tag(Tag::IJMetadataByteCounts, &[20u32, 8, 6, 32][..])
+            .unwrap();
+        encoder.write_tag(Tag::IJMetadata, &metadata[..]).unwrap();
+        image.write_data(&[0]).unwrap();
+
+        let mut image = tiff.new_image::<colortype::Gray16>(1, 1).unwrap();
+        image.image_description("Not from ImageJ").unwrap();
+        image.write_data(&[0]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    let imagej = decoder.imagej_metadata().unwrap().unwrap();
+    assert_eq!(imagej.version, "1.53t");
+    assert_eq!((imagej.images, imagej.channels), (Some(2), Some(2)));
+    assert_eq!((imagej.slices, imagej.frames), (None, None));
+    assert!(imagej.hyperstack);
+    assert_eq!(imagej.display_range, Some((0.0, 4095.0)));
+    assert_eq!(imagej.labels, ["DAPI", "GFP"]);
+    assert_eq!(imagej.channel_ranges, [(0.0, 4095.0), (100.0, 200.0)]);
+
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.imagej_metadata().unwrap(), None);
+}
+
+#[test]
+fn test_ome_metadata() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<OME xmlns="http://www.openmicroscopy.org/Schemas/OME/2016-06">
+  <Image ID="Image:0"><Pixels ID="Pixels:0" DimensionOrder="XYCZT" Type="uint8" SizeX="1"
+    SizeY="1" SizeC="2" SizeZ="1" SizeT="1"><TiffData/></Pixels></Image>
+</OME>"#;
+
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.image_description(xml).unwrap();
+        image.write_data(&[0]).unwrap();
+
+        let image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.write_data(&[1]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    let ome = decoder.ome_metadata().unwrap().unwrap();
+    assert_eq!(ome.xml, xml);
+    assert_eq!(ome.pixels.len(), 1);
+    let pixels = &ome.pixels[0];
+    assert_eq!((pixels.size_c, pixels.size_z, pixels.size_t), (2, 1, 1));
+    assert_eq!(pixels.dimension_order, "XYCZT");
+
+    let plane = pixels.plane_index(1, 0, 0).unwrap();
+    decoder.seek_to_image(plane as usize).unwrap();
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(data) => assert_eq!(data, [1]),
+        _ => panic!("Wrong data type"),
+    }
+    assert_eq!(decoder.ome_metadata().unwrap(), None);
+}
+
+#[test]
+fn test_subfile_type() {
+    let data = [0u8; 16];
+    let file = encode(|tiff| {
+        tiff.write_image_with_overviews::<colortype::Gray8>(4, 4, &data, &[2])
+            .unwrap();
+
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image
+            .encoder()
+            .write_tag(Tag::NewSubfileType, 2u32 | 4)
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.encoder().write_tag(Tag::SubfileType, 3u16).unwrap();
+        image.write_data(&[0]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    let mut kinds = vec![decoder.subfile_type().unwrap()];
+    while decoder.more_images() {
+        decoder.next_image().unwrap();
+        kinds.push(decoder.subfile_type().unwrap());
+    }
+
+    assert_eq!(kinds.len(), 4);
+    assert!(kinds[0].is_full_resolution());
+    assert!(kinds[1].reduced_resolution && !kinds[1].is_full_resolution());
+    assert!(kinds[2].page && kinds[2].transparency_mask);
+    assert!(!kinds[2].is_full_resolution());
+    assert!(kinds[3].page && kinds[3].is_full_resolution());
+}
+
+#[test]
+fn test_structured_data_errors() {
+    let data: Vec<u8> = (0..32 * 32).map(|i| (i % 251) as u8).collect();
+
+    let mut file = encode(|tiff| {
+        tiff.write_image_with_compression::<colortype::Gray8, _>(32, 32, compression::Lzw, &data)
+            .unwrap();
+    });
+    let (offset, length) = {
+        let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+        let offset = decoder.get_tag_u64(Tag::StripOffsets).unwrap() as usize;
+        let length = decoder.get_tag_u64(Tag::StripByteCounts).unwrap() as usize;
+        (offset, length)
+    };
+
+    // A strip reaching beyond the end of the file.
+    let truncated = encode(|tiff| {
+        let mut directory = tiff.new_directory().unwrap();
+        directory.write_tag(Tag::ImageWidth, 4u32).unwrap();
+        directory.write_tag(Tag::ImageLength, 4u32).unwrap();
+        directory.write_tag(Tag::BitsPerSample, 8u16).unwrap();
+        directory
+            .write_tag(Tag::PhotometricInterpretation, 1u16)
+            .unwrap();
+        directory.write_tag(Tag::RowsPerStrip, 4u32).unwrap();
+        directory.write_tag(Tag::StripOffsets, 8u32).unwrap();
+        directory.write_tag(Tag::StripByteCounts, 1_000u32).unwrap();
+    });
+    let mut decoder = Decoder::new(Cursor::new(truncated)).unwrap();
+    match decoder.read_image() {
+        Err(TiffError::Truncated { rows, .. }) => assert_eq!(rows, 0),
+        other => panic!("Unexpected result {:?}", other.map(|_| ())),
+    }
+
+    // Codes which the LZW decoder has not seen yet.
+    for byte in &mut file[offset..offset + length] {
+        *byte = 0xff;
+    }
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    match decoder.read_image() {
+        Err(TiffError::FormatError(TiffFormatError::CompressedDataCorrupt(_))) => {}
+        other => panic!("Unexpected result {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_ifd_cycle() {
+    let mut file = encode(|tiff| {
+        tiff.write_image::<colortype::Gray8>(2, 2, &[0; 4]).unwrap();
+        tiff.write_image::<colortype::Gray8>(2, 2, &[1; 4]).unwrap();
+    });
+
+    // Point the second directory back at the first one.
+    let read_u32 = |file: &[u8], at: usize| {
+        u32::from_le_bytes([file[at], file[at + 1], file[at + 2], file[at + 3]]) as usize
+    };
+    let next_pointer = |file: &[u8], ifd: usize| {
+        ifd + 2 + 12 * usize::from(u16::from_le_bytes([file[ifd], file[ifd + 1]]))
+    };
+    let first = read_u32(&file, 4);
+    let second = read_u32(&file, next_pointer(&file, first));
+    let at = next_pointer(&file, second);
+    file[at..at + 4].copy_from_slice(&(first as u32).to_le_bytes());
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert!(decoder.more_images());
+    match decoder.next_image() {
+        Err(TiffError::FormatError(TiffFormatError::CycleInOffsets)) => {}
+        other => panic!("Unexpected result {:?}", other),
+    }
+    assert!(!decoder.more_images());
+}
+
+#[test]
+fn test_excess_strips() {
+    // More strips than rows in the image must not index past the decoded image.
+    let file = encode(|tiff| {
+        let mut directory = tiff.new_directory().unwrap();
+        directory.write_tag(Tag::ImageWidth, 2u32).unwrap();
+        directory.write_tag(Tag::ImageLength, 2u32).unwrap();
+        directory.write_tag(Tag::BitsPerSample, 8u16).unwrap();
+        directory
+            .write_tag(Tag::PhotometricInterpretation, 1u16)
+            .unwrap();
+        directory.write_tag(Tag::RowsPerStrip, 1u32).unwrap();
+        directory
+            .write_tag(Tag::StripOffsets, &[0u32, 0, 0, 0][..])
+            .unwrap();
+        directory
+            .write_tag(Tag::StripByteCounts, &[2u32, 2, 2, 2][..])
+            .unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert!(decoder.read_image().is_err());
+}
+
+#[test]
+fn test_missing_strip_byte_counts() {
+    let data: Vec<u8> = (0..3 * 5).collect();
+
+    let mut file = encode(|tiff| {
+        tiff.image_builder::<colortype::Gray8>(3, 5)
+            .rows_per_strip(2)
+            .build()
+            .unwrap()
+            .write_data(&data)
+            .unwrap();
+    });
+    let offsets = Decoder::new(Cursor::new(&file))
+        .unwrap()
+        .get_tag_u32_vec(Tag::StripOffsets)
+        .unwrap();
+    assert_eq!(offsets.len(), 3);
+
+    // A second directory sharing the strips, but without their byte counts.
+    append(&mut file, |tiff| {
+        let mut directory = tiff.new_directory().unwrap();
+        directory.write_tag(Tag::ImageWidth, 3u32).unwrap();
+        directory.write_tag(Tag::ImageLength, 5u32).unwrap();
+        directory.write_tag(Tag::BitsPerSample, 8u16).unwrap();
+        directory
+            .write_tag(Tag::PhotometricInterpretation, 1u16)
+            .unwrap();
+        directory.write_tag(Tag::RowsPerStrip, 2u32).unwrap();
+        directory
+            .write_tag(Tag::StripOffsets, &offsets[..])
+            .unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    decoder.next_image().unwrap();
+    assert!(decoder.find_tag(Tag::StripByteCounts).unwrap().is_none());
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(decoded) => assert_eq!(decoded, data),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+#[test]
+fn test_rows_per_strip_default() {
+    let data: Vec<u8> = (0..3 * 5).collect();
+
+    let mut file = encode(|tiff| {
+        tiff.image_builder::<colortype::Gray8>(3, 5)
+            .rows_per_strip(5)
+            .build()
+            .unwrap()
+            .write_data(&data)
+            .unwrap();
+    });
+    let offsets = Decoder::new(Cursor::new(&file))
+        .unwrap()
+        .get_tag_u32_vec(Tag::StripOffsets)
+        .unwrap();
+
+    // Directories sharing the single strip, with the spec default and without RowsPerStrip.
+    for rows_per_strip in &[Some(u32::MAX), None] {
+        append(&mut file, |tiff| {
+            let mut directory = tiff.new_directory().unwrap();
+            directory.write_tag(Tag::ImageWidth, 3u32).unwrap();
+            directory.write_tag(Tag::ImageLength, 5u32).unwrap();
+            directory.write_tag(Tag::BitsPerSample, 8u16).unwrap();
+            directory
+                .write_tag(Tag::PhotometricInterpretation, 1u16)
+                .unwrap();
+            if let Some(rows_per_strip) = *rows_per_strip {
+                directory
+                    .write_tag(Tag::RowsPerStrip, rows_per_strip)
+                    .unwrap();
+            }
+            directory
+                .write_tag(Tag::StripOffsets, &offsets[..])
+                .unwrap();
+            directory.write_tag(Tag::StripByteCounts, 15u32).unwrap();
+        });
+    }
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    for _ in 0..2 {
+        decoder.next_image().unwrap();
+        assert_eq!(decoder.strip_count().unwrap(), 1);
+        assert_eq!(decoder.chunk_dimensions(), (3, 5));
+        match decoder.read_image().unwrap() {
+            DecodingResult::U8(decoded) => assert_eq!(decoded, data),
+            _ => panic!("Wrong data type"),
+        }
+    }
+}
+
+#[test]
+fn test_sparse_strips() {
+    let data: Vec<u8> = (1..=8).collect();
+
+    let write = |nodata: Option<&str>| {
+        encode(|tiff| {
+            let mut directory = tiff.new_directory().unwrap();
+            let offset = directory.write_data(&data[..]).unwrap() as u32;
+            directory.write_tag(Tag::ImageWidth, 4u32).unwrap();
+            directory.write_tag(Tag::ImageLength, 4u32).unwrap();
+            directory.write_tag(Tag::BitsPerSample, 8u16).unwrap();
+            directory
+                .write_tag(Tag::PhotometricInterpretation, 1u16)
+                .unwrap();
+            directory.write_tag(Tag::RowsPerStrip, 2u32).unwrap();
+            // The second strip is left out of the file.
+            directory
+                .write_tag(Tag::StripOffsets, &[offset, 0][..])
+                .unwrap();
+            directory
+                .write_tag(Tag::StripByteCounts, &[8u32, 0][..])
+                .unwrap();
+            if let Some(nodata) = nodata {
+                directory.write_tag(Tag::GdalNodata, nodata).unwrap();
+            }
+        })
+    };
+
+    let mut decoder = Decoder::new(Cursor::new(write(None))).unwrap();
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(decoded) => {
+            assert_eq!(&decoded[..8], &data[..]);
+            assert_eq!(&decoded[8..], &[0; 8]);
+        }
+        _ => panic!("Wrong data type"),
+    }
+
+    // Sparse chunks take the nodata value of the image.
+    let mut decoder = Decoder::new(Cursor::new(write(Some("7")))).unwrap();
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(decoded) => assert_eq!(&decoded[8..], &[7; 8]),
+        _ => panic!("Wrong data type"),
+    }
+    match decoder.read_chunk(1).unwrap() {
+        DecodingResult::U8(decoded) => assert_eq!(decoded, [7; 8]),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+#[test]
+fn test_odd_strip_byte_counts() {
+    let samples: Vec<u16> = (0..6).map(|i| 0x0101 * i).collect();
+    let mut data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    data.push(0);
+
+    let write = |byte_counts: [u32; 2]| {
+        encode(|tiff| {
+            let mut directory = tiff.new_directory().unwrap();
+            let offset = directory.write_data(&data[..]).unwrap() as u32;
+            directory.write_tag(Tag::ImageWidth, 3u32).unwrap();
+            directory.write_tag(Tag::ImageLength, 2u32).unwrap();
+            directory.write_tag(Tag::BitsPerSample, 16u16).unwrap();
+            directory
+                .write_tag(Tag::PhotometricInterpretation, 1u16)
+                .unwrap();
+            directory.write_tag(Tag::RowsPerStrip, 1u32).unwrap();
+            directory
+                .write_tag(Tag::StripOffsets, &[offset, offset + 6][..])
+                .unwrap();
+            directory
+                .write_tag(Tag::StripByteCounts, &byte_counts[..])
+                .unwrap();
+        })
+    };
+
+    // A padding byte after the samples is ignored.
+    let mut decoder = Decoder::new(Cursor::new(write([6, 7]))).unwrap();
+    match decoder.read_image().unwrap() {
+        DecodingResult::U16(decoded) => assert_eq!(decoded, samples),
+        _ => panic!("Wrong data type"),
+    }
+
+    // A strip lacking the last byte of a sample is reported instead of reading past it.
+    let mut decoder = Decoder::new(Cursor::new(write([6, 5]))).unwrap();
+    match decoder.read_image() {
+        Err(TiffError::FormatError(TiffFormatError::UnexpectedCompressedData {
+            actual_bytes,
+            required_bytes,
+        })) => assert_eq!((actual_bytes, required_bytes), (5, 6)),
+        other => panic!("Unexpected result {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_chunk_ranges_checked_before_decoding() {
+    let data: Vec<u8> = (0..4 * 4).collect();
+
+    let mut file = encode(|tiff| {
+        tiff.image_builder::<colortype::Gray8>(4, 4)
+            .rows_per_strip(2)
+            .build()
+            .unwrap()
+            .write_data(&data)
+            .unwrap();
+    });
+    let offsets = Decoder::new(Cursor::new(&file))
+        .unwrap()
+        .get_tag_u32_vec(Tag::StripOffsets)
+        .unwrap();
+
+    // The second strip claims far more data than the file holds.
+    append(&mut file, |tiff| {
+        let mut directory = tiff.new_directory().unwrap();
+        directory.write_tag(Tag::ImageWidth, 4u32).unwrap();
+        directory.write_tag(Tag::ImageLength, 4u32).unwrap();
+        directory.write_tag(Tag::BitsPerSample, 8u16).unwrap();
+        directory
+            .write_tag(Tag::PhotometricInterpretation, 1u16)
+            .unwrap();
+        directory.write_tag(Tag::RowsPerStrip, 2u32).unwrap();
+        directory
+            .write_tag(Tag::StripOffsets, &offsets[..])
+            .unwrap();
+        directory
+            .write_tag(Tag::StripByteCounts, &[8u32, 1_000_000][..])
+            .unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    decoder.next_image().unwrap();
+    // The rows before it are decoded and the rest are left zero.
+    match decoder.read_image() {
+        Err(TiffError::Truncated {
+            rows,
+            partial: DecodingResult::U8(partial),
+        }) => {
+            assert_eq!(rows, 2);
+            assert_eq!(&partial[..8], &data[..8]);
+            assert!(partial[8..].iter().all(|&sample| sample == 0));
+        }
+        other => panic!("Unexpected result {:?}", other.map(|_| ())),
+    }
+
+    // Strips within the file can still be read individually.
+    match decoder.read_chunk(0).unwrap() {
+        DecodingResult::U8(decoded) => assert_eq!(decoded, &data[..8]),
+        _ => panic!("Wrong data type"),
+    }
+    assert!(decoder.read_chunk(1).is_err());
+}
+
+#[test]
+fn test_decoding_mode() {
+    let data: Vec<u8> = (0..2 * 2 * 3).collect();
+
+    let mut file = encode(|tiff| {
+        tiff.write_image::<colortype::RGB8>(2, 2, &data).unwrap();
+    });
+    let offsets = Decoder::new(Cursor::new(&file))
+        .unwrap()
+        .get_tag_u32_vec(Tag::StripOffsets)
+        .unwrap();
+
+    // The same pixels with a BYTE width, a single BitsPerSample value and no ResolutionUnit.
+    append(&mut file, |tiff| {
+        let mut directory = tiff.new_directory().unwrap();
+        directory.write_tag(Tag::ImageWidth, 2u8).unwrap();
+        directory.write_tag(Tag::ImageLength, 2u32).unwrap();
+        directory.write_tag(Tag::BitsPerSample, 8u16).unwrap();
+        directory
+            .write_tag(Tag::PhotometricInterpretation, 2u16)
+            .unwrap();
+        directory.write_tag(Tag::SamplesPerPixel, 3u16).unwrap();
+        directory
+            .write_tag(Tag::StripOffsets, &offsets[..])
+            .unwrap();
+        directory.write_tag(Tag::StripByteCounts, 12u32).unwrap();
+        directory
+            .write_tag(Tag::XResolution, Rational { n: 72, d: 1 })
+            .unwrap();
+        directory
+            .write_tag(Tag::YResolution, Rational { n: 72, d: 1 })
+            .unwrap();
+    });
+
+    for &mode in &[DecodingMode::Lenient, DecodingMode::Strict] {
+        let mut decoder = Decoder::new(Cursor::new(&file)).unwrap().with_mode(mode);
+        match decoder.read_image().unwrap() {
+            DecodingResult::U8(decoded) => assert_eq!(decoded, data),
+            _ => panic!("Wrong data type"),
+        }
+        assert!(decoder.resolution().unwrap().is_some());
+    }
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.colortype().unwrap(), ColorType::RGB(8));
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(decoded) => assert_eq!(decoded, data),
+        _ => panic!("Wrong data type"),
+    }
+    let (_, _, unit) = decoder.resolution().unwrap().unwrap();
+    assert_eq!(unit, ResolutionUnit::Inch);
+
+    let mut decoder = Decoder::new(Cursor::new(&file))
+        .unwrap()
+        .with_mode(DecodingMode::Strict);
+    decoder.next_image().unwrap();
+    match decoder.read_image() {
+        Err(TiffError::FormatError(TiffFormatError::InvalidTagValueType(Tag::ImageWidth))) => {}
+        other => panic!("Unexpected result {:?}", other.map(|_| ())),
+    }
+    assert!(decoder.read_chunk(0).is_err());
+    assert!(decoder.resolution().is_err());
+}
+
+#[test]
+fn test_chunk_recovery() {
+    let data: Vec<u8> = (0..8 * 8).map(|i| (i * 3) as u8).collect();
+
+    let mut file = encode(|tiff| {
+        tiff.image_builder::<colortype::Gray8>(8, 8)
+            .compression(compression::Lzw)
+            .rows_per_strip(4)
+            .build()
+            .unwrap()
+            .write_data(&data)
+            .unwrap();
+    });
+    let (offsets, counts) = {
+        let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+        (
+            decoder.get_tag_u64_vec(Tag::StripOffsets).unwrap(),
+            decoder.get_tag_u64_vec(Tag::StripByteCounts).unwrap(),
+        )
+    };
+
+    // Replace the second strip with codes the LZW decoder has not seen yet.
+    let (offset, count) = (offsets[1] as usize, counts[1] as usize);
+    for byte in &mut file[offset..offset + count] {
+        *byte = 0xff;
+    }
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert!(decoder.read_image().is_err());
+
+    let mut decoder = Decoder::new(Cursor::new(&file))
+        .unwrap()
+        .with_chunk_recovery(true);
+    assert!(decoder.warnings().is_empty());
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(decoded) => {
+            assert_eq!(decoded[..32], data[..32]);
+            assert!(decoded[32..].iter().all(|&b| b == 0));
+        }
+        _ => panic!("Wrong data type"),
+    }
+    match decoder.warnings() {
+        [DecodingWarning::CorruptChunk {
+            chunk: 1,
+            error: TiffError::FormatError(TiffFormatError::CompressedDataCorrupt(_)),
+        }] => {}
+        other => panic!("Unexpected warnings {:?}", other),
+    }
+}
+
+#[test]
+fn test_raw_samples() {
+    let data: Vec<u8> = (0..4 * 4 * 3).map(|i| (i * 5) as u8).collect();
+
+    let mut file = encode(|tiff| {
+        tiff.write_image::<colortype::RGB8>(4, 4, &data).unwrap();
+    });
+
+    // Change the photometric interpretation to a value from outside of the specification.
+    let entry = [0x06, 0x01, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00];
+    let position = file
+        .windows(entry.len())
+        .position(|window| window == entry)
+        .unwrap();
+    file[position + 8..position + 10].copy_from_slice(&0x8001u16.to_le_bytes());
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert!(matches!(
+        decoder.colortype(),
+        Err(TiffError::UnsupportedError(
+            TiffUnsupportedError::UnknownInterpretation
+        ))
+    ));
+    assert!(decoder.read_image().is_err());
+
+    let mut decoder = Decoder::new(Cursor::new(&file))
+        .unwrap()
+        .with_raw_samples(true);
+    assert_eq!(
+        decoder.colortype().unwrap(),
+        ColorType::Multiband {
+            bit_depth: 8,
+            num_samples: 3
+        }
+    );
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(decoded) => assert_eq!(decoded, data),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+#[test]
+fn test_integer_tag_types() {
+    // Core tags written with other integer types than the specification prescribes.
+    let data: Vec<u8> = (0..3 * 2 * 3).collect();
+
+    let file = encode(|tiff| {
+        let mut directory = tiff.new_directory().unwrap();
+        let offset = directory.write_data(&data[..]).unwrap();
+        directory.write_tag(Tag::ImageWidth, 3i16).unwrap();
+        directory.write_tag(Tag::ImageLength, 2u8).unwrap();
+        directory
+            .write_tag(Tag::BitsPerSample, &[8i16, 8, 8][..])
+            .unwrap();
+        directory.write_tag(Tag::SamplesPerPixel, 3u32).unwrap();
+        directory
+            .write_tag(Tag::PhotometricInterpretation, 2u32)
+            .unwrap();
+        directory.write_tag(Tag::RowsPerStrip, 2i32).unwrap();
+        directory
+            .write_tag(Tag::StripOffsets, offset as u16)
+            .unwrap();
+        directory
+            .write_tag(Tag::StripByteCounts, data.len() as u8)
+            .unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert_eq!(decoder.dimensions().unwrap(), (3, 2));
+    assert_eq!(decoder.colortype().unwrap(), ColorType::RGB(8));
+    assert_eq!(
+        decoder.get_tag_u16_vec(Tag::BitsPerSample).unwrap(),
+        [8, 8, 8]
+    );
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(decoded) => assert_eq!(decoded, data),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+#[test]
+fn test_tile_size_not_multiple_of_16() {
+    let data: Vec<u8> = (0..10 * 6).map(|i| (i * 7) as u8).collect();
+    // The image as 8x4 tiles, padded to two tiles across and down.
+    let mut tiles = Vec::new();
+    for (tile_x, tile_y) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+        for y in tile_y * 4..tile_y * 4 + 4 {
+            for x in tile_x * 8..tile_x * 8 + 8 {
+                tiles.push(if x < 10 && y < 6 { data[y * 10 + x] } else { 0 });
+            }
+        }
+    }
+
+    let file = encode(|tiff| {
+        let mut directory = tiff.new_directory().unwrap();
+        let offset = directory.write_data(&tiles[..]).unwrap() as u32;
+        directory.write_tag(Tag::ImageWidth, 10u32).unwrap();
+        directory.write_tag(Tag::ImageLength, 6u32).unwrap();
+        directory.write_tag(Tag::BitsPerSample, 8u16).unwrap();
+        directory
+            .write_tag(Tag::PhotometricInterpretation, 1u16)
+            .unwrap();
+        directory.write_tag(Tag::TileWidth, 8u32).unwrap();
+        directory.write_tag(Tag::TileLength, 4u32).unwrap();
+        directory
+            .write_tag(
+                Tag::TileOffsets,
+                &[offset, offset + 32, offset + 64, offset + 96][..],
+            )
+            .unwrap();
+        directory
+            .write_tag(Tag::TileByteCounts, &[32u32, 32, 32, 32][..])
+            .unwrap();
+    });
+
+    for &mode in &[DecodingMode::Lenient, DecodingMode::Strict] {
+        let mut decoder = Decoder::new(Cursor::new(&file)).unwrap().with_mode(mode);
+        match decoder.read_image().unwrap() {
+            DecodingResult::U8(decoded) => assert_eq!(decoded, data),
+            _ => panic!("Wrong data type"),
+        }
+        match (mode, decoder.warnings()) {
+            (DecodingMode::Lenient, []) => {}
+            (
+                DecodingMode::Strict,
+                [DecodingWarning::TileSize {
+                    width: 8,
+                    length: 4,
+                }],
+            ) => {}
+            (_, other) => panic!("Unexpected warnings {:?}", other),
+        }
+    }
+
+    // Chunks taken from the cache are checked the same way.
+    let mut decoder = Decoder::new(Cursor::new(&file))
+        .unwrap()
+        .with_mode(DecodingMode::Strict)
+        .with_chunk_cache(1024);
+    decoder.read_chunk(0).unwrap();
+    decoder.seek_to_image(0).unwrap();
+    assert!(decoder.warnings().is_empty());
+    decoder.read_chunk(0).unwrap();
+    assert!(matches!(
+        decoder.warnings(),
+        [DecodingWarning::TileSize {
+            width: 8,
+            length: 4
+        }]
+    ));
+}
+
+#[test]
+fn test_unsorted_and_duplicate_entries() {
+    let data: Vec<u8> = (0..4 * 3).collect();
+
+    let mut file = encode(|tiff| {
+        tiff.write_image::<colortype::Gray8>(4, 3, &data).unwrap();
+    });
+
+    // Rewrite the directory with its entries in reverse order, preceded by a second ImageWidth.
+    let ifd = u32::from_le_bytes([file[4], file[5], file[6], file[7]]) as usize;
+    let count = u16::from_le_bytes([file[ifd], file[ifd + 1]]) as usize;
+    let mut entries: Vec<&[u8]> = file[ifd + 2..ifd + 2 + 12 * count].chunks(12).collect();
+    entries.reverse();
+    let mut rewritten = ((count + 1) as u16).to_le_bytes().to_vec();
+    rewritten.extend_from_slice(&[0x00, 0x01, 0x04, 0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x00]);
+    rewritten.extend_from_slice(&[0x00, 0x00]);
+    for entry in entries {
+        rewritten.extend_from_slice(entry);
+    }
+    rewritten.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+    let new_ifd = file.len() as u32;
+    file.extend_from_slice(&rewritten);
+    file[4..8].copy_from_slice(&new_ifd.to_le_bytes());
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert_eq!(decoder.dimensions().unwrap(), (4, 3));
+    assert_eq!(decoder.get_tag_u32(Tag::ImageWidth).unwrap(), 4);
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(decoded) => assert_eq!(decoded, data),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+#[test]
+fn test_white_is_zero_inversion() {
+    // Writes a 3x2 image with a single strip or tile from the stored bytes of its samples.
+    let write = |bits: u16, samples: u16, format: u16, predictor: u16, tile: bool, data: &[u8]| {
+        encode(|tiff| {
+            let mut directory = tiff.new_directory().unwrap();
+            let offset = directory.write_data(data).unwrap() as u32;
+            directory.write_tag(Tag::ImageWidth, 3u32).unwrap();
+            directory.write_tag(Tag::ImageLength, 2u32).unwrap();
+            directory
+                .write_tag(Tag::BitsPerSample, &vec![bits; usize::from(samples)][..])
+                .unwrap();
+            directory.write_tag(Tag::SamplesPerPixel, samples).unwrap();
+            directory.write_tag(Tag::SampleFormat, format).unwrap();
+            directory
+                .write_tag(Tag::PhotometricInterpretation, 0u16)
+                .unwrap();
+            directory.write_tag(Tag::Predictor, predictor).unwrap();
+            if tile {
+                directory.write_tag(Tag::TileWidth, 16u32).unwrap();
+                directory.write_tag(Tag::TileLength, 16u32).unwrap();
+                directory.write_tag(Tag::TileOffsets, offset).unwrap();
+                directory
+                    .write_tag(Tag::TileByteCounts, data.len() as u32)
+                    .unwrap();
+            } else {
+                directory.write_tag(Tag::StripOffsets, offset).unwrap();
+                directory
+                    .write_tag(Tag::StripByteCounts, data.len() as u32)
+                    .unwrap();
+            }
+        })
+    };
+    let decode = |file: Vec<u8>| {
+        Decoder::new(Cursor::new(file))
+            .unwrap()
+            .read_image()
+            .unwrap()
+    };
+
+    // Horizontal differencing of rows of 3 pixels, and padding rows to the width of a tile.
+    let predict = |data: &[u16], samples: usize| -> Vec<u16> {
+        let mut predicted = data.to_vec();
+        for row in predicted.chunks_mut(3 * samples) {
+            for i in (samples..row.len()).rev() {
+                row[i] = row[i].wrapping_sub(row[i - samples]);
+            }
+        }
+        predicted
+    };
+    let pad = |data: &[u8], row_bytes: usize| -> Vec<u8> {
+        let mut padded = vec![0; 16 * 16 * row_bytes / 3];
+        for (row, padded) in data
+            .chunks(row_bytes)
+            .zip(padded.chunks_mut(16 * row_bytes / 3))
+        {
+            padded[..row_bytes].copy_from_slice(row);
+        }
+        padded
+    };
+
+    // Samples of less than a byte are inverted in their packed rows.
+    let packed = [0x12u8, 0x30, 0x45, 0x60];
+    match decode(write(4, 1, 1, 1, false, &packed)) {
+        DecodingResult::U8(decoded) => assert_eq!(decoded, [0xed, 0xcf, 0xba, 0x9f]),
+        _ => panic!("Wrong data type"),
+    }
+
+    let gray: Vec<u16> = vec![10, 20, 30, 40, 50, 60];
+    let gray_alpha: Vec<u16> = vec![10, 255, 20, 128, 30, 0, 40, 1, 50, 2, 60, 3];
+    for &(predictor, tile) in [(1u16, false), (2, false), (1, true), (2, true)].iter() {
+        let stored = |data: &[u16], samples: usize, to_bytes: &dyn Fn(u16) -> Vec<u8>| {
+            let data = if predictor == 2 {
+                predict(data, samples)
+            } else {
+                data.to_vec()
+            };
+            let bytes: Vec<u8> = data.into_iter().flat_map(to_bytes).collect();
+            if tile {
+                pad(&bytes, bytes.len() / 2)
+            } else {
+                bytes
+            }
+        };
+        let byte = |sample: u16| vec![sample as u8];
+        let word = |sample: u16| (sample * 1000).to_le_bytes().to_vec();
+
+        match decode(write(8, 1, 1, predictor, tile, &stored(&gray, 1, &byte))) {
+            DecodingResult::U8(decoded) => {
+                assert_eq!(decoded, [245, 235, 225, 215, 205, 195])
+            }
+            _ => panic!("Wrong data type"),
+        }
+
+        let words: Vec<u16> = gray.iter().map(|&sample| sample * 1000).collect();
+        match decode(write(16, 1, 1, predictor, tile, &stored(&gray, 1, &word))) {
+            DecodingResult::U16(decoded) => {
+                let expected: Vec<u16> = words.iter().map(|&sample| !sample).collect();
+                assert_eq!(decoded, expected)
+            }
+            _ => panic!("Wrong data type"),
+        }
+
+        // The alpha samples are left as they are.
+        match decode(write(
+            8,
+            2,
+            1,
+            predictor,
+            tile,
+            &stored(&gray_alpha, 2, &byte),
+        )) {
+            DecodingResult::U8(decoded) => assert_eq!(
+                decoded,
+                [245, 255, 235, 128, 225, 0, 215, 1, 205, 2, 195, 3]
+            ),
+            _ => panic!("Wrong data type"),
+        }
+
+        // Signed samples map the lowest value to the highest.
+        let signed: Vec<u16> = vec![0x80, 0xce, 0, 50, 100, 0x7f];
+        match decode(write(8, 1, 2, predictor, tile, &stored(&signed, 1, &byte))) {
+            DecodingResult::I8(decoded) => assert_eq!(decoded, [127, 49, -1, -51, -101, -128]),
+            _ => panic!("Wrong data type"),
+        }
+    }
+}
+
+#[test]
+fn test_predictor_validation() {
+    let data: Vec<u8> = (0..4 * 2).collect();
+
+    let write = |photometric: u16, bits: u16, predictor: u16| {
+        encode(|tiff| {
+            let mut directory = tiff.new_directory().unwrap();
+            let offset = directory.write_data(&data[..]).unwrap() as u32;
+            directory
+                .write_tag(Tag::ImageWidth, 8 / bits as u32 * 4)
+                .unwrap();
+            directory.write_tag(Tag::ImageLength, 2u32).unwrap();
+            directory.write_tag(Tag::BitsPerSample, bits).unwrap();
+            directory
+                .write_tag(Tag::PhotometricInterpretation, photometric)
+                .unwrap();
+            directory.write_tag(Tag::Predictor, predictor).unwrap();
+            directory.write_tag(Tag::StripOffsets, offset).unwrap();
+            directory.write_tag(Tag::StripByteCounts, 8u32).unwrap();
+            if photometric == 3 {
+                let map = vec![0u16; 3 << bits];
+                directory.write_tag(Tag::ColorMap, &map[..]).unwrap();
+            }
+        })
+    };
+
+    // A palette image with the horizontal predictor, also when decoded as raw samples.
+    let file = write(3, 8, 2);
+    let mut decoder = Decoder::new(Cursor::new(&file))
+        .unwrap()
+        .with_raw_samples(true);
+    assert!(matches!(
+        decoder.read_image(),
+        Err(TiffError::FormatError(
+            TiffFormatError::PaletteWithPredictor(Predictor::Horizontal)
+        ))
+    ));
+
+    // Sub-byte samples are rejected before any data is read.
+    let file = write(1, 4, 2);
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert!(matches!(
+        decoder.read_image(),
+        Err(TiffError::UnsupportedError(
+            TiffUnsupportedError::HorizontalPredictor(ColorType::Gray(4))
+        ))
+    ));
+
+    // The floating point predictor needs floating point samples.
+    let file = write(1, 8, 3);
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert!(matches!(
+        decoder.read_image(),
+        Err(TiffError::UnsupportedError(
+            TiffUnsupportedError::FloatingPointPredictor(ColorType::Gray(8))
+        ))
+    ));
+
+    let file = write(1, 8, 2);
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert!(decoder.read_image().is_ok());
+}
+
+#[test]
+fn test_samples_per_pixel_validation() {
+    let write = |photometric: u16, samples: u16, extra_samples: &[u16]| {
+        let data = vec![0u8; usize::from(samples) * 4];
+        encode(|tiff| {
+            let mut directory = tiff.new_directory().unwrap();
+            let offset = directory.write_data(&data[..]).unwrap() as u32;
+            directory.write_tag(Tag::ImageWidth, 2u32).unwrap();
+            directory.write_tag(Tag::ImageLength, 2u32).unwrap();
+            directory.write_tag(Tag::BitsPerSample, 8u16).unwrap();
+            directory.write_tag(Tag::SamplesPerPixel, samples).unwrap();
+            directory
+                .write_tag(Tag::PhotometricInterpretation, photometric)
+                .unwrap();
+            if !extra_samples.is_empty() {
+                directory
+                    .write_tag(Tag::ExtraSamples, extra_samples)
+                    .unwrap();
+            }
+            directory.write_tag(Tag::StripOffsets, offset).unwrap();
+            directory
+                .write_tag(Tag::StripByteCounts, data.len() as u32)
+                .unwrap();
+        })
+    };
+
+    let mismatch = |file: Vec<u8>| match Decoder::new(Cursor::new(file)) {
+        Err(TiffError::FormatError(TiffFormatError::SamplesPerPixelMismatch {
+            interpretation,
+            samples,
+        })) => Some((interpretation, samples)),
+        _ => None,
+    };
+
+    assert_eq!(
+        mismatch(write(2, 1, &[])),
+        Some((PhotometricInterpretation::RGB, 1))
+    );
+    assert_eq!(
+        mismatch(write(1, 3, &[])),
+        Some((PhotometricInterpretation::BlackIsZero, 3))
+    );
+    assert_eq!(
+        mismatch(write(3, 2, &[])),
+        Some((PhotometricInterpretation::RGBPalette, 2))
+    );
+
+    // Declared extra samples and an undeclared alpha channel are accepted.
+    assert!(Decoder::new(Cursor::new(write(1, 3, &[0, 0]))).is_ok());
+    assert!(Decoder::new(Cursor::new(write(1, 2, &[]))).is_ok());
+    assert!(Decoder::new(Cursor::new(write(2, 4, &[]))).is_ok());
+}
+
+#[test]
+fn test_too_many_bits_per_sample() {
+    let data = [0u8; 2 * 2 * 3];
+    let file = encode(|tiff| {
+        let mut directory = tiff.new_directory().unwrap();
+        let offset = directory.write_data(&data[..]).unwrap() as u32;
+        directory.write_tag(Tag::ImageWidth, 2u32).unwrap();
+        directory.write_tag(Tag::ImageLength, 2u32).unwrap();
+        directory
+            .write_tag(Tag::BitsPerSample, &[8u16, 8, 8, 8, 8][..])
+            .unwrap();
+        directory.write_tag(Tag::SamplesPerPixel, 3u16).unwrap();
+        directory
+            .write_tag(Tag::PhotometricInterpretation, 2u16)
+            .unwrap();
+        directory.write_tag(Tag::StripOffsets, offset).unwrap();
+        directory
+            .write_tag(Tag::StripByteCounts, data.len() as u32)
+            .unwrap();
+    });
+
+    assert!(matches!(
+        Decoder::new(Cursor::new(file)),
+        Err(TiffError::FormatError(
+            TiffFormatError::InconsistentSizesEncountered
+        ))
+    ));
+}
+
+#[test]
+fn test_decode_progress() {
+    let data: Vec<u8> = (0..60 * 60).map(|i| i as u8).collect();
+    let file = encode(|tiff| {
+        let image = tiff
+            .image_builder::<colortype::Gray8>(60, 60)
+            .compression(compression::Lzw)
+            .tile_size(32, 32)
+            .build()
+            .unwrap();
+        image.write_data(&data).unwrap();
+    });
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let callback_reports = Arc::clone(&reports);
+    let mut decoder = Decoder::new(Cursor::new(file))
+        .unwrap()
+        .with_progress(move |progress| {
+            callback_reports.lock().unwrap().push(*progress);
+            true
+        });
+    let counts = decoder.get_tag_u64_vec(Tag::TileByteCounts).unwrap();
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(decoded) => assert_eq!(decoded, data),
+        _ => panic!("Wrong data type"),
+    }
+
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 4);
+    for (index, progress) in reports.iter().enumerate() {
+        assert_eq!(
+            *progress,
+            tiff::decoder::Progress {
+                chunks_read: index as u64 + 1,
+                total_chunks: 4,
+                bytes_read: counts[..=index].iter().sum(),
+            }
+        );
+    }
+}
+
+#[test]
+fn test_decode_progress_cancel() {
+    let data: Vec<u16> = (0..100 * 100).map(|i| i as u16).collect();
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray16>(100, 100).unwrap();
+        image.rows_per_strip(10).unwrap();
+        image.write_data(&data).unwrap();
+    });
+
+    let calls = Arc::new(Mutex::new(0));
+    let callback_calls = Arc::clone(&calls);
+    let file = Cursor::new(file);
+    let mut decoder = Decoder::new(file).unwrap().with_progress(move |progress| {
+        *callback_calls.lock().unwrap() += 1;
+        progress.chunks_read < 3
+    });
+    let err = decoder.read_image().unwrap_err();
+    assert!(err.to_string().contains("cancelled"));
+    assert_eq!(*calls.lock().unwrap(), 3);
+
+    // The decoder can still be used after cancelling.
+    let mut decoder = decoder.with_progress(|_| true);
+    match decoder.read_image().unwrap() {
+        DecodingResult::U16(decoded) => assert_eq!(decoded, data),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+#[test]
+fn test_typed_tag_access() {
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        let encoder = image.encoder();
+        encoder.write_tag(Tag::DocumentName, "scan.tif").unwrap();
+        encoder.write_tag(Tag::PageNumber, &[2u16, 5][..]).unwrap();
+        encoder
+            .write_tag(Tag::XPosition, Rational { n: 3, d: 2 })
+            .unwrap();
+        encoder
+            .write_tag(
+                Tag::Unknown(65000),
+                &[Rational { n: 48, d: 1 }, Rational { n: 7, d: 60 }][..],
+            )
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert_eq!(
+        decoder.get_tag_ascii_string(Tag::DocumentName).unwrap(),
+        "scan.tif"
+    );
+    assert_eq!(decoder.get_tag_u16_vec(Tag::PageNumber).unwrap(), [2, 5]);
+    assert_eq!(decoder.get_tag_u16(Tag::ImageWidth).unwrap(), 1);
+    assert_eq!(decoder.get_tag_rational(Tag::XPosition).unwrap(), (3, 2));
+    assert_eq!(
+        decoder.get_tag_rational_vec(Tag::Unknown(65000)).unwrap(),
+        [(48, 1), (7, 60)]
+    );
+    assert_eq!(
+        decoder.get_tag_rational_vec(Tag::XPosition).unwrap(),
+        [(3, 2)]
+    );
+    assert!(decoder.get_tag_rational(Tag::DocumentName).is_err());
+    assert!(decoder.find_tag(Tag::PageName).unwrap().is_none());
+}
+
+/// ASCII field with arbitrary content, which `str` refuses to write.
+struct RawAscii<'a>(&'a [u8]);
+
+impl<'a> TiffValue for RawAscii<'a> {
+    const BYTE_LEN: u8 = 1;
+    const FIELD_TYPE: Type = Type::ASCII;
+
+    fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    fn data(&self) -> std::borrow::Cow<'_, [u8]> {
+        std::borrow::Cow::Borrowed(self.0)
+    }
+}
+
+#[test]
+fn test_ascii_strings() {
+    let fields: [(u16, &[u8]); 6] = [
+        (65000, b"a\0b\0"),
+        (65001, b"first\0second\0\0\0"),
+        (65002, b"abc\0\xff\xfe"),
+        (65003, b"xy"),
+        (65004, b"A"),
+        (65005, b"caf\xc3\xa9 au lait\0"),
+    ];
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        for (tag, bytes) in &fields {
+            image
+                .encoder()
+                .write_tag(Tag::Unknown(*tag), RawAscii(bytes))
+                .unwrap();
+        }
+        image.write_data(&[0]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    let mut strings = |tag| decoder.get_tag_ascii_string_vec(Tag::Unknown(tag)).unwrap();
+    assert_eq!(strings(65000), ["a", "b"]);
+    assert_eq!(strings(65001), ["first", "second"]);
+    assert_eq!(strings(65002), ["abc"]);
+    assert_eq!(strings(65003), ["xy"]);
+    assert_eq!(strings(65004), ["A"]);
+    assert_eq!(strings(65005), ["café au lait"]);
+
+    assert_eq!(
+        decoder.get_tag_ascii_string(Tag::Unknown(65001)).unwrap(),
+        "first"
+    );
+    assert_eq!(
+        decoder.get_tag(Tag::Unknown(65002)).unwrap(),
+        ifd::Value::Ascii("abc".into())
+    );
+}
+
+#[test]
+fn test_rational_to_f64() {
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.resolution(ResolutionUnit::Centimeter, Rational { n: 254, d: 2 });
+        let encoder = image.encoder();
+        encoder
+            .write_tag(
+                Tag::Unknown(65000),
+                &[
+                    Rational { n: 48, d: 1 },
+                    Rational { n: 7, d: 2 },
+                    Rational { n: 0, d: 1 },
+                ][..],
+            )
+            .unwrap();
+        encoder
+            .write_tag(Tag::ExposureBiasValue, SRational { n: -2, d: 3 })
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert_eq!(
+        decoder.get_tag_rational(Tag::XResolution).unwrap(),
+        (254, 2)
+    );
+    assert_eq!(decoder.get_tag_f64(Tag::XResolution).unwrap(), 127.0);
+    assert_eq!(
+        decoder.get_tag_f64_vec(Tag::Unknown(65000)).unwrap(),
+        [48.0, 3.5, 0.0]
+    );
+    assert_eq!(decoder.get_tag_f64_vec(Tag::YResolution).unwrap(), [127.0]);
+    assert_eq!(
+        decoder.get_tag_f64(Tag::ExposureBiasValue).unwrap(),
+        -2.0 / 3.0
+    );
+    assert!(decoder.get_tag_f64(Tag::ImageWidth).is_err());
+}
+
+#[test]
+fn test_float_tags() {
+    fn check<K: tiff::encoder::TiffKind>(mut tiff: TiffEncoder<&mut Cursor<Vec<u8>>, K>) {
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        let encoder = image.encoder();
+        encoder.write_tag(Tag::Unknown(65000), 1.5f32).unwrap();
+        encoder
+            .write_tag(Tag::Unknown(65001), &[0.25f32, -2.0][..])
+            .unwrap();
+        encoder
+            .write_tag(Tag::Unknown(65002), &[1.0f32, 2.0, 3.0][..])
+            .unwrap();
+        encoder.write_tag(Tag::Unknown(65003), -0.125f64).unwrap();
+        encoder
+            .write_tag(Tag::GeoDoubleParamsTag, &[6378137.0f64, 298.257223563][..])
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+    }
+
+    for big in [false, true].iter() {
+        let mut file = Cursor::new(Vec::new());
+        if *big {
+            check(TiffEncoder::new_big(&mut file).unwrap());
+        } else {
+            check(TiffEncoder::new(&mut file).unwrap());
+        }
+
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut decoder = Decoder::new(&mut file).unwrap();
+        assert_eq!(decoder.get_tag_f32(Tag::Unknown(65000)).unwrap(), 1.5);
+        assert_eq!(
+            decoder.get_tag_f32_vec(Tag::Unknown(65001)).unwrap(),
+            [0.25, -2.0]
+        );
+        assert_eq!(
+            decoder.get_tag_f32_vec(Tag::Unknown(65002)).unwrap(),
+            [1.0, 2.0, 3.0]
+        );
+        assert_eq!(
+            decoder.get_tag_f64_vec(Tag::Unknown(65002)).unwrap(),
+            [1.0, 2.0, 3.0]
+        );
+        assert_eq!(decoder.get_tag_f64(Tag::Unknown(65003)).unwrap(), -0.125);
+        assert_eq!(
+            decoder.get_tag_f64_vec(Tag::GeoDoubleParamsTag).unwrap(),
+            [6378137.0, 298.257223563]
+        );
+        assert!(decoder.get_tag_f32(Tag::GeoDoubleParamsTag).is_err());
+    }
+}
+
+#[test]
+fn test_byte_payloads() {
+    let profile: Vec<u8> = (0..=255).collect();
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        let encoder = image.encoder();
+        encoder
+            .write_tag(Tag::IccProfile, tiff::encoder::Undefined(&profile))
+            .unwrap();
+        encoder
+            .write_tag(Tag::MakerNote, tiff::encoder::Undefined(&[1, 2, 3]))
+            .unwrap();
+        encoder.write_tag(Tag::Xmp, &b"<x:xmpmeta/>"[..]).unwrap();
+        encoder
+            .write_tag(Tag::Unknown(65000), &[7u8, 8][..])
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert_eq!(
+        decoder.get_tag(Tag::IccProfile).unwrap(),
+        ifd::Value::Bytes(profile.clone())
+    );
+    assert_eq!(decoder.get_tag_u8_vec(Tag::IccProfile).unwrap(), profile);
+    assert_eq!(
+        decoder.get_tag(Tag::MakerNote).unwrap(),
+        ifd::Value::Bytes(vec![1, 2, 3])
+    );
+    assert_eq!(
+        decoder.get_tag_u8_vec(Tag::Xmp).unwrap(),
+        b"<x:xmpmeta/>".to_vec()
+    );
+    assert_eq!(
+        decoder.get_tag(Tag::Unknown(65000)).unwrap(),
+        ifd::Value::Bytes(vec![7, 8])
+    );
+    assert_eq!(
+        decoder.get_tag_u32_vec(Tag::Unknown(65000)).unwrap(),
+        [7, 8]
+    );
+}
+
+#[test]
+fn test_gps_info() {
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image
+            .encoder()
+            .write_sub_directory(Tag::GpsDirectory, |gps| {
+                gps.write_tag(Tag::Unknown(0), &[2u8, 3, 0, 0][..])?;
+                gps.write_tag(Tag::Unknown(1), "S")?;
+                gps.write_tag(
+                    Tag::Unknown(2),
+                    &[
+                        Rational { n: 33, d: 1 },
+                        Rational { n: 51, d: 1 },
+                        Rational { n: 5436, d: 100 },
+                    ][..],
+                )?;
+                gps.write_tag(Tag::Unknown(3), "E")?;
+                gps.write_tag(
+                    Tag::Unknown(4),
+                    &[
+                        Rational { n: 151, d: 1 },
+                        Rational { n: 1275, d: 100 },
+                        Rational { n: 0, d: 1 },
+                    ][..],
+                )?;
+                gps.write_tag(Tag::Unknown(5), 1u8)?;
+                gps.write_tag(Tag::Unknown(6), Rational { n: 45, d: 2 })
+            })
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+
+        let image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.write_data(&[0]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    let gps = decoder.gps_info().unwrap().unwrap();
+    assert!((gps.latitude.unwrap() + (33.0 + 51.0 / 60.0 + 54.36 / 3600.0)).abs() < 1e-9);
+    assert!((gps.longitude.unwrap() - (151.0 + 12.75 / 60.0)).abs() < 1e-9);
+    assert_eq!(gps.altitude, Some(-22.5));
+
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.gps_info().unwrap(), None);
+}
+
+#[test]
+fn test_interop_directory() {
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image
+            .encoder()
+            .write_sub_directory(Tag::ExifDirectory, |exif| {
+                exif.write_tag(Tag::ExposureBiasValue, SRational { n: 1, d: 3 })?;
+                exif.write_sub_directory(Tag::InteropDirectory, |interop| {
+                    interop.write_tag(Tag::Unknown(1), "R98")?;
+                    interop.write_tag(Tag::Unknown(2), tiff::encoder::Undefined(b"0100"))
+                })?;
+                Ok(())
+            })
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image
+            .encoder()
+            .write_sub_directory(Tag::InteropDirectory, |interop| {
+                interop.write_tag(Tag::Unknown(1), "THM")
+            })
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    let exif = decoder
+        .sub_directory_tags(Tag::ExifDirectory)
+        .unwrap()
+        .unwrap();
+    assert_eq!(exif.len(), 2);
+    assert_eq!(
+        exif[0],
+        (Tag::ExposureBiasValue, ifd::Value::SRational(1, 3))
+    );
+    assert_eq!(exif[1].0, Tag::InteropDirectory);
+    assert_eq!(
+        decoder.interop_tags().unwrap().unwrap(),
+        [
+            (Tag::Unknown(1), ifd::Value::Ascii("R98".into())),
+            (Tag::Unknown(2), ifd::Value::Bytes(b"0100".to_vec())),
+        ]
+    );
+    assert_eq!(decoder.sub_directory_tags(Tag::GpsDirectory).unwrap(), None);
+
+    decoder.next_image().unwrap();
+    assert_eq!(
+        decoder.interop_tags().unwrap().unwrap(),
+        [(Tag::Unknown(1), ifd::Value::Ascii("THM".into()))]
+    );
+    assert_eq!(
+        decoder.sub_directory_tags(Tag::ExifDirectory).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_private_tags_by_number() {
+    let file = encode(|tiff| {
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        let encoder = image.encoder();
+        encoder
+            .write_tag(Tag::Unknown(42112), "<GDALMetadata></GDALMetadata>")
+            .unwrap();
+        encoder.write_tag(Tag::Unknown(65420), 7u32).unwrap();
+        image.write_data(&[0]).unwrap();
+    });
+
+    let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+    assert!(decoder.tag_list().contains(&Tag::Unknown(65420)));
+    assert_eq!(
+        decoder
+            .find_tag_by_number(42112)
+            .unwrap()
+            .unwrap()
+            .into_string()
+            .unwrap(),
+        "<GDALMetadata></GDALMetadata>"
+    );
+    assert_eq!(
+        decoder
+            .find_tag_by_number(65420)
+            .unwrap()
+            .unwrap()
+            .into_u32()
+            .unwrap(),
+        7
+    );
+    // Known tags are found by number as well.
+    assert_eq!(
+        decoder
+            .find_tag_by_number(256)
+            .unwrap()
+            .unwrap()
+            .into_u32()
+            .unwrap(),
+        1
+    );
+    assert!(decoder.find_tag_by_number(65421).unwrap().is_none());
+}
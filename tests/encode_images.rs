@@ -1,9 +1,14 @@
 extern crate tiff;
 
-use tiff::decoder::{ifd, Decoder, DecodingResult};
-use tiff::encoder::{colortype, Ifd, Ifd8, SRational, TiffEncoder};
-use tiff::tags::Tag;
-use tiff::ColorType;
+use tiff::decoder::{ifd, ChunkType, Decoder, DecodingResult};
+use tiff::encoder::{
+    colortype, compression, CogEncoder, Ifd, Ifd8, Progress, Rational, SRational, StreamEncoder,
+    TiffEncoder, TiffKind, TiffKindBig, TiffKindStandard,
+};
+use tiff::tags::{
+    ExtraSamples, ModelType, Orientation, Predictor, RasterType, ResolutionUnit, Tag,
+};
+use tiff::{ColorType, TiffError, TiffFormatError};
 
 use std::fs::File;
 use std::io::{Cursor, Seek, SeekFrom};
@@ -148,6 +153,31 @@ fn test_encode_undersized_buffer() {
     }
 }
 
+#[test]
+fn test_encode_undersized_tiled_buffer() {
+    // Enough samples for every pixel, but not for every channel of them.
+    let input_data = vec![0u8; 50 * 50];
+    let mut tiff = TiffEncoder::new(Cursor::new(Vec::new())).unwrap();
+    let image = tiff
+        .image_builder::<colortype::RGB8>(50, 50)
+        .tile_size(16, 16)
+        .build()
+        .unwrap();
+    assert!(image.write_data(&input_data).is_err());
+}
+
+#[test]
+fn test_encode_undersized_subsampled_buffer() {
+    let input_data = vec![0u8; 6 * 4];
+    let mut tiff = TiffEncoder::new(Cursor::new(Vec::new())).unwrap();
+    let image = tiff
+        .image_builder::<colortype::YCbCr8>(6, 4)
+        .ycbcr_subsampling(2, 2)
+        .build()
+        .unwrap();
+    assert!(image.write_data(&input_data).is_err());
+}
+
 const TEST_IMAGE_DIR: &str = "./tests/images/";
 
 macro_rules! test_roundtrip {
@@ -233,6 +263,81 @@ fn test_rgb_u32_roundtrip() {
     test_u32_roundtrip::<colortype::RGB32>("gradient-3c-32b.tiff", ColorType::RGB(32));
 }
 
+#[test]
+fn test_gray_u32_label_map() {
+    // Labels exceeding 16 bits, as found in segmentation results.
+    let labels: Vec<u32> = (0..64 * 48).map(|i| 70_000 + (i / 100) as u32).collect();
+
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        tiff.image_builder::<colortype::Gray32>(64, 48)
+            .compression(compression::Lzw)
+            .predictor(Predictor::Horizontal)
+            .build()
+            .unwrap()
+            .write_data(&labels)
+            .unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut file).unwrap();
+    assert_eq!(decoder.colortype().unwrap(), ColorType::Gray(32));
+    assert_eq!(decoder.get_tag_u32_vec(Tag::BitsPerSample).unwrap(), [32]);
+    assert_eq!(decoder.get_tag_u32_vec(Tag::SampleFormat).unwrap(), [1]);
+    match decoder.read_image().unwrap() {
+        DecodingResult::U32(data) => assert_eq!(data, labels),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+#[test]
+fn test_signed_samples() {
+    let anomalies: Vec<i16> = (0..40 * 30).map(|i| (i % 97) as i16 * 37 - 1800).collect();
+    let differences: Vec<i8> = (0..40 * 30).map(|i| (i % 256) as u8 as i8).collect();
+    let wide: Vec<i32> = (0..40 * 30).map(|i| (i - 600) * 100_003).collect();
+
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        tiff.image_builder::<colortype::GrayI16>(40, 30)
+            .compression(compression::Lzw)
+            .predictor(Predictor::Horizontal)
+            .build()
+            .unwrap()
+            .write_data(&anomalies)
+            .unwrap();
+        tiff.write_image::<colortype::GrayI8>(40, 30, &differences)
+            .unwrap();
+        tiff.write_image_with_compression::<colortype::GrayI32, _>(
+            40,
+            30,
+            compression::Deflate::default(),
+            &wide,
+        )
+        .unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut file).unwrap();
+    for bits in &[16, 8, 32] {
+        assert_eq!(
+            decoder.get_tag_u32_vec(Tag::BitsPerSample).unwrap(),
+            [*bits]
+        );
+        assert_eq!(decoder.get_tag_u32_vec(Tag::SampleFormat).unwrap(), [2]);
+        match decoder.read_image().unwrap() {
+            DecodingResult::I16(data) => assert_eq!(data, anomalies),
+            DecodingResult::I8(data) => assert_eq!(data, differences),
+            DecodingResult::I32(data) => assert_eq!(data, wide),
+            _ => panic!("Wrong data type"),
+        }
+        if decoder.more_images() {
+            decoder.next_image().unwrap();
+        }
+    }
+}
+
 #[test]
 fn test_gray_u64_roundtrip() {
     test_u64_roundtrip::<colortype::Gray64>("gradient-1c-64b.tiff", ColorType::Gray(64));
@@ -446,6 +551,62 @@ fn test_signed() {
     }
 }
 
+#[test]
+fn test_signed_tags_bigtiff() {
+    let mut data = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new_big(&mut data).unwrap();
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        let encoder = image.encoder();
+        // Entry with a type that is patched to an unknown one below.
+        encoder.write_tag(Tag::Unknown(1), 0x7777u16).unwrap();
+        encoder.write_tag(Tag::Unknown(65000), -5_i8).unwrap();
+        encoder
+            .write_tag(Tag::Unknown(65001), &[-1_i16, 2, -3, 4, -5][..])
+            .unwrap();
+        encoder
+            .write_tag(Tag::Unknown(65002), &[-1_i32, 2, -3][..])
+            .unwrap();
+        encoder
+            .write_tag(Tag::ExposureBiasValue, SRational { n: -1, d: 3 })
+            .unwrap();
+        encoder
+            .write_tag(
+                Tag::Unknown(65003),
+                &[SRational { n: -1, d: 3 }, SRational { n: 5, d: -7 }][..],
+            )
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+    }
+
+    let mut bytes = data.into_inner();
+    let mut entry = vec![];
+    entry.extend_from_slice(&1u16.to_ne_bytes());
+    entry.extend_from_slice(&3u16.to_ne_bytes());
+    entry.extend_from_slice(&1u64.to_ne_bytes());
+    entry.extend_from_slice(&0x7777u16.to_ne_bytes());
+    let position = bytes
+        .windows(entry.len())
+        .position(|window| window == &entry[..])
+        .unwrap();
+    bytes[position + 2..position + 4].copy_from_slice(&99u16.to_ne_bytes());
+
+    let mut decoder = Decoder::new(Cursor::new(bytes)).unwrap();
+    assert!(decoder.find_tag(Tag::Unknown(1)).unwrap().is_none());
+    assert_eq!(decoder.dimensions().unwrap(), (1, 1));
+    assert_eq!(decoder.assert_tag_i32(65000), -5);
+    assert_eq!(decoder.assert_tag_i32_vec(65001), [-1, 2, -3, 4, -5]);
+    assert_eq!(decoder.assert_tag_i64_vec(65002), [-1, 2, -3]);
+    assert_eq!(
+        decoder.get_tag_srational(Tag::ExposureBiasValue).unwrap(),
+        (-1, 3)
+    );
+    assert_eq!(
+        decoder.get_tag_srational_vec(Tag::Unknown(65003)).unwrap(),
+        [(-1, 3), (5, -7)]
+    );
+}
+
 #[test]
 /// check multipage image handling
 fn test_multipage_image() {
@@ -519,3 +680,1475 @@ fn test_rows_per_strip() {
         }
     }
 }
+
+#[test]
+fn test_icc_profile() {
+    // Not a valid profile, only the bytes have to survive the roundtrip.
+    let profile: Vec<u8> = (0..=255).collect();
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.icc_profile(&profile).unwrap();
+        image.write_data(&[0]).unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    {
+        let mut decoder = Decoder::new(&mut file).unwrap();
+        assert_eq!(decoder.get_tag_u8_vec(Tag::IccProfile).unwrap(), profile);
+    }
+}
+
+#[test]
+fn test_resolution() {
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.dpi(300, 150);
+        image.write_data(&[0]).unwrap();
+
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.resolution(ResolutionUnit::Centimeter, Rational { n: 254, d: 2 });
+        image.write_data(&[0]).unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    {
+        let mut decoder = Decoder::new(&mut file).unwrap();
+        assert_eq!(
+            decoder.get_tag(Tag::XResolution).unwrap(),
+            ifd::Value::Rational(300, 1)
+        );
+        assert_eq!(
+            decoder.get_tag(Tag::YResolution).unwrap(),
+            ifd::Value::Rational(150, 1)
+        );
+        assert_eq!(
+            decoder.get_tag_u32(Tag::ResolutionUnit).unwrap(),
+            u32::from(ResolutionUnit::Inch.to_u16())
+        );
+        assert_eq!(
+            decoder.resolution().unwrap(),
+            Some((
+                Rational { n: 300, d: 1 },
+                Rational { n: 150, d: 1 },
+                ResolutionUnit::Inch
+            ))
+        );
+
+        decoder.next_image().unwrap();
+        assert_eq!(
+            decoder.get_tag(Tag::XResolution).unwrap(),
+            ifd::Value::Rational(254, 2)
+        );
+        assert_eq!(
+            decoder.get_tag(Tag::YResolution).unwrap(),
+            ifd::Value::Rational(254, 2)
+        );
+        assert_eq!(
+            decoder.get_tag_u32(Tag::ResolutionUnit).unwrap(),
+            u32::from(ResolutionUnit::Centimeter.to_u16())
+        );
+        assert_eq!(
+            decoder.resolution().unwrap(),
+            Some((
+                Rational { n: 254, d: 2 },
+                Rational { n: 254, d: 2 },
+                ResolutionUnit::Centimeter
+            ))
+        );
+    }
+}
+
+#[test]
+fn test_geotiff_tags() {
+    let scale = [0.5, 0.25, 0.0];
+    let tiepoint = [0.0, 0.0, 0.0, 440720.0, 3751320.0, 0.0];
+    let transformation = [
+        60.0, 0.0, 0.0, 440720.0, 0.0, -60.0, 0.0, 3751320.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+        1.0,
+    ];
+    let keys = [1, 1, 0, 3, 1024, 0, 1, 1, 1025, 0, 1, 1, 3072, 0, 1, 26711];
+
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.model_pixel_scale(&scale).unwrap();
+        image.model_tiepoint(&[tiepoint]).unwrap();
+        image.model_transformation(&transformation).unwrap();
+        image.geo_key_directory(&keys).unwrap();
+        image.geo_double_params(&[6378137.0]).unwrap();
+        image.geo_ascii_params("NAD27 / UTM zone 11N|").unwrap();
+        assert!(image.geo_key_directory(&[1, 1, 0]).is_err());
+        image.write_data(&[0]).unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    {
+        let mut decoder = Decoder::new(&mut file).unwrap();
+        assert_eq!(
+            decoder.get_tag_f64_vec(Tag::ModelPixelScaleTag).unwrap(),
+            scale
+        );
+        assert_eq!(
+            decoder.get_tag_f64_vec(Tag::ModelTiepointTag).unwrap(),
+            tiepoint
+        );
+        assert_eq!(
+            decoder
+                .get_tag_f64_vec(Tag::ModelTransformationTag)
+                .unwrap(),
+            &transformation[..]
+        );
+        assert_eq!(
+            decoder.get_tag_u16_vec(Tag::GeoKeyDirectoryTag).unwrap(),
+            keys
+        );
+        assert_eq!(
+            decoder.get_tag_f64_vec(Tag::GeoDoubleParamsTag).unwrap(),
+            [6378137.0]
+        );
+        assert_eq!(
+            decoder
+                .get_tag_ascii_string(Tag::GeoAsciiParamsTag)
+                .unwrap(),
+            "NAD27 / UTM zone 11N|"
+        );
+
+        assert_eq!(decoder.model_pixel_scale().unwrap(), Some(scale));
+        assert_eq!(decoder.model_tiepoints().unwrap(), Some(vec![tiepoint]));
+        assert_eq!(
+            decoder.model_transformation().unwrap(),
+            Some(transformation)
+        );
+
+        let geo_keys = decoder.geo_keys().unwrap().unwrap();
+        assert_eq!(geo_keys.keys.len(), 3);
+        assert_eq!(geo_keys.model_type(), Some(ModelType::Projected));
+        assert_eq!(geo_keys.raster_type(), Some(RasterType::PixelIsArea));
+        assert_eq!(geo_keys.crs_code(), Some(26711));
+        assert_eq!(geo_keys.citation(), None);
+    }
+}
+
+fn check_exif_directory<K: TiffKind>(big: bool) {
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::<_, K>::new_generic(&mut file).unwrap();
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.encoder().write_tag(Tag::Model, "Camera").unwrap();
+        image
+            .encoder()
+            .write_sub_directory(Tag::ExifDirectory, |exif| {
+                exif.write_tag(Tag::ExposureTime, Rational { n: 1, d: 250 })?;
+                exif.write_tag(Tag::PhotographicSensitivity, 400u16)?;
+                exif.write_tag(Tag::DateTimeOriginal, "2021:06:01 12:30:00")
+            })
+            .unwrap();
+        image.write_data(&[42]).unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    {
+        let mut decoder = Decoder::new(&mut file).unwrap();
+        assert_eq!(decoder.get_tag_ascii_string(Tag::Model).unwrap(), "Camera");
+        let exif_offset = decoder.get_tag_u64(Tag::ExifDirectory).unwrap();
+        match decoder.read_image().unwrap() {
+            DecodingResult::U8(data) => assert_eq!(data, [42]),
+            _ => panic!("Wrong data type"),
+        }
+
+        decoder.goto_offset_u64(exif_offset).unwrap();
+        let count = if big {
+            decoder.read_long8().unwrap()
+        } else {
+            decoder.read_short().unwrap().into()
+        };
+        assert_eq!(count, 3);
+
+        let mut tags = Vec::new();
+        for _ in 0..count {
+            tags.push(decoder.read_short().unwrap());
+            decoder.read_short().unwrap();
+            if big {
+                decoder.read_long8().unwrap();
+                decoder.read_long8().unwrap();
+            } else {
+                decoder.read_long().unwrap();
+                decoder.read_long().unwrap();
+            }
+        }
+        assert_eq!(
+            tags,
+            [
+                Tag::ExposureTime.to_u16(),
+                Tag::PhotographicSensitivity.to_u16(),
+                Tag::DateTimeOriginal.to_u16()
+            ]
+        );
+        let next = if big {
+            decoder.read_long8().unwrap()
+        } else {
+            decoder.read_long().unwrap().into()
+        };
+        assert_eq!(next, 0);
+    }
+}
+
+#[test]
+fn test_exif_directory() {
+    check_exif_directory::<TiffKindStandard>(false);
+    check_exif_directory::<TiffKindBig>(true);
+}
+
+#[test]
+fn test_ascii_tags() {
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.image_description("A single pixel").unwrap();
+        image.software("image-tiff").unwrap();
+        image.artist("Jane Doe").unwrap();
+        image.copyright("Public Domain").unwrap();
+        image.make("ACME").unwrap();
+        image.model("Roadrunner 3000").unwrap();
+        image.date_time("2021:06:01 12:30:00").unwrap();
+        assert!(image.date_time("2021-06-01 12:30:00").is_err());
+        assert!(image.date_time("2021:06:01").is_err());
+        assert!(image.software("nul\0byte").is_err());
+        assert!(image.artist("Jürgen").is_err());
+        image.write_data(&[0]).unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    {
+        let mut decoder = Decoder::new(&mut file).unwrap();
+        for &(tag, expected) in &[
+            (Tag::ImageDescription, "A single pixel"),
+            (Tag::Software, "image-tiff"),
+            (Tag::Artist, "Jane Doe"),
+            (Tag::Copyright, "Public Domain"),
+            (Tag::Make, "ACME"),
+            (Tag::Model, "Roadrunner 3000"),
+            (Tag::DateTime, "2021:06:01 12:30:00"),
+        ] {
+            assert_eq!(decoder.get_tag_ascii_string(tag).unwrap(), expected);
+        }
+    }
+}
+
+#[test]
+fn test_overviews() {
+    let width = 5;
+    let height = 3;
+    let data: Vec<u8> = (0..width * height * 3).map(|v| v as u8).collect();
+
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        assert!(tiff
+            .write_image_with_overviews::<colortype::RGB8>(width, height, &data, &[1])
+            .is_err());
+    }
+
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        tiff.write_image_with_overviews::<colortype::RGB8>(width, height, &data, &[2, 4])
+            .unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    {
+        let mut decoder = Decoder::new(&mut file).unwrap();
+        assert_eq!(decoder.dimensions().unwrap(), (width, height));
+        assert!(decoder.find_tag(Tag::NewSubfileType).unwrap().is_none());
+
+        decoder.next_image().unwrap();
+        assert_eq!(decoder.dimensions().unwrap(), (3, 2));
+        assert_eq!(decoder.get_tag_u32(Tag::NewSubfileType).unwrap(), 1);
+        match decoder.read_image().unwrap() {
+            DecodingResult::U8(overview) => assert_eq!(
+                overview,
+                [
+                    0, 1, 2, 6, 7, 8, 12, 13, 14, //
+                    30, 31, 32, 36, 37, 38, 42, 43, 44,
+                ]
+            ),
+            _ => panic!("Wrong data type"),
+        }
+
+        decoder.next_image().unwrap();
+        assert_eq!(decoder.dimensions().unwrap(), (2, 1));
+        assert_eq!(decoder.get_tag_u32(Tag::NewSubfileType).unwrap(), 1);
+        match decoder.read_image().unwrap() {
+            DecodingResult::U8(overview) => assert_eq!(overview, [0, 1, 2, 12, 13, 14]),
+            _ => panic!("Wrong data type"),
+        }
+        assert!(!decoder.more_images());
+    }
+}
+
+fn check_append<K: TiffKind>(
+    append: fn(&mut Cursor<Vec<u8>>) -> TiffEncoder<&mut Cursor<Vec<u8>>, K>,
+) {
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::<_, K>::new_generic(&mut file).unwrap();
+        tiff.write_image::<colortype::Gray8>(2, 1, &[1, 2]).unwrap();
+    }
+    for page in 1..3u8 {
+        let mut tiff = append(&mut file);
+        tiff.write_image::<colortype::Gray8>(2, 1, &[page * 10, page * 10 + 1])
+            .unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut file).unwrap();
+    let mut pages = Vec::new();
+    loop {
+        match decoder.read_image().unwrap() {
+            DecodingResult::U8(data) => pages.push(data),
+            _ => panic!("Wrong data type"),
+        }
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image().unwrap();
+    }
+    assert_eq!(pages, [[1, 2], [10, 11], [20, 21]]);
+}
+
+#[test]
+fn test_append() {
+    check_append::<TiffKindStandard>(|file| TiffEncoder::append(file).unwrap());
+    check_append::<TiffKindBig>(|file| TiffEncoder::append_big(file).unwrap());
+}
+
+#[test]
+fn test_append_kind_mismatch() {
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        tiff.write_image::<colortype::Gray8>(1, 1, &[1]).unwrap();
+    }
+    assert!(TiffEncoder::append_big(&mut file).is_err());
+    assert!(TiffEncoder::append(Cursor::new(b"not a tiff".to_vec())).is_err());
+}
+
+#[test]
+fn test_append_empty_file() {
+    let mut file = Cursor::new(Vec::new());
+    TiffEncoder::new(&mut file).unwrap();
+    {
+        let mut tiff = TiffEncoder::append(&mut file).unwrap();
+        tiff.write_image::<colortype::Gray8>(1, 1, &[7]).unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut file).unwrap();
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(data) => assert_eq!(data, [7]),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+#[test]
+fn test_cog_layout() {
+    let (width, height) = (100u32, 70u32);
+    let data: Vec<u8> = (0..width * height * 3).map(|v| (v % 251) as u8).collect();
+
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut cog = CogEncoder::new(&mut file);
+        assert!(cog.tile_size(30, 32).is_err());
+        cog.tile_size(32, 32).unwrap();
+        cog.write_image_with_tags::<colortype::RGB8, _, _>(
+            width,
+            height,
+            compression::Lzw,
+            &data,
+            |encoder| encoder.write_tag(Tag::Software, "image-tiff"),
+        )
+        .unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut file).unwrap();
+
+    // Walk the IFD chain, all directories must precede the tile data.
+    let mut ifd_end = 0;
+    decoder.goto_offset(4).unwrap();
+    let mut next = decoder.read_long().unwrap();
+    while next != 0 {
+        decoder.goto_offset(next).unwrap();
+        let entries = u32::from(decoder.read_short().unwrap());
+        ifd_end = ifd_end.max(next + 2 + entries * 12 + 4);
+        decoder.goto_offset(next + 2 + entries * 12).unwrap();
+        next = decoder.read_long().unwrap();
+    }
+
+    let expected_sizes = [(100, 70), (50, 35), (25, 18)];
+    let mut tile_ranges = Vec::new();
+    decoder.seek_to_image(0).unwrap();
+    for (page, &(page_width, page_height)) in expected_sizes.iter().enumerate() {
+        if page > 0 {
+            decoder.next_image().unwrap();
+            assert_eq!(decoder.get_tag_u32(Tag::NewSubfileType).unwrap(), 1);
+        } else {
+            assert_eq!(
+                decoder.get_tag_ascii_string(Tag::Software).unwrap(),
+                "image-tiff"
+            );
+        }
+        assert_eq!(decoder.dimensions().unwrap(), (page_width, page_height));
+        assert_eq!(decoder.get_tag_u32(Tag::TileWidth).unwrap(), 32);
+
+        let offsets = decoder.get_tag_u64_vec(Tag::TileOffsets).unwrap();
+        let counts = decoder.get_tag_u64_vec(Tag::TileByteCounts).unwrap();
+        assert!(offsets.iter().all(|&offset| offset >= u64::from(ifd_end)));
+        let start = *offsets.iter().min().unwrap();
+        let end = offsets
+            .iter()
+            .zip(&counts)
+            .map(|(o, c)| o + c)
+            .max()
+            .unwrap();
+        tile_ranges.push((start, end));
+
+        let factor = (width / page_width) as usize;
+        let expected: Vec<u8> = if factor == 1 {
+            data.clone()
+        } else {
+            data.chunks(width as usize * 3)
+                .step_by(factor)
+                .flat_map(|row| row.chunks(3).step_by(factor).flatten().copied())
+                .collect()
+        };
+        match decoder.read_image().unwrap() {
+            DecodingResult::U8(image) => assert_eq!(image, expected),
+            _ => panic!("Wrong data type"),
+        }
+    }
+    assert!(!decoder.more_images());
+
+    // Tile data is ordered from the smallest overview to the full resolution image.
+    assert!(tile_ranges[2].1 <= tile_ranges[1].0);
+    assert!(tile_ranges[1].1 <= tile_ranges[0].0);
+}
+
+#[test]
+fn test_cog_sparse_tiles() {
+    // Only the top left tile of the full resolution image contains data.
+    let mut data = vec![0u16; 64 * 48];
+    data[0] = 1;
+    data[64 + 1] = 2;
+
+    let write = |sparse: bool| {
+        let mut file = Cursor::new(Vec::new());
+        let mut cog = CogEncoder::new(&mut file);
+        cog.tile_size(16, 16).unwrap();
+        cog.overviews(&[2]).unwrap();
+        cog.sparse(sparse);
+        cog.write_image::<colortype::Gray16>(64, 48, &data).unwrap();
+        file
+    };
+
+    let dense = write(false).into_inner();
+    let mut file = write(true);
+    assert!(file.get_ref().len() < dense.len());
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut file).unwrap();
+    let offsets = decoder.get_tag_u64_vec(Tag::TileOffsets).unwrap();
+    let counts = decoder.get_tag_u64_vec(Tag::TileByteCounts).unwrap();
+    assert_eq!(offsets.len(), 12);
+    assert_eq!(counts[0], 16 * 16 * 2);
+    assert!(offsets[0] != 0);
+    assert!(offsets[1..].iter().all(|&offset| offset == 0));
+    assert!(counts[1..].iter().all(|&count| count == 0));
+
+    match decoder.read_chunk(0).unwrap() {
+        DecodingResult::U16(tile) => {
+            assert_eq!(tile[..2], [1, 0]);
+            assert_eq!(tile[16..18], [0, 2]);
+        }
+        _ => panic!("Wrong data type"),
+    }
+
+    decoder.next_image().unwrap();
+    let offsets = decoder.get_tag_u64_vec(Tag::TileOffsets).unwrap();
+    assert_eq!(offsets.len(), 4);
+    assert!(offsets[0] != 0);
+    assert!(offsets[1..].iter().all(|&offset| offset == 0));
+}
+
+#[test]
+fn test_copy_raw_image() {
+    for file in &[
+        "tiled-rect-rgb-u8.tif",
+        "issue_69_lzw.tiff",
+        "predictor-3-gray-f32.tif",
+        "tiled-jpeg-rgb-u8.tif",
+    ] {
+        let path = PathBuf::from(TEST_IMAGE_DIR).join(file);
+        let mut source = Decoder::new(File::open(path).unwrap()).unwrap();
+
+        let mut copy = Cursor::new(Vec::new());
+        {
+            let mut tiff = TiffEncoder::new(&mut copy).unwrap();
+            let mut directory = tiff.new_directory().unwrap();
+            directory.copy_raw_image(&mut source).unwrap();
+            directory.write_tag(Tag::Software, "image-tiff").unwrap();
+            directory.finish().unwrap();
+        }
+
+        copy.seek(SeekFrom::Start(0)).unwrap();
+        let mut decoder = Decoder::new(&mut copy).unwrap();
+        assert_eq!(decoder.dimensions().unwrap(), source.dimensions().unwrap());
+        assert_eq!(decoder.colortype().unwrap(), source.colortype().unwrap());
+        assert_eq!(decoder.get_chunk_type(), source.get_chunk_type());
+        assert_eq!(
+            decoder.get_tag_ascii_string(Tag::Software).unwrap(),
+            "image-tiff"
+        );
+        let offsets_tag = match decoder.get_chunk_type() {
+            ChunkType::Strip => Tag::StripOffsets,
+            ChunkType::Tile => Tag::TileOffsets,
+        };
+        let chunk_count = decoder.get_tag_u64_vec(offsets_tag).unwrap().len() as u32;
+        for chunk in 0..chunk_count {
+            assert_eq!(
+                decoder.read_chunk_bytes(chunk).unwrap(),
+                source.read_chunk_bytes(chunk).unwrap()
+            );
+        }
+        assert_eq!(
+            format!("{:?}", decoder.read_image().unwrap()),
+            format!("{:?}", source.read_image().unwrap())
+        );
+    }
+}
+
+#[test]
+fn test_copy_raw_subsampled_image() {
+    let data: Vec<u8> = (0..8 * 6 * 3).map(|i| (i * 5 % 256) as u8).collect();
+    let reference = [
+        Rational { n: 0, d: 1 },
+        Rational { n: 255, d: 1 },
+        Rational { n: 128, d: 1 },
+        Rational { n: 255, d: 1 },
+        Rational { n: 128, d: 1 },
+        Rational { n: 255, d: 1 },
+    ];
+    let mut source = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut source).unwrap();
+        let mut image = tiff
+            .image_builder::<colortype::YCbCr8>(8, 6)
+            .compression(compression::Lzw)
+            .ycbcr_subsampling(2, 2)
+            .rows_per_strip(2)
+            .build()
+            .unwrap();
+        let encoder = image.encoder();
+        encoder.write_tag(Tag::YCbCrPositioning, 2u16).unwrap();
+        encoder
+            .write_tag(Tag::ReferenceBlackWhite, &reference[..])
+            .unwrap();
+        encoder.write_tag(Tag::FillOrder, 1u16).unwrap();
+        image.write_data(&data).unwrap();
+    }
+    source.seek(SeekFrom::Start(0)).unwrap();
+    let mut source = Decoder::new(source).unwrap();
+
+    let mut copy = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut copy).unwrap();
+        let mut directory = tiff.new_directory().unwrap();
+        directory.copy_raw_image(&mut source).unwrap();
+        directory.finish().unwrap();
+    }
+    copy.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(copy).unwrap();
+
+    for &tag in &[
+        Tag::YCbCrSubSampling,
+        Tag::YCbCrPositioning,
+        Tag::ReferenceBlackWhite,
+        Tag::FillOrder,
+    ] {
+        assert_eq!(
+            format!("{:?}", decoder.get_tag(tag).unwrap()),
+            format!("{:?}", source.get_tag(tag).unwrap())
+        );
+    }
+    for chunk in 0..3 {
+        assert_eq!(
+            decoder.read_chunk_bytes(chunk).unwrap(),
+            source.read_chunk_bytes(chunk).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_image_builder() {
+    let gray: Vec<u16> = (0..50 * 40).map(|i| (i * 37 % 65536) as u16).collect();
+    let rgb: Vec<u8> = (0..30 * 20 * 3).map(|i| (i * 7 % 256) as u8).collect();
+    let float: Vec<f32> = (0..33 * 17).map(|i| i as f32 * 0.25 - 10.0).collect();
+
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        tiff.image_builder::<colortype::Gray16>(50, 40)
+            .compression(compression::Lzw)
+            .predictor(Predictor::Horizontal)
+            .tile_size(16, 32)
+            .build()
+            .unwrap()
+            .write_data(&gray)
+            .unwrap();
+        tiff.image_builder::<colortype::RGB8>(30, 20)
+            .compression(compression::Deflate::default())
+            .predictor(Predictor::Horizontal)
+            .rows_per_strip(7)
+            .build()
+            .unwrap()
+            .write_data(&rgb)
+            .unwrap();
+        tiff.image_builder::<colortype::Gray32Float>(33, 17)
+            .compression(compression::Lzw)
+            .predictor(Predictor::FloatingPoint)
+            .build()
+            .unwrap()
+            .write_data(&float)
+            .unwrap();
+        tiff.image_builder::<colortype::Gray32Float>(33, 17)
+            .compression(compression::Deflate::default())
+            .predictor(Predictor::FloatingPoint)
+            .tile_size(16, 16)
+            .build()
+            .unwrap()
+            .write_data(&float)
+            .unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut file).unwrap();
+    assert_eq!(decoder.get_chunk_type(), ChunkType::Tile);
+    assert_eq!(decoder.get_tag_u32(Tag::TileWidth).unwrap(), 16);
+    assert!(decoder.find_tag(Tag::RowsPerStrip).unwrap().is_none());
+    match decoder.read_image().unwrap() {
+        DecodingResult::U16(data) => assert_eq!(data, gray),
+        _ => panic!("Wrong data type"),
+    }
+
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.get_tag_u32(Tag::RowsPerStrip).unwrap(), 7);
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(data) => assert_eq!(data, rgb),
+        _ => panic!("Wrong data type"),
+    }
+
+    for _ in 0..2 {
+        decoder.next_image().unwrap();
+        assert_eq!(decoder.get_tag_u32(Tag::Predictor).unwrap(), 3);
+        match decoder.read_image().unwrap() {
+            DecodingResult::F32(data) => assert_eq!(data, float),
+            _ => panic!("Wrong data type"),
+        }
+    }
+    assert!(!decoder.more_images());
+}
+
+#[test]
+fn test_image_builder_validation() {
+    let mut file = Cursor::new(Vec::new());
+    let mut tiff = TiffEncoder::new(&mut file).unwrap();
+
+    assert!(tiff
+        .image_builder::<colortype::Gray8>(10, 10)
+        .compression(compression::Lzw)
+        .predictor(Predictor::FloatingPoint)
+        .build()
+        .is_err());
+    assert!(tiff
+        .image_builder::<colortype::Gray32Float>(10, 10)
+        .compression(compression::Lzw)
+        .predictor(Predictor::Horizontal)
+        .build()
+        .is_err());
+    assert!(tiff
+        .image_builder::<colortype::Gray8>(10, 10)
+        .predictor(Predictor::Horizontal)
+        .build()
+        .is_err());
+    assert!(tiff
+        .image_builder::<colortype::Gray8>(10, 10)
+        .tile_size(20, 16)
+        .build()
+        .is_err());
+    assert!(tiff
+        .image_builder::<colortype::Gray8>(10, 10)
+        .tile_size(16, 16)
+        .rows_per_strip(4)
+        .build()
+        .is_err());
+    assert!(tiff
+        .image_builder::<colortype::Gray8>(0, 10)
+        .build()
+        .is_err());
+
+    // Nothing but the header was written.
+    drop(tiff);
+    assert_eq!(file.get_ref().len(), 8);
+}
+
+#[test]
+fn test_extra_samples() {
+    let data = vec![128u8; 4 * 4 * 4];
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        tiff.write_image::<colortype::RGBA8>(4, 4, &data).unwrap();
+
+        let mut image = tiff.new_image::<colortype::RGBA8>(4, 4).unwrap();
+        assert!(image
+            .extra_samples(&[ExtraSamples::AssociatedAlpha, ExtraSamples::Unspecified])
+            .is_err());
+        image
+            .extra_samples(&[ExtraSamples::AssociatedAlpha])
+            .unwrap();
+        image.write_data(&data).unwrap();
+
+        tiff.write_image::<colortype::RGB8>(4, 4, &data[..4 * 4 * 3])
+            .unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut file).unwrap();
+    assert_eq!(
+        decoder
+            .find_tag_unsigned_vec::<u16>(Tag::ExtraSamples)
+            .unwrap(),
+        Some(vec![2])
+    );
+    assert_eq!(decoder.colortype().unwrap(), ColorType::RGBA(8));
+    decoder.next_image().unwrap();
+    assert_eq!(
+        decoder
+            .find_tag_unsigned_vec::<u16>(Tag::ExtraSamples)
+            .unwrap(),
+        Some(vec![1])
+    );
+    decoder.next_image().unwrap();
+    assert!(decoder.find_tag(Tag::ExtraSamples).unwrap().is_none());
+}
+
+#[test]
+fn test_ycbcr_subsampling() {
+    // A 6x4 image whose pixels are (x + 10 * y, 100 + x, 200 + y).
+    let mut data = Vec::new();
+    for y in 0..4u8 {
+        for x in 0..6u8 {
+            data.extend_from_slice(&[x + 10 * y, 100 + x, 200 + y]);
+        }
+    }
+
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        tiff.image_builder::<colortype::YCbCr8>(6, 4)
+            .ycbcr_subsampling(2, 2)
+            .rows_per_strip(2)
+            .build()
+            .unwrap()
+            .write_data(&data)
+            .unwrap();
+        tiff.image_builder::<colortype::YCbCr8>(6, 4)
+            .compression(compression::Lzw)
+            .ycbcr_subsampling(2, 1)
+            .tile_size(16, 16)
+            .build()
+            .unwrap()
+            .write_data(&data)
+            .unwrap();
+        tiff.write_image::<colortype::YCbCr8>(6, 4, &data).unwrap();
+
+        assert!(tiff
+            .image_builder::<colortype::RGB8>(6, 4)
+            .ycbcr_subsampling(2, 2)
+            .build()
+            .is_err());
+        assert!(tiff
+            .image_builder::<colortype::YCbCr8>(6, 4)
+            .ycbcr_subsampling(1, 2)
+            .build()
+            .is_err());
+        assert!(tiff
+            .image_builder::<colortype::YCbCr8>(6, 4)
+            .ycbcr_subsampling(2, 2)
+            .rows_per_strip(3)
+            .build()
+            .is_err());
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut file).unwrap();
+    assert_eq!(
+        decoder
+            .find_tag_unsigned_vec::<u16>(Tag::YCbCrSubSampling)
+            .unwrap(),
+        Some(vec![2, 2])
+    );
+    assert_eq!(
+        decoder.get_tag_u32(Tag::PhotometricInterpretation).unwrap(),
+        6
+    );
+    // The first strip holds three data units of four luma and two averaged chroma samples.
+    assert_eq!(
+        decoder.read_chunk_bytes(0).unwrap(),
+        [
+            0, 1, 10, 11, 101, 201, //
+            2, 3, 12, 13, 103, 201, //
+            4, 5, 14, 15, 105, 201,
+        ]
+    );
+    assert_eq!(decoder.read_chunk_bytes(1).unwrap().len(), 18);
+
+    decoder.next_image().unwrap();
+    assert_eq!(
+        decoder
+            .find_tag_unsigned_vec::<u16>(Tag::YCbCrSubSampling)
+            .unwrap(),
+        Some(vec![2, 1])
+    );
+    assert_eq!(decoder.get_chunk_type(), ChunkType::Tile);
+
+    decoder.next_image().unwrap();
+    assert_eq!(
+        decoder
+            .find_tag_unsigned_vec::<u16>(Tag::YCbCrSubSampling)
+            .unwrap(),
+        Some(vec![1, 1])
+    );
+}
+
+#[test]
+fn test_gray_alpha() {
+    let gray: Vec<u8> = (0..8 * 8 * 2).map(|i| i as u8).collect();
+    let gray16: Vec<u16> = (0..8 * 8 * 2).map(|i| i as u16 * 300).collect();
+    let float: Vec<f32> = (0..8 * 8 * 2).map(|i| i as f32 / 128.0).collect();
+
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        tiff.write_image::<colortype::GrayA8>(8, 8, &gray).unwrap();
+        tiff.write_image_with_compression::<colortype::GrayA16, _>(8, 8, compression::Lzw, &gray16)
+            .unwrap();
+        tiff.write_image::<colortype::GrayA32Float>(8, 8, &float)
+            .unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut file).unwrap();
+    assert_eq!(decoder.colortype().unwrap(), ColorType::GrayA(8));
+    assert_eq!(
+        decoder
+            .find_tag_unsigned_vec::<u16>(Tag::ExtraSamples)
+            .unwrap(),
+        Some(vec![2])
+    );
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(data) => assert_eq!(data, gray),
+        _ => panic!("Wrong data type"),
+    }
+
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.colortype().unwrap(), ColorType::GrayA(16));
+    match decoder.read_image().unwrap() {
+        DecodingResult::U16(data) => assert_eq!(data, gray16),
+        _ => panic!("Wrong data type"),
+    }
+
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.colortype().unwrap(), ColorType::GrayA(32));
+    match decoder.read_image().unwrap() {
+        DecodingResult::F32(data) => assert_eq!(data, float),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+#[test]
+fn test_auto_compression() {
+    // Simple linear congruential generator to produce incompressible data.
+    let mut state = 12345u32;
+    let mut random = move || {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (state >> 16) as u8
+    };
+    let noise: Vec<u8> = (0..64 * 64).map(|_| random()).collect();
+    let two_tone: Vec<u8> = (0..64 * 64)
+        .map(|_| if random() < 128 { 0 } else { 255 })
+        .collect();
+    let gradient: Vec<u16> = (0..64 * 64)
+        .map(|i| (i % 64 * 997 + i / 64 * 13) as u16)
+        .collect();
+    let float: Vec<f32> = (0..64 * 64)
+        .map(|i| (i % 64) as f32 * 0.1 + (i / 64) as f32)
+        .collect();
+
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        tiff.write_image_auto::<colortype::Gray8>(64, 64, &noise)
+            .unwrap();
+        tiff.write_image_auto::<colortype::Gray8>(64, 64, &two_tone)
+            .unwrap();
+        tiff.write_image_auto::<colortype::Gray16>(64, 64, &gradient)
+            .unwrap();
+        tiff.write_image_auto::<colortype::Gray32Float>(64, 64, &float)
+            .unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut file).unwrap();
+    let settings = |decoder: &mut Decoder<_>| {
+        (
+            decoder.get_tag_u32(Tag::Compression).unwrap(),
+            decoder.get_tag_u32(Tag::Predictor).unwrap_or(1),
+        )
+    };
+
+    assert_eq!(settings(&mut decoder), (1, 1));
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(data) => assert_eq!(data, noise),
+        _ => panic!("Wrong data type"),
+    }
+    decoder.next_image().unwrap();
+    assert_eq!(settings(&mut decoder), (8, 1));
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(data) => assert_eq!(data, two_tone),
+        _ => panic!("Wrong data type"),
+    }
+    decoder.next_image().unwrap();
+    assert_eq!(settings(&mut decoder), (5, 2));
+    match decoder.read_image().unwrap() {
+        DecodingResult::U16(data) => assert_eq!(data, gradient),
+        _ => panic!("Wrong data type"),
+    }
+    decoder.next_image().unwrap();
+    assert_eq!(settings(&mut decoder), (5, 3));
+    match decoder.read_image().unwrap() {
+        DecodingResult::F32(data) => assert_eq!(data, float),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+#[test]
+fn test_thumbnail() {
+    let image_data: Vec<u8> = (0..32 * 32 * 3).map(|i| (i % 251) as u8).collect();
+    let thumbnail: Vec<u8> = (0..4 * 4 * 3).map(|i| i as u8).collect();
+
+    let mut file = Cursor::new(Vec::new());
+    let thumbnail_offset = {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        let mut image = tiff.new_image::<colortype::RGB8>(32, 32).unwrap();
+        let offset = image
+            .encoder()
+            .write_thumbnail::<colortype::RGB8>(4, 4, &thumbnail)
+            .unwrap();
+        image.write_data(&image_data).unwrap();
+        offset
+    };
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    {
+        let mut decoder = Decoder::new(&mut file).unwrap();
+        assert_eq!(decoder.get_tag_u64(Tag::SubIfd).unwrap(), thumbnail_offset);
+        match decoder.read_image().unwrap() {
+            DecodingResult::U8(data) => assert_eq!(data, image_data),
+            _ => panic!("Wrong data type"),
+        }
+        assert!(!decoder.more_images());
+    }
+
+    // Point the header at the thumbnail to decode it as the first image.
+    let mut bytes = file.into_inner();
+    bytes[4..8].copy_from_slice(&(thumbnail_offset as u32).to_ne_bytes());
+    let mut decoder = Decoder::new(Cursor::new(bytes)).unwrap();
+    assert_eq!(decoder.dimensions().unwrap(), (4, 4));
+    assert_eq!(decoder.get_tag_u32(Tag::NewSubfileType).unwrap(), 1);
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(data) => assert_eq!(data, thumbnail),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+/// Read the tags of the sub-directory at `offset` of a standard Tiff file.
+fn sub_directory_tags<R: std::io::Read + std::io::Seek>(
+    decoder: &mut Decoder<R>,
+    offset: u64,
+) -> Vec<u16> {
+    decoder.goto_offset_u64(offset).unwrap();
+    let count = decoder.read_short().unwrap();
+    let mut tags = Vec::new();
+    for _ in 0..count {
+        tags.push(decoder.read_short().unwrap());
+        decoder.read_short().unwrap();
+        decoder.read_long().unwrap();
+        decoder.read_long().unwrap();
+    }
+    tags
+}
+
+#[test]
+fn test_copy_metadata() {
+    let mut source = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut source).unwrap();
+        let mut image = tiff.new_image::<colortype::Gray8>(2, 2).unwrap();
+        image.software("Scanner 1.0").unwrap();
+        image.artist("Jane Doe").unwrap();
+        image.dpi(300, 300);
+        image.model_pixel_scale(&[0.5, 0.5, 0.0]).unwrap();
+        image.geo_key_directory(&[1, 1, 0, 0]).unwrap();
+        image
+            .encoder()
+            .write_sub_directory(Tag::ExifDirectory, |exif| {
+                exif.write_tag(Tag::ExposureTime, Rational { n: 1, d: 250 })?;
+                exif.write_tag(Tag::DateTimeOriginal, "2021:06:01 12:30:00")
+            })
+            .unwrap();
+        image.write_data(&[1, 2, 3, 4]).unwrap();
+    }
+
+    source.seek(SeekFrom::Start(0)).unwrap();
+    let mut source = Decoder::new(source).unwrap();
+    let mut copy = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut copy).unwrap();
+        let mut image = tiff.new_image::<colortype::RGB8>(1, 1).unwrap();
+        image
+            .encoder()
+            .copy_metadata_filtered(&mut source, |tag| tag != Tag::Artist)
+            .unwrap();
+        image.write_data(&[10, 20, 30]).unwrap();
+    }
+
+    copy.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut copy).unwrap();
+    // The image structure is not copied.
+    assert_eq!(decoder.dimensions().unwrap(), (1, 1));
+    assert_eq!(decoder.colortype().unwrap(), ColorType::RGB(8));
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(data) => assert_eq!(data, [10, 20, 30]),
+        _ => panic!("Wrong data type"),
+    }
+
+    assert!(decoder.find_tag(Tag::Artist).unwrap().is_none());
+    for &tag in &[
+        Tag::Software,
+        Tag::XResolution,
+        Tag::ResolutionUnit,
+        Tag::ModelPixelScaleTag,
+        Tag::GeoKeyDirectoryTag,
+    ] {
+        assert_eq!(
+            decoder.get_tag(tag).unwrap(),
+            source.get_tag(tag).unwrap(),
+            "{:?}",
+            tag
+        );
+    }
+
+    let exif_offset = decoder.get_tag_u64(Tag::ExifDirectory).unwrap();
+    assert_eq!(
+        sub_directory_tags(&mut decoder, exif_offset),
+        [Tag::ExposureTime.to_u16(), Tag::DateTimeOriginal.to_u16()]
+    );
+}
+
+#[test]
+fn test_copy_metadata_foreign_files() {
+    // A big-endian file and a file with an EXIF directory.
+    for file in &["rgb-3c-16b.tiff", "predictor-3-gray-f32.tif"] {
+        let path = PathBuf::from(TEST_IMAGE_DIR).join(file);
+        let mut source = Decoder::new(File::open(path).unwrap()).unwrap();
+
+        let mut copy = Cursor::new(Vec::new());
+        {
+            let mut tiff = TiffEncoder::new(&mut copy).unwrap();
+            let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+            image.encoder().copy_metadata(&mut source).unwrap();
+            image.write_data(&[0]).unwrap();
+        }
+
+        copy.seek(SeekFrom::Start(0)).unwrap();
+        let mut decoder = Decoder::new(&mut copy).unwrap();
+        assert_eq!(
+            decoder.get_tag(Tag::Software).unwrap(),
+            source.get_tag(Tag::Software).unwrap()
+        );
+        if let Some(resolution) = source.find_tag(Tag::XResolution).unwrap() {
+            assert_eq!(decoder.get_tag(Tag::XResolution).unwrap(), resolution);
+        }
+        if let Some(exif_offset) = source.find_tag_unsigned::<u64>(Tag::ExifDirectory).unwrap() {
+            let copied_offset = decoder.get_tag_u64(Tag::ExifDirectory).unwrap();
+            assert_eq!(
+                sub_directory_tags(&mut decoder, copied_offset),
+                sub_directory_tags(&mut source, exif_offset)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_xmp() {
+    let packet = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmp:Rating="4"/>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#;
+
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.xmp(packet).unwrap();
+        image.write_data(&[0]).unwrap();
+
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image
+            .encoder()
+            .write_tag(Tag::Xmp, tiff::encoder::Undefined(packet.as_bytes()))
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+
+        let image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.write_data(&[0]).unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut file).unwrap();
+    assert_eq!(decoder.get_tag_u8_vec(Tag::Xmp).unwrap(), packet.as_bytes());
+    assert_eq!(decoder.xmp().unwrap().unwrap(), packet.as_bytes());
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.xmp().unwrap().unwrap(), packet.as_bytes());
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.xmp().unwrap(), None);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_metadata() {
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        let mut image = tiff.new_image::<colortype::Gray8>(1, 1).unwrap();
+        image.date_time("2021:03:14 15:09:26").unwrap();
+        image.xmp("<x:xmpmeta/>").unwrap();
+        image
+            .encoder()
+            .write_sub_directory(Tag::GpsDirectory, |gps| {
+                gps.write_tag(Tag::Unknown(5), 0u8)?;
+                gps.write_tag(Tag::Unknown(6), Rational { n: 12, d: 1 })
+            })
+            .unwrap();
+        image.write_data(&[0]).unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut file).unwrap();
+    let tags = decoder.tags().unwrap();
+    let json = serde_json::to_string(&tags).unwrap();
+    let decoded: Vec<(Tag, ifd::Value)> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, tags);
+
+    let date_time = decoder.date_time().unwrap().unwrap();
+    let json = serde_json::to_string(&date_time).unwrap();
+    assert_eq!(
+        json,
+        r#"{"year":2021,"month":3,"day":14,"hour":15,"minute":9,"second":26}"#
+    );
+
+    let gps = decoder.gps_info().unwrap().unwrap();
+    let json = serde_json::to_string(&gps).unwrap();
+    assert_eq!(
+        serde_json::from_str::<tiff::decoder::GpsInfo>(&json).unwrap(),
+        gps
+    );
+}
+
+#[test]
+fn test_orientation() {
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        let mut image = tiff.new_image::<colortype::Gray8>(2, 1).unwrap();
+        image.orientation(Orientation::RightTop).unwrap();
+        image.write_data(&[1, 2]).unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut file).unwrap();
+    let orientation = decoder.get_tag_unsigned::<u16>(Tag::Orientation).unwrap();
+    assert_eq!(
+        Orientation::from_u16(orientation),
+        Some(Orientation::RightTop)
+    );
+}
+
+#[test]
+fn test_progress() {
+    let data: Vec<u8> = (0..60 * 60).map(|i| i as u8).collect();
+    let mut reports = Vec::new();
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        let mut image = tiff
+            .image_builder::<colortype::Gray8>(60, 60)
+            .compression(compression::Lzw)
+            .tile_size(32, 32)
+            .build()
+            .unwrap();
+        image.progress(|progress| {
+            reports.push(*progress);
+            true
+        });
+        image.write_data(&data).unwrap();
+    }
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut decoder = Decoder::new(&mut file).unwrap();
+    let counts = decoder.get_tag_u64_vec(Tag::TileByteCounts).unwrap();
+    assert_eq!(reports.len(), 4);
+    for (index, progress) in reports.iter().enumerate() {
+        assert_eq!(
+            *progress,
+            Progress {
+                chunks_written: index as u64 + 1,
+                total_chunks: 4,
+                bytes_written: counts[..=index].iter().sum(),
+            }
+        );
+    }
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(decoded) => assert_eq!(decoded, data),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+#[test]
+fn test_progress_cancel() {
+    let data = vec![0u16; 100 * 100];
+    let mut file = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut file).unwrap();
+        let mut image = tiff.new_image::<colortype::Gray16>(100, 100).unwrap();
+        image.rows_per_strip(10).unwrap();
+        image.progress(|progress| progress.chunks_written < 3);
+        let err = image.write_data(&data).unwrap_err();
+        assert!(err.to_string().contains("cancelled"));
+    }
+
+    // Only the written strips remain, no directory points to them.
+    let written = file.into_inner();
+    assert_eq!(written.len(), 8 + 3 * 100 * 10 * 2);
+    assert!(Decoder::new(Cursor::new(written)).is_err());
+}
+
+#[test]
+fn test_verify() {
+    let gray: Vec<u16> = (0..50 * 40).map(|i| (i * 37 % 65536) as u16).collect();
+    let rgb: Vec<u8> = (0..30 * 20 * 3).map(|i| (i * 7 % 256) as u8).collect();
+
+    let mut file = Cursor::new(Vec::new());
+    let mut tiff = TiffEncoder::new(&mut file).unwrap();
+    tiff.write_image::<colortype::Gray16>(50, 40, &gray)
+        .unwrap();
+    tiff.image_builder::<colortype::RGB8>(30, 20)
+        .rows_per_strip(7)
+        .build()
+        .unwrap()
+        .write_data(&rgb)
+        .unwrap();
+    tiff.image_builder::<colortype::Gray16>(50, 40)
+        .compression(compression::Lzw)
+        .tile_size(16, 16)
+        .build()
+        .unwrap()
+        .write_data(&gray)
+        .unwrap();
+    tiff.verify().unwrap();
+    // Encoding can continue after the check.
+    tiff.write_image::<colortype::RGB8>(30, 20, &rgb).unwrap();
+    tiff.verify().unwrap();
+
+    let mut file = Cursor::new(Vec::new());
+    let mut bigtiff = TiffEncoder::new_big(&mut file).unwrap();
+    bigtiff
+        .write_image::<colortype::Gray16>(50, 40, &gray)
+        .unwrap();
+    bigtiff.verify().unwrap();
+}
+
+#[test]
+fn test_verify_corrupt() {
+    let mut file = Cursor::new(Vec::new());
+    let mut tiff = TiffEncoder::new(&mut file).unwrap();
+    tiff.write_image::<colortype::Gray8>(4, 4, &[0; 16])
+        .unwrap();
+    {
+        let mut directory = tiff.new_directory().unwrap();
+        directory.write_tag(Tag::ImageWidth, 4u32).unwrap();
+        directory.write_tag(Tag::ImageLength, 4u32).unwrap();
+        directory.write_tag(Tag::BitsPerSample, 8u16).unwrap();
+        directory
+            .write_tag(Tag::PhotometricInterpretation, 1u16)
+            .unwrap();
+        directory.write_tag(Tag::RowsPerStrip, 4u32).unwrap();
+        directory
+            .write_tag(Tag::StripOffsets, 1_000_000u32)
+            .unwrap();
+        directory.write_tag(Tag::StripByteCounts, 16u32).unwrap();
+    }
+    let err = tiff.verify().unwrap_err();
+    assert!(err.to_string().contains("Image 1"));
+    assert!(matches!(
+        err,
+        TiffError::FormatError(TiffFormatError::ChunkOutOfFile {
+            image: 1,
+            offset: 1_000_000,
+            length: 16,
+        })
+    ));
+}
+
+/// A writer that doesn't implement `Seek` and records the size of every write.
+struct Pipe {
+    data: Vec<u8>,
+    writes: Vec<usize>,
+}
+
+impl std::io::Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.data.extend_from_slice(buf);
+        self.writes.push(buf.len());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_stream_encoder() {
+    let gray: Vec<u16> = (0..50 * 40).map(|i| (i * 37 % 65536) as u16).collect();
+    let rgb: Vec<u8> = (0..30 * 20 * 3).map(|i| (i * 7 % 256) as u8).collect();
+
+    let mut seekable = Cursor::new(Vec::new());
+    {
+        let mut tiff = TiffEncoder::new(&mut seekable).unwrap();
+        tiff.write_image::<colortype::Gray16>(50, 40, &gray)
+            .unwrap();
+        tiff.image_builder::<colortype::RGB8>(30, 20)
+            .compression(compression::Lzw)
+            .tile_size(16, 16)
+            .build()
+            .unwrap()
+            .write_data(&rgb)
+            .unwrap();
+    }
+
+    let pipe = Pipe {
+        data: Vec::new(),
+        writes: Vec::new(),
+    };
+    let mut tiff = StreamEncoder::new(pipe).unwrap();
+    tiff.encoder()
+        .unwrap()
+        .write_image::<colortype::Gray16>(50, 40, &gray)
+        .unwrap();
+    tiff.encoder()
+        .unwrap()
+        .image_builder::<colortype::RGB8>(30, 20)
+        .compression(compression::Lzw)
+        .tile_size(16, 16)
+        .build()
+        .unwrap()
+        .write_data(&rgb)
+        .unwrap();
+    let pipe = tiff.finish().unwrap();
+
+    // The first image was passed on before the second one was written.
+    assert!(pipe.writes.len() > 2);
+    assert_eq!(pipe.data, seekable.into_inner());
+
+    let mut decoder = Decoder::new(Cursor::new(pipe.data)).unwrap();
+    match decoder.read_image().unwrap() {
+        DecodingResult::U16(data) => assert_eq!(data, gray),
+        _ => panic!("Wrong data type"),
+    }
+    decoder.next_image().unwrap();
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(data) => assert_eq!(data, rgb),
+        _ => panic!("Wrong data type"),
+    }
+}
+
+#[test]
+fn test_deterministic_output() {
+    fn encode() -> (Vec<u8>, Vec<u8>) {
+        let data: Vec<u16> = (0..100 * 70).map(|i| (i * 31 % 4099) as u16).collect();
+        let mut file = Cursor::new(Vec::new());
+        {
+            let mut tiff = TiffEncoder::new(&mut file).unwrap();
+            let mut image = tiff
+                .image_builder::<colortype::Gray16>(100, 70)
+                .compression(compression::Deflate::default())
+                .predictor(Predictor::Horizontal)
+                .tile_size(32, 32)
+                .build()
+                .unwrap();
+            image.software("image-tiff").unwrap();
+            image
+                .encoder()
+                .write_sub_directory(Tag::ExifDirectory, |exif| {
+                    exif.write_tag(Tag::ExposureTime, Rational { n: 1, d: 60 })
+                })
+                .unwrap();
+            image
+                .encoder()
+                .write_thumbnail::<colortype::Gray16>(10, 7, &data[..70])
+                .unwrap();
+            image.write_data(&data).unwrap();
+
+            tiff.write_image_with_overviews_and_compression::<colortype::Gray16, _>(
+                100,
+                70,
+                compression::Lzw,
+                &data,
+                &[2, 4],
+            )
+            .unwrap();
+        }
+
+        let mut cog_file = Cursor::new(Vec::new());
+        let mut cog = CogEncoder::new(&mut cog_file);
+        cog.tile_size(32, 32).unwrap();
+        cog.sparse(true);
+        cog.write_image_with_compression::<colortype::Gray16, _>(100, 70, compression::Lzw, &data)
+            .unwrap();
+        (file.into_inner(), cog_file.into_inner())
+    }
+
+    assert_eq!(encode(), encode());
+}
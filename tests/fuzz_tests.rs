@@ -1,9 +1,10 @@
 extern crate tiff;
 
-use tiff::decoder::Decoder;
+use tiff::decoder::{ChunkType, Decoder, DecodingMode, Limits};
 use tiff::TiffResult;
 
 use std::fs::File;
+use std::io::Read;
 
 fn test_directory<F: Fn(File) -> bool>(path: &str, f: F) {
     for entry in std::fs::read_dir(path).unwrap() {
@@ -18,6 +19,46 @@ fn decode_tiff(file: File) -> TiffResult<()> {
     Ok(())
 }
 
+/// Runs a file through every decoding path that takes data from the file, as the `decode_chunks`
+/// fuzz target does.
+fn decode_tiff_chunks(data: &[u8], mode: DecodingMode, recover_chunks: bool) {
+    let mut limits = Limits::default();
+    limits.decoding_buffer_size = 1_000_000;
+    limits.ifd_value_size = 1_000_000;
+    limits.intermediate_buffer_size = 1_000_000;
+
+    let mut decoder = match Decoder::new(std::io::Cursor::new(data)) {
+        Ok(decoder) => decoder
+            .with_limits(limits)
+            .with_mode(mode)
+            .with_chunk_recovery(recover_chunks),
+        Err(_) => return,
+    };
+
+    loop {
+        let _ = decoder.dimensions();
+        let _ = decoder.colortype();
+        let _ = decoder.tags();
+        let _ = decoder.resolution();
+        let _ = decoder.read_image();
+
+        let chunks = match decoder.get_chunk_type() {
+            ChunkType::Strip => decoder.strip_count(),
+            ChunkType::Tile => decoder.tile_count(),
+        };
+        if let Ok(chunks) = chunks {
+            for chunk in 0..chunks.min(64) {
+                let _ = decoder.read_chunk(chunk);
+                let _ = decoder.read_chunk_bytes(chunk);
+            }
+        }
+
+        if !decoder.more_images() || decoder.next_image().is_err() {
+            break;
+        }
+    }
+}
+
 #[test]
 fn oor_panic() {
     test_directory("./tests/fuzz_images/oor_panic", |file| {
@@ -49,3 +90,21 @@ fn divide_by_zero() {
         true
     });
 }
+
+#[test]
+fn panic_free() {
+    for dir in &[
+        "./tests/fuzz_images/panic_free",
+        "./tests/fuzz_images/oor_panic",
+        "./tests/fuzz_images/divide_by_zero",
+    ] {
+        test_directory(dir, |mut file| {
+            let mut data = Vec::new();
+            file.read_to_end(&mut data).unwrap();
+            decode_tiff_chunks(&data, DecodingMode::Lenient, false);
+            decode_tiff_chunks(&data, DecodingMode::Strict, false);
+            decode_tiff_chunks(&data, DecodingMode::Lenient, true);
+            true
+        });
+    }
+}